@@ -1,66 +1,166 @@
 use crate::common::*;
+use crate::session;
+use crate::stats::Stats;
 
+use anyhow::Context;
 use eframe::egui;
 use egui::{
     Color32, CornerRadius, Id, LayerId, Order, Pos2, Rect, Sense, Stroke, StrokeKind, Vec2,
 };
-
-const CARD_SIZE: Vec2 = Vec2::new(90.0, 130.0);
-const CARD_PADDING: f32 = 10.0;
-const TABLEAU_CARD_V_OFFSET: f32 = 25.0; // Vertical offset of cards in tableau pile
-const WASTE_CARD_H_OFFSET: f32 = 20.0; // Horizontal offset of cards in waste pile
-const AUTOPLAY_INTERVAL: f64 = 3.0; // Duration between autoplay moves
+use klondike_common::{
+    action::suggest_move,
+    board::{ScoreMode, greenfelt_seed},
+    render::write_gif,
+    talon::WasteFan,
+};
+use std::time::Duration;
+
+const CARD_FONT_SIZE: f32 = 20.0; // Size of the rank and suit on a card at scale 1
+const MOVE_DURATION: f64 = 0.2; // Duration of a move animation at normal speed
+const HISTORY_PREFIX: &str = "History:";
+const REDO_PREFIX: &str = "Redo:";
+const SEED_PREFIX: &str = "Seed:";
+const ELAPSED_PREFIX: &str = "Elapsed:";
+const PAR_MAX_STATES: u32 = 10_000_000; // Max states to explore when computing the par
+const HINT_DURATION: f64 = 1.5; // Duration of the hint highlight
+const DROP_SNAP_DISTANCE: f32 = 40.0; // Distance from a legal pile within which a drop snaps to it
+const GAME_FILE_NAME: &str = "klondike.txt"; // Default name of a saved game
+const REPLAY_FILE_NAME: &str = "klondike.gif"; // Default name of an exported replay
+const CASCADE_DURATION: f64 = 2.5; // Duration of the bounces of a card off the table after a win
+const CASCADE_BOUNCES: f32 = 3.5; // Times a card bounces on the bottom of the window after a win
+const CASCADE_INTERVAL: f64 = 0.15; // Time between two cards leaving the foundations after a win
+const AUTOSAVE_INTERVAL: f64 = 2.0; // Seconds between two saves of the game in progress
+const REPLAY_FRAME_DELAY: Duration = Duration::from_millis(500); // Time each move of an exported replay is shown
 
 pub struct KlondikeApp {
     init_board: Board,
     board: Board,
+    seed: Option<u32>, // Deal of the game, none for a board loaded from a file
+    seed_input: Option<String>, // Seed typed in the "New Game from Seed" dialog, while it is open
+    window_title: String, // Title last set on the window
     solution: Option<(Vec<SolutionMove>, usize, Option<Board>)>,
     foundation_rects: [Rect; 4],
     tableau_rects: [Rect; 7],
+    layout: Layout, // Sizes of the cards, fitted to the width of the window each frame
+    tableau_height: f32, // Height the tableau piles can take before they are compacted
     stock_rect: Rect,
     waste_rect: Rect,
     dragged_cards: Vec<Card>,
     drag_source: Option<PileId>,
     drag_offset: Vec2,
+    drag_pos: Pos2,
     animations: Vec<CardAnimation>,
     history: Vec<GameMove>,
     redo_stack: Vec<GameMove>,
     autofinish: Autofinish,
+    moving_safe_cards: bool, // Moving the safe cards to the foundations, one per animation
     hook_moved: bool,
+    moved_cards: Option<(PileId, usize)>, // Cards of the move in progress, highlighted once it completes
+    highlight: Option<Highlight>,
+    keyboard_focus: Option<KeyboardFocus>,
+    undo_repeat: KeyRepeat,
+    redo_repeat: KeyRepeat,
+    hint: Option<[Highlight; 2]>, // Source and destination of the suggested move
     score: u8,
+    vegas_balance: i32, // Vegas dollars won in the previous deals, carried in cumulative mode
     start_time: f64,
     end_time: Option<f64>,
+    accumulated_pause: f64, // Seconds the timer was paused before `paused_at`
+    paused_at: Option<f64>, // When the timer was paused, if it is
+    paused_by_focus: bool,  // Paused because the window lost the focus, resumed when it is back
     autoplay: bool,
     next_play_time: f64,
+    par: Par,
+    solving: Option<BackgroundSolve>, // Solve of the deal requested from the toolbar
+    win_dismissed: bool,
+    stuck: bool, // No move can make progress anymore, see `has_productive_moves`
+    error: Option<String>, // Failure of the last save or load, shown until dismissed
+    resume: Option<String>, // Game left unfinished by the last session, offered at startup
+    autosaved: Option<String>, // Game last saved to be resumed, none if there is nothing to resume
+    next_autosave_time: f64,
+    stats: Stats,
+    show_stats: bool,
+    settings: Settings,
 }
 
 impl eframe::App for KlondikeApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // --- Hotkey handling ---
-        if ctx.input_mut(|i| i.key_pressed(egui::Key::Z)) {
+        let ready = self.animations.is_empty();
+        // Keys typed in a text field, like the seed of a new game, are no hotkeys
+        let typing = ctx.wants_keyboard_input();
+        let key_state = |key| {
+            ctx.input(|i| {
+                (
+                    !typing && i.key_pressed(key),
+                    !typing && i.key_down(key),
+                    i.time,
+                )
+            })
+        };
+        let (pressed, down, now) = key_state(egui::Key::Z);
+        if self.undo_repeat.update(pressed, down, now, ready) {
             self.undo(ctx);
         }
-        if ctx.input_mut(|i| i.key_pressed(egui::Key::X)) {
+        let (pressed, down, now) = key_state(egui::Key::X);
+        if self.redo_repeat.update(pressed, down, now, ready) {
             self.redo(ctx);
         }
-        if ctx.input_mut(|i| i.key_pressed(egui::Key::N)) {
+        if !typing && ctx.input_mut(|i| i.key_pressed(egui::Key::N)) {
             self.renew();
         }
-        if ctx.input_mut(|i| i.key_pressed(egui::Key::G)) {
+        if !typing && ctx.input_mut(|i| i.key_pressed(egui::Key::G)) {
             self.replay();
         }
-        if ctx.input_mut(|i| i.key_pressed(egui::Key::P)) {
-            self.toggle_autoplay();
+        if !typing && ctx.input_mut(|i| i.key_pressed(egui::Key::P)) {
+            self.toggle_autoplay(now);
+        }
+        if !typing && ctx.input_mut(|i| i.key_pressed(egui::Key::H)) {
+            self.show_hint(ctx);
         }
 
         if self.start_time == 0.0 {
-            self.start_time = ctx.input(|i| i.time);
+            self.start_time = now;
+        }
+        let window_title = match self.seed {
+            Some(seed) => format!("Klondike Solitaire - Game #{seed}"),
+            None => "Klondike Solitaire".to_string(),
+        };
+        if window_title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(window_title.clone()));
+            self.window_title = window_title;
+        }
+        // Watching a solution in the background doesn't pause the timer
+        let focused = ctx.input(|i| i.focused);
+        if !focused && !self.autoplay && self.paused_at.is_none() {
+            self.pause_timer(now);
+            self.paused_by_focus = self.paused_at.is_some();
+        } else if focused && self.paused_by_focus {
+            self.resume_timer(now);
+        }
+
+        // Saved on close too, as the last save may be a few moves behind
+        let closing = ctx.input(|i| i.viewport().close_requested());
+        if self.resume.is_none() && (closing || now >= self.next_autosave_time) {
+            self.next_autosave_time = now + AUTOSAVE_INTERVAL;
+            self.autosave(now, closing);
         }
 
         let pointer = ctx.input(|i| i.pointer.clone());
         let is_pointer_down = pointer.any_down();
         let is_pointer_released = pointer.any_released();
 
+        // A new press skips pending snap back animations so it can start a new drag right away
+        if pointer.any_pressed() {
+            self.finish_snap_back_animations();
+            self.animations
+                .retain(|anim| anim.kind != AnimationKind::Cascade);
+            self.keyboard_focus = None;
+        }
+        if !typing {
+            self.handle_keyboard_play(ctx);
+        }
+
         // If mouse is released, record drop position. We will handle it after UI rendering.
         let mut drop_pos = None;
         if is_pointer_released && !self.dragged_cards.is_empty() {
@@ -72,39 +172,75 @@ impl eframe::App for KlondikeApp {
             self.draw_toolbar(ui, ctx);
         });
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.style_mut().spacing.item_spacing = Vec2::splat(CARD_PADDING);
-
-            // Draw top area (stock, waste pile, foundation piles, control buttons)
-            ui.horizontal(|ui| {
-                self.draw_stock(ui);
-                self.draw_waste(ui);
-                ui.add_space(
-                    CARD_SIZE.x + CARD_PADDING
-                        - (self.board.draw_count.saturating_sub(1)) as f32 * WASTE_CARD_H_OFFSET,
-                );
-                for i in 0..4 {
-                    self.draw_foundation(ui, i);
+        let table_frame =
+            egui::Frame::central_panel(&ctx.style()).fill(self.settings.theme.theme().table_bg);
+        egui::CentralPanel::default()
+            .frame(table_frame)
+            .show(ctx, |ui| {
+                self.layout = Layout::fit_width(ui.available_width());
+                let Layout {
+                    card_size,
+                    padding,
+                    waste_h_offset,
+                    ..
+                } = self.layout;
+                ui.style_mut().spacing.item_spacing = Vec2::splat(padding);
+                // Registered before the cards, so it only gets the clicks they don't sense
+                let table = ui.interact(ui.max_rect(), Id::new("table"), Sense::click());
+                if table.secondary_clicked() && self.dragged_cards.is_empty() {
+                    self.undo(ctx);
                 }
-            });
 
-            ui.add_space(CARD_PADDING);
+                // Draw top area (stock, waste pile, foundation piles, control buttons)
+                let gap = card_size.x + padding
+                    - (self.board.draw_count.saturating_sub(1)) as f32 * waste_h_offset;
+                ui.horizontal(|ui| {
+                    if self.settings.left_handed {
+                        for i in (0..4).rev() {
+                            self.draw_foundation(ui, i);
+                        }
+                        ui.add_space(gap);
+                        self.draw_waste(ui);
+                        self.draw_stock(ui);
+                    } else {
+                        self.draw_stock(ui);
+                        self.draw_waste(ui);
+                        ui.add_space(gap);
+                        for i in 0..4 {
+                            self.draw_foundation(ui, i);
+                        }
+                    }
+                });
+
+                ui.add_space(padding);
+
+                // Draw tableau piles
+                ui.horizontal_top(|ui| {
+                    for column in 0..7 {
+                        match self.settings.left_handed {
+                            true => self.draw_tableau_pile(ui, 6 - column),
+                            false => self.draw_tableau_pile(ui, column),
+                        }
+                    }
+                });
 
-            // Draw tableau piles
-            ui.horizontal_top(|ui| {
-                for i in 0..7 {
-                    self.draw_tableau_pile(ui, i);
+                // If dragging, draw dragged cards on top layer
+                if !self.dragged_cards.is_empty()
+                    && let Some(drag_pos) = pointer.interact_pos()
+                {
+                    self.drag_pos = drag_pos + self.drag_offset;
+                    self.draw_dragged_cards(ctx, self.drag_pos);
                 }
             });
 
-            // If dragging, draw dragged cards on top layer
-            if !self.dragged_cards.is_empty()
-                && let Some(drag_pos) = pointer.interact_pos()
-            {
-                self.draw_dragged_cards(ctx, drag_pos + self.drag_offset);
-            }
-        });
+        if !self.dragged_cards.is_empty() {
+            self.paint_drop_targets(ctx);
+        }
 
+        self.paint_keyboard_focus(ctx);
+        if self.stuck {
+            self.show_stuck_banner(ctx);
+        }
         self.update_and_draw_animations(ctx);
 
         if !self.animations.is_empty() {
@@ -116,6 +252,28 @@ impl eframe::App for KlondikeApp {
             self.handle_moved(ctx);
         }
 
+        self.poll_solving();
+
+        if self.error.is_some() {
+            self.popup_error(ctx);
+        }
+
+        if self.resume.is_some() {
+            self.popup_resume(ctx);
+        }
+
+        if self.seed_input.is_some() {
+            self.popup_seed_input(ctx);
+        }
+
+        if self.show_stats {
+            self.popup_stats(ctx);
+        }
+
+        if self.score == 52 && !self.win_dismissed {
+            self.popup_win(ctx);
+        }
+
         if self.autoplay {
             self.handle_autoplay(ctx);
             ctx.request_repaint();
@@ -123,12 +281,16 @@ impl eframe::App for KlondikeApp {
         }
 
         if let Some(pos) = drop_pos {
-            self.handle_drop(pos);
+            self.handle_drop(ctx, pos);
         }
 
         // If mouse is not pressed, ensure no cards are being dragged
         if !is_pointer_down && !self.dragged_cards.is_empty() {
-            self.return_dragged_cards();
+            self.return_dragged_cards(ctx);
+        }
+
+        if self.moving_safe_cards && self.dragged_cards.is_empty() {
+            self.perform_safe_move_step(ctx);
         }
 
         self.handle_autofinish(ctx);
@@ -139,18 +301,28 @@ impl eframe::App for KlondikeApp {
 
 impl KlondikeApp {
     pub fn new(board: Board) -> Self {
+        Self::with_settings(board, Settings::default())
+    }
+
+    pub fn with_settings(board: Board, settings: Settings) -> Self {
         Self {
             init_board: board.clone(),
             board,
+            seed: None,
+            seed_input: None,
+            window_title: String::new(),
             solution: None,
             foundation_rects: [Rect::ZERO; 4],
             tableau_rects: [Rect::ZERO; 7],
+            layout: Layout::default(),
+            tableau_height: f32::INFINITY,
             stock_rect: Rect::ZERO,
             waste_rect: Rect::ZERO,
 
             dragged_cards: Vec::new(),
             drag_source: None,
             drag_offset: Vec2::ZERO,
+            drag_pos: Pos2::ZERO,
 
             animations: Vec::new(),
 
@@ -158,13 +330,37 @@ impl KlondikeApp {
             redo_stack: Vec::new(),
 
             autofinish: Autofinish::Idle,
+            moving_safe_cards: false,
             hook_moved: false,
+            moved_cards: None,
+            highlight: None,
+            keyboard_focus: None,
+            undo_repeat: KeyRepeat::default(),
+            redo_repeat: KeyRepeat::default(),
+            hint: None,
             score: 0,
+            vegas_balance: 0,
             start_time: 0.0,
             end_time: None,
+            accumulated_pause: 0.0,
+            paused_at: None,
+            paused_by_focus: false,
 
             autoplay: false,
             next_play_time: 0.0,
+
+            par: Par::Unknown,
+            solving: None,
+            win_dismissed: false,
+            stuck: false,
+            error: None,
+            resume: None,
+            autosaved: None,
+            next_autosave_time: 0.0,
+            stats: Stats::default(),
+            show_stats: false,
+
+            settings,
         }
     }
 
@@ -174,54 +370,367 @@ impl KlondikeApp {
         self.autoplay = true;
     }
 
+    /// Bound the solve of the deal requested from the toolbar
+    pub fn set_solve_limits(&mut self, max_states: u32, timeout: Option<Duration>) {
+        self.settings.max_states = max_states;
+        self.settings.timeout = timeout;
+    }
+
+    /// Limit the times the waste can be recycled into the stock, like `--max-redeals` of the solver
+    pub fn set_redeal_limit(&mut self, limit: Option<usize>) {
+        self.settings.redeal_limit = limit;
+    }
+
+    /// Whether the waste can be recycled again, within the redeal limit
+    fn can_redeal(&self) -> bool {
+        let redeals = self
+            .history
+            .iter()
+            .filter(|m| (m.source, m.destination) == (PileId::Waste, PileId::Stock))
+            .count();
+        self.settings
+            .redeal_limit
+            .is_none_or(|limit| redeals < limit)
+    }
+
+    /// Solve the deal on a background thread, see [`Self::poll_solving`]
+    fn start_solving(&mut self) {
+        match BackgroundSolve::spawn(
+            &self.init_board,
+            self.settings.max_states,
+            self.settings.timeout,
+            self.settings.redeal_limit,
+        ) {
+            Ok(worker) => self.solving = Some(worker),
+            Err(err) => self.error = Some(format!("{err:#}")),
+        }
+    }
+
+    /// Replay the deal with the solution once the background solve finishes
+    fn poll_solving(&mut self) {
+        let Some(result) = self.solving.as_ref().and_then(|worker| worker.poll()) else {
+            return;
+        };
+        self.solving = None;
+        match result {
+            Ok(result) => {
+                let moves = result.actions.iter().map(solution_move).collect();
+                self.solution = Some((moves, 0, None));
+                self.replay();
+            }
+            Err(err) => self.error = Some(format!("{err:#}")),
+        }
+    }
+
+    /// Start the game dealt from `seed`, as numbered by greenfelt.net
+    pub fn from_seed(seed: u32, draw_count: usize) -> Self {
+        let mut app = Self::new(Board::new(seed, draw_count));
+        app.seed = Some(seed);
+        app
+    }
+
     /// Renew the game
     pub fn renew(&mut self) {
-        let board = Board::new(rand::random(), self.board.draw_count);
-        *self = Self::new(board);
+        self.deal(rand::random());
+    }
+
+    /// Replace the game with the deal of `seed`, keeping the draw count
+    fn deal(&mut self, seed: u32) {
+        self.abandon_game();
+        let board = Board::new(seed, self.board.draw_count);
+        let vegas_balance = self.vegas_total();
+        self.restart(board);
+        self.seed = Some(seed);
+        if self.settings.score_mode == ScoreMode::VegasCumulative {
+            self.vegas_balance = vegas_balance;
+        }
     }
 
     /// Replay the game
     pub fn replay(&mut self) {
+        self.abandon_game();
         let solution = self.solution.take();
-        *self = Self::new(self.init_board.clone());
+        let seed = self.seed;
+        self.restart(self.init_board.clone());
+        self.seed = seed;
         if let Some((moves, _, _)) = solution {
             self.solve(moves);
         }
     }
 
+    /// Replace the game with `board`, keeping the settings and the stats
+    fn restart(&mut self, board: Board) {
+        let stats = std::mem::take(&mut self.stats);
+        *self = Self::with_settings(board, self.settings.clone());
+        self.stats = stats;
+    }
+
+    /// Whether the game counts in the stats, games played from a solution
+    /// or with a draw count other than 1 and 3 don't
+    fn is_tracked(&self) -> bool {
+        self.solution.is_none()
+            && !self.history.is_empty()
+            && matches!(self.board.draw_count, 1 | 3)
+    }
+
+    /// Count an unfinished game as lost before it is replaced
+    fn abandon_game(&mut self) {
+        if self.end_time.is_none() && self.is_tracked() {
+            self.stats.record_loss(self.board.draw_count);
+            self.save_stats();
+        }
+    }
+
+    /// Load the stats kept across sessions, read once at startup
+    pub fn load_stats(&mut self) {
+        match Stats::load() {
+            Ok(stats) => self.stats = stats,
+            Err(err) => self.error = Some(format!("{err:#}")),
+        }
+    }
+
+    fn save_stats(&mut self) {
+        if let Err(err) = self.stats.save() {
+            self.error = Some(format!("{err:#}"));
+        }
+    }
+
+    /// Vegas dollars of this deal plus the balance carried from the previous deals
+    fn vegas_total(&self) -> i32 {
+        self.vegas_balance + ScoreMode::Vegas.score(self.score)
+    }
+
+    /// Score shown in the toolbar for the selected scoring mode
+    fn score_text(&self) -> String {
+        let dollars = |value: i32| match value {
+            ..0 => format!("-${}", -value),
+            _ => format!("${value}"),
+        };
+        match self.settings.score_mode {
+            ScoreMode::Standard => format!("Score: {}", self.score),
+            ScoreMode::Vegas => format!("Score: {}", dollars(ScoreMode::Vegas.score(self.score))),
+            ScoreMode::VegasCumulative => format!("Score: {}", dollars(self.vegas_total())),
+        }
+    }
+
+    /// Save the current board to a file picked by the user
+    fn save_game(&self) -> anyhow::Result<()> {
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name(GAME_FILE_NAME)
+            .save_file()
+        else {
+            return Ok(());
+        };
+        std::fs::write(&path, self.board.to_pretty_string())
+            .with_context(|| format!("Failed to save game to '{}'", path.display()))
+    }
+
+    /// Replace the game with a board loaded from a file picked by the user
+    fn load_game(&mut self) -> anyhow::Result<()> {
+        let Some(path) = rfd::FileDialog::new().pick_file() else {
+            return Ok(());
+        };
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        let board = Board::parse(&content).context("Failed to parse board")?;
+        self.abandon_game();
+        self.restart(board);
+        Ok(())
+    }
+
+    /// Render the initial board and the board after each move played to a GIF picked by the user
+    fn export_replay(&self) -> anyhow::Result<()> {
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name(REPLAY_FILE_NAME)
+            .add_filter("GIF", &["gif"])
+            .save_file()
+        else {
+            return Ok(());
+        };
+        let mut board = self.init_board.clone();
+        let mut fan = WasteFan::default();
+        let mut frames = vec![(board.to_solver_board()?, fan)];
+        for game_move in &self.history {
+            board.apply_move(game_move, false);
+            game_move.update_waste_fan(&mut fan);
+            frames.push((board.to_solver_board()?, fan));
+        }
+        write_gif(&path, &frames, REPLAY_FRAME_DELAY)
+    }
+
+    /// Offer to resume the game left unfinished by the last session
+    pub fn offer_resume(&mut self) {
+        match session::load() {
+            Ok(content) => self.resume = content,
+            Err(err) => self.error = Some(format!("{err:#}")),
+        }
+    }
+
+    /// Exported history of the game to resume on the next start, none once it is won
+    /// or before the first move
+    fn unfinished_history(&self) -> Option<String> {
+        (self.end_time.is_none() && !self.history.is_empty()).then(|| self.export_history())
+    }
+
+    /// The unfinished history followed by the seed and the timer, restored on resume
+    fn autosave_content(&self, history: &str, now: f64) -> String {
+        let mut output = history.to_string();
+        if let Some(seed) = self.seed {
+            output.push_str(&format!("\n{SEED_PREFIX} {seed}"));
+        }
+        output.push_str(&format!("\n{ELAPSED_PREFIX} {:.1}", self.elapsed(now)));
+        output
+    }
+
+    /// Save the game in progress when it changed since the last save, and on close to
+    /// keep its timer up to date
+    fn autosave(&mut self, now: f64, closing: bool) {
+        let history = self.unfinished_history();
+        if history == self.autosaved && !(closing && history.is_some()) {
+            return;
+        }
+        let result = match &history {
+            Some(history) => session::save(&self.autosave_content(history, now)),
+            None => session::clear(),
+        };
+        if let Err(err) = result {
+            self.error = Some(format!("{err:#}"));
+        }
+        self.autosaved = history;
+    }
+
+    /// Import an autosaved game, restoring its seed and its timer as of `now`
+    fn import_session(content: &str, now: f64) -> anyhow::Result<Self> {
+        let mut app = Self::import_history(content)?;
+        let find_value = |prefix: &str| {
+            content
+                .lines()
+                .find_map(|line| line.trim().strip_prefix(prefix))
+                .map(str::trim)
+        };
+        if let Some(seed) = find_value(SEED_PREFIX) {
+            app.seed = Some(seed.parse().context("Invalid seed")?);
+        }
+        if let Some(elapsed) = find_value(ELAPSED_PREFIX) {
+            let elapsed: f64 = elapsed.parse().context("Invalid elapsed time")?;
+            app.start_time = now - elapsed;
+        }
+        app.autosaved = app.unfinished_history();
+        Ok(app)
+    }
+
+    /// Export the initial board and the moves played, including the undone moves that can be redone
+    pub fn export_history(&self) -> String {
+        let format_moves = |moves: &mut dyn Iterator<Item = &GameMove>| {
+            moves.map(|m| m.to_token()).collect::<Vec<_>>().join(" ")
+        };
+        let mut output = self.init_board.to_pretty_string();
+        output.push_str(&format!(
+            "\n{HISTORY_PREFIX} {}",
+            format_moves(&mut self.history.iter())
+        ));
+        if !self.redo_stack.is_empty() {
+            output.push_str(&format!(
+                "\n{REDO_PREFIX} {}",
+                format_moves(&mut self.redo_stack.iter().rev())
+            ));
+        }
+        output
+    }
+
+    /// Check whether the content is an exported history
+    pub fn is_history(content: &str) -> bool {
+        content
+            .lines()
+            .any(|line| line.trim_start().starts_with(HISTORY_PREFIX))
+    }
+
+    /// Rebuild the game by replaying an exported history
+    pub fn import_history(content: &str) -> anyhow::Result<Self> {
+        let board = Board::parse(content).context("Failed to parse board")?;
+        let mut app = Self::new(board);
+        let find_moves = |prefix: &str| -> anyhow::Result<Vec<SolutionMove>> {
+            match content
+                .lines()
+                .find_map(|line| line.trim().strip_prefix(prefix))
+            {
+                Some(moves) => parse_moves(moves).context("Failed to parse moves"),
+                None => Ok(vec![]),
+            }
+        };
+        let history = find_moves(HISTORY_PREFIX)?;
+        let redo = find_moves(REDO_PREFIX)?;
+
+        for (i, mov) in history.iter().chain(redo.iter()).enumerate() {
+            if !app.is_legal_move(*mov) {
+                let (source, destination, count) = *mov;
+                let game_move = GameMove {
+                    source,
+                    destination,
+                    count,
+                    source_flip: false,
+                };
+                anyhow::bail!("Illegal move {} at step {}", game_move.to_token(), i + 1);
+            }
+            let game_move = app.build_solution_move(*mov);
+            app.apply_move_instant(&game_move, false);
+            app.history.push(game_move);
+        }
+        for _ in 0..redo.len() {
+            if let Some(game_move) = app.history.pop() {
+                app.apply_move_instant(&game_move, true);
+                app.redo_stack.push(game_move);
+            }
+        }
+        app.score = app.board.score();
+        Ok(app)
+    }
+
     /// Draw a card in the specified rectangle
-    fn paint_card(painter: &egui::Painter, rect: Rect, card: &Card) {
+    fn paint_card(&self, painter: &egui::Painter, rect: Rect, card: &Card) {
+        let theme = self.settings.theme.theme();
         let bg_color = if card.face_up {
-            Color32::from_gray(248)
+            theme.face_color
         } else {
-            Color32::from_rgb(0, 128, 128)
+            theme.card_back
         };
         painter.rect_filled(rect, CornerRadius::same(5), bg_color);
         painter.rect_stroke(
             rect,
             CornerRadius::same(5),
-            Stroke::new(1.0, Color32::from_gray(100)),
+            Stroke::new(1.0, theme.outline),
             StrokeKind::Inside,
         );
 
         if card.face_up {
-            let text_color = card.color();
+            let text_color = match card.is_red() {
+                true => theme.red_suit,
+                false => theme.black_suit,
+            };
+            let bold = theme.bold_red_rank && card.is_red();
             let (rank_symbol, suit_symbol) = card.symbols();
             let rank_symbol = if rank_symbol == 'T' {
                 "10".to_string()
             } else {
                 rank_symbol.to_string()
             };
-            let font_id = egui::FontId::proportional(20.0);
-            let padding = Vec2::new(3.0, 3.0);
+            let font_id = egui::FontId::proportional(CARD_FONT_SIZE * self.layout.scale);
+            let padding = Vec2::splat(3.0 * self.layout.scale);
 
-            painter.text(
-                rect.min + padding,
-                egui::Align2::LEFT_TOP,
-                rank_symbol.clone(),
-                font_id.clone(),
-                text_color,
-            );
+            // egui has no bold font by default, so bold ranks are painted twice, slightly apart
+            let rank_offsets: &[Vec2] = match bold {
+                true => &[Vec2::ZERO, Vec2::new(1.0, 0.0)],
+                false => &[Vec2::ZERO],
+            };
+            for &offset in rank_offsets {
+                painter.text(
+                    rect.min + padding + offset,
+                    egui::Align2::LEFT_TOP,
+                    rank_symbol.clone(),
+                    font_id.clone(),
+                    text_color,
+                );
+            }
             painter.text(
                 Pos2::new(rect.max.x - padding.x, rect.min.y + padding.y),
                 egui::Align2::RIGHT_TOP,
@@ -236,63 +745,127 @@ impl KlondikeApp {
                 font_id.clone(),
                 text_color,
             );
-            painter.text(
-                rect.max - padding,
-                egui::Align2::RIGHT_BOTTOM,
-                rank_symbol,
-                font_id,
-                text_color,
+            for &offset in rank_offsets {
+                painter.text(
+                    rect.max - padding - offset,
+                    egui::Align2::RIGHT_BOTTOM,
+                    rank_symbol.clone(),
+                    font_id.clone(),
+                    text_color,
+                );
+            }
+        }
+    }
+
+    /// Draw the fading highlight around a card that just moved or is part of a hint
+    fn paint_highlight(
+        &self,
+        painter: &egui::Painter,
+        rect: Rect,
+        pile: PileId,
+        card_idx: usize,
+        pile_len: usize,
+    ) {
+        let now = painter.ctx().input(|i| i.time);
+        let moved = self.highlight.iter().map(|highlight| {
+            (
+                highlight,
+                self.settings.highlight_duration,
+                Color32::from_rgb(255, 200, 0),
+            )
+        });
+        let hint = self
+            .hint
+            .iter()
+            .flatten()
+            .map(|highlight| (highlight, HINT_DURATION, Color32::from_rgb(0, 160, 255)));
+        for (highlight, duration, color) in moved.chain(hint) {
+            if highlight.pile != pile || card_idx + highlight.count < pile_len {
+                continue;
+            }
+            let progress = (now - highlight.start_time) / duration;
+            if !(0.0..1.0).contains(&progress) {
+                continue;
+            }
+            painter.rect_stroke(
+                rect,
+                CornerRadius::same(5),
+                Stroke::new(3.0, color.gamma_multiply(1.0 - progress as f32)),
+                StrokeKind::Inside,
             );
         }
     }
 
     /// Draw an empty pile placeholder in the specified rectangle
-    fn paint_empty_pile(painter: &egui::Painter, rect: Rect) {
+    fn paint_empty_pile(&self, painter: &egui::Painter, rect: Rect) {
         painter.rect_stroke(
             rect,
             CornerRadius::same(5),
-            Stroke::new(1.0, Color32::from_gray(100)),
+            Stroke::new(1.0, self.settings.theme.theme().outline),
             StrokeKind::Inside,
         );
     }
 
     /// Draw stock pile
     fn draw_stock(&mut self, ui: &mut egui::Ui) {
-        let (rect, response) = ui.allocate_exact_size(CARD_SIZE, Sense::click());
+        let (rect, response) = ui.allocate_exact_size(self.layout.card_size, Sense::click());
         self.stock_rect = rect;
 
         if response.clicked() && self.animations.is_empty() {
-            if self.board.stock.is_empty() {
-                if !self.board.waste.is_empty() {
-                    self.apply_and_record_move(
-                        ui.ctx(),
-                        self.build_game_move(PileId::Waste, PileId::Stock, self.board.waste.len()),
-                    );
-                }
-            } else {
-                let draw_count = self.board.draw_count.min(self.board.stock.len());
-                if draw_count > 0 {
-                    self.apply_and_record_move(
-                        ui.ctx(),
-                        self.build_game_move(PileId::Stock, PileId::Waste, draw_count),
-                    );
-                }
-            }
+            self.draw_from_stock(ui.ctx());
         }
 
         let painter = ui.painter_at(rect);
         if self.board.stock.is_empty() {
-            Self::paint_empty_pile(&painter, rect);
+            self.paint_empty_pile(&painter, rect);
+            if !self.board.waste.is_empty() && !self.can_redeal() {
+                painter.text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    "✖",
+                    egui::FontId::proportional(32.0 * self.layout.scale),
+                    self.settings.theme.theme().outline,
+                );
+                response.on_hover_text("No redeals left");
+            }
+            self.paint_highlight(&painter, rect, PileId::Stock, 0, 0);
+        } else {
+            self.paint_card(&painter, rect, &Card::new_with_id(0));
+            let stock_len = self.board.stock.len();
+            self.paint_highlight(&painter, rect, PileId::Stock, stock_len - 1, stock_len);
+        }
+    }
+
+    /// Draw cards from the stock, or recycle the waste once the stock is empty
+    fn draw_from_stock(&mut self, ctx: &egui::Context) {
+        if self.board.stock.is_empty() {
+            if !self.board.waste.is_empty() && self.can_redeal() {
+                self.apply_and_record_move(
+                    ctx,
+                    self.build_game_move(PileId::Waste, PileId::Stock, self.board.waste.len()),
+                );
+            }
         } else {
-            Self::paint_card(&painter, rect, &Card::new_with_id(0));
+            let draw_count = self.board.draw_count.min(self.board.stock.len());
+            if draw_count > 0 {
+                self.apply_and_record_move(
+                    ctx,
+                    self.build_game_move(PileId::Stock, PileId::Waste, draw_count),
+                );
+            }
         }
     }
 
     /// Draw waste pile
     fn draw_waste(&mut self, ui: &mut egui::Ui) {
+        let Layout {
+            card_size,
+            waste_h_offset,
+            ..
+        } = self.layout;
         let waste_width =
-            CARD_SIZE.x + (self.board.draw_count.saturating_sub(1)) as f32 * WASTE_CARD_H_OFFSET;
-        let (_, rect) = ui.allocate_space(Vec2::new(waste_width, CARD_SIZE.y));
+            card_size.x + (self.board.draw_count.saturating_sub(1)) as f32 * waste_h_offset;
+        let (_, rect) = ui.allocate_space(Vec2::new(waste_width, card_size.y));
         self.waste_rect = rect;
 
         if self.board.waste.is_empty() {
@@ -300,20 +873,19 @@ impl KlondikeApp {
         }
 
         let waste_len = self.board.waste.len();
-        let draw_count = self.board.draw_count.min(waste_len);
-        let start_idx = waste_len - draw_count;
+        let visible_count = self.waste_shown_count();
+        let start_idx = waste_len - visible_count;
 
         let mut top_card_rect = Rect::ZERO;
 
-        for i in 0..draw_count {
+        for i in 0..visible_count {
             let card_idx = start_idx + i;
             let card = self.board.waste[card_idx];
             let card_pos = self.get_card_pos(PileId::Waste, Some(i));
-            let card_rect = Rect::from_min_size(card_pos, CARD_SIZE);
-            Self::paint_card(ui.painter(), card_rect, &card);
-            if i == draw_count - 1 {
-                top_card_rect = card_rect;
-            }
+            let card_rect = Rect::from_min_size(card_pos, self.layout.card_size);
+            self.paint_card(ui.painter(), card_rect, &card);
+            self.paint_highlight(ui.painter(), card_rect, PileId::Waste, card_idx, waste_len);
+            top_card_rect = card_rect;
         }
 
         let top_card_idx = waste_len - 1;
@@ -323,12 +895,7 @@ impl KlondikeApp {
             Sense::click_and_drag(),
         );
 
-        if top_card_response.clicked() {
-            let source = PileId::Waste;
-            if !self.try_auto_move_to_foundation(ui.ctx(), source, top_card_idx) {
-                self.try_auto_move_to_tableau(ui.ctx(), source, top_card_idx);
-            }
-        }
+        self.handle_card_click(ui.ctx(), PileId::Waste, top_card_idx, &top_card_response);
 
         if top_card_response.drag_started()
             && self.dragged_cards.is_empty()
@@ -340,12 +907,22 @@ impl KlondikeApp {
 
     /// Draw foundation pile
     fn draw_foundation(&mut self, ui: &mut egui::Ui, i: usize) {
-        let (rect, response) = ui.allocate_exact_size(CARD_SIZE, Sense::drag());
+        let (rect, response) = ui.allocate_exact_size(self.layout.card_size, Sense::drag());
         self.foundation_rects[i] = rect;
         let painter = ui.painter_at(rect);
 
-        if let Some(&card) = self.board.foundations[i].last() {
-            Self::paint_card(&painter, rect, &card);
+        let now = ui.ctx().input(|input| input.time);
+        let foundation_len = self.foundation_shown_len(i, now);
+        if foundation_len > 0 {
+            let card = self.board.foundations[i][foundation_len - 1];
+            self.paint_card(&painter, rect, &card);
+            self.paint_highlight(
+                &painter,
+                rect,
+                PileId::Foundation(i),
+                foundation_len - 1,
+                foundation_len,
+            );
 
             if response.drag_started()
                 && self.dragged_cards.is_empty()
@@ -358,29 +935,31 @@ impl KlondikeApp {
                 );
             }
         } else {
-            Self::paint_empty_pile(&painter, rect);
+            self.paint_empty_pile(&painter, rect);
+            self.paint_highlight(&painter, rect, PileId::Foundation(i), 0, 0);
         }
     }
 
     /// Draw tableau pile
+    ///
+    /// Right-clicking the top card only ever moves it to a foundation, never to a tableau.
     fn draw_tableau_pile(&mut self, ui: &mut egui::Ui, i: usize) {
         let pile = self.board.tableaus[i].clone();
 
-        let pile_height = if pile.is_empty() {
-            CARD_SIZE.y
-        } else {
-            CARD_SIZE.y + (pile.len() - 1) as f32 * TABLEAU_CARD_V_OFFSET
-        };
+        self.tableau_height = ui.available_height();
+        let pile_height =
+            self.layout.card_size.y + self.tableau_card_y(i, pile.len().saturating_sub(1));
 
-        let (_, pile_rect) = ui.allocate_space(Vec2::new(CARD_SIZE.x, pile_height));
+        let (_, pile_rect) = ui.allocate_space(Vec2::new(self.layout.card_size.x, pile_height));
         self.tableau_rects[i] = pile_rect;
 
         if pile.is_empty() {
-            Self::paint_empty_pile(ui.painter(), pile_rect);
+            self.paint_empty_pile(ui.painter(), pile_rect);
+            self.paint_highlight(ui.painter(), pile_rect, PileId::Tableau(i), 0, 0);
         } else {
             for (j, card) in pile.iter().enumerate() {
                 let card_pos = self.get_card_pos(PileId::Tableau(i), Some(j));
-                let card_rect = Rect::from_min_size(card_pos, CARD_SIZE);
+                let card_rect = Rect::from_min_size(card_pos, self.layout.card_size);
 
                 if card.face_up {
                     let response = ui.interact(
@@ -389,12 +968,9 @@ impl KlondikeApp {
                         Sense::click_and_drag(),
                     );
 
-                    if response.clicked() {
-                        let source = PileId::Tableau(i);
-                        let ctx = ui.ctx();
-                        if !self.try_auto_move_to_foundation(ctx, source, j) {
-                            self.try_auto_move_to_tableau(ctx, source, j);
-                        }
+                    self.handle_card_click(ui.ctx(), PileId::Tableau(i), j, &response);
+                    if response.secondary_clicked() && j == pile.len() - 1 {
+                        self.try_auto_move_to_foundation(ui.ctx(), PileId::Tableau(i), j);
                     }
 
                     if response.drag_started()
@@ -404,7 +980,8 @@ impl KlondikeApp {
                         self.start_drag(PileId::Tableau(i), j, &response);
                     }
                 }
-                Self::paint_card(ui.painter(), card_rect, card);
+                self.paint_card(ui.painter(), card_rect, card);
+                self.paint_highlight(ui.painter(), card_rect, PileId::Tableau(i), j, pile.len());
             }
         }
     }
@@ -415,9 +992,28 @@ impl KlondikeApp {
         let painter = ctx.layer_painter(layer_id);
 
         for (i, card) in self.dragged_cards.iter().enumerate() {
-            let card_pos = pos + Vec2::new(0.0, i as f32 * TABLEAU_CARD_V_OFFSET);
-            let card_rect = Rect::from_min_size(card_pos, CARD_SIZE);
-            Self::paint_card(&painter, card_rect, card);
+            let card_pos = pos + Vec2::new(0.0, i as f32 * self.layout.tableau_v_offset);
+            let card_rect = Rect::from_min_size(card_pos, self.layout.card_size);
+            self.paint_card(&painter, card_rect, card);
+        }
+    }
+
+    /// Outline the piles that accept the dragged cards, under the dragged cards
+    fn paint_drop_targets(&self, ctx: &egui::Context) {
+        let painter = ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("drop_targets")));
+        let color = self.settings.theme.theme().drop_target;
+        for pile in self.drop_targets() {
+            let len = self.board.pile(pile).len();
+            let rect = Rect::from_min_size(
+                self.get_card_pos(pile, Some(len.saturating_sub(1))),
+                self.layout.card_size,
+            );
+            painter.rect_stroke(
+                rect,
+                CornerRadius::same(5),
+                Stroke::new(2.0, color),
+                StrokeKind::Inside,
+            );
         }
     }
 
@@ -432,6 +1028,10 @@ impl KlondikeApp {
                     self.renew();
                     ui.close();
                 }
+                if ui.button("New Game from Seed…").clicked() {
+                    self.seed_input = Some(String::new());
+                    ui.close();
+                }
                 if ui
                     .add(egui::Button::new("Replay Game").shortcut_text("G"))
                     .clicked()
@@ -440,6 +1040,30 @@ impl KlondikeApp {
                     ui.close();
                 }
                 ui.separator();
+                if ui.button("Save Game").clicked() {
+                    ui.close();
+                    if let Err(err) = self.save_game() {
+                        self.error = Some(format!("{err:#}"));
+                    }
+                }
+                if ui.button("Load Game").clicked() {
+                    ui.close();
+                    if let Err(err) = self.load_game() {
+                        self.error = Some(format!("{err:#}"));
+                    }
+                }
+                let export_button = egui::Button::new("Export Replay");
+                if ui
+                    .add_enabled(!self.history.is_empty(), export_button)
+                    .on_hover_text("Save the moves played so far as an animated GIF")
+                    .clicked()
+                {
+                    ui.close();
+                    if let Err(err) = self.export_replay() {
+                        self.error = Some(format!("{err:#}"));
+                    }
+                }
+                ui.separator();
                 let undo_button = egui::Button::new("Undo").shortcut_text("Z");
                 if ui
                     .add_enabled(!self.history.is_empty(), undo_button)
@@ -456,8 +1080,74 @@ impl KlondikeApp {
                     self.redo(ui.ctx());
                     ui.close();
                 }
+                if ui
+                    .add(egui::Button::new("Hint").shortcut_text("H"))
+                    .clicked()
+                {
+                    self.show_hint(ui.ctx());
+                    ui.close();
+                }
+                ui.separator();
+                ui.menu_button("Scoring", |ui| {
+                    let score_mode = &mut self.settings.score_mode;
+                    ui.radio_value(score_mode, ScoreMode::Standard, "Standard");
+                    ui.radio_value(score_mode, ScoreMode::Vegas, "Vegas");
+                    ui.radio_value(score_mode, ScoreMode::VegasCumulative, "Vegas Cumulative");
+                });
+                ui.menu_button("Theme", |ui| {
+                    for theme in ThemeKind::ALL {
+                        ui.radio_value(&mut self.settings.theme, theme, theme.name());
+                    }
+                });
+                ui.menu_button("Settings", |ui| {
+                    ui.add(
+                        egui::Slider::new(&mut self.settings.animation_speed, 0.0..=2.0)
+                            .text("Animation speed")
+                            .suffix("×"),
+                    )
+                    .on_hover_text("0 applies moves instantly");
+                    ui.checkbox(&mut self.settings.left_handed, "Left-handed layout")
+                        .on_hover_text("Stock on the right, foundations on the left");
+                    ui.checkbox(&mut self.settings.win_cascade, "Win cascade")
+                        .on_hover_text("Bounce the cards off the table after a win, click to skip");
+                    ui.add(
+                        egui::Slider::new(&mut self.settings.autoplay_interval, 0.5..=5.0)
+                            .text("Autoplay interval")
+                            .suffix("s"),
+                    );
+                });
+                ui.separator();
+                if ui.button("Statistics").clicked() {
+                    self.show_stats = true;
+                    ui.close();
+                }
+                if ui.button("Copy History").clicked() {
+                    ctx.copy_text(self.export_history());
+                    ui.close();
+                }
             });
 
+            let safe_button = ui
+                .add_enabled(!self.autoplay, egui::Button::new("⇈"))
+                .on_hover_text("Move Safe Cards to Foundations");
+            if safe_button.clicked() {
+                self.moving_safe_cards = true;
+            }
+
+            if self.solving.is_some() {
+                ui.spinner().on_hover_text("Solving the deal...");
+                if ui.button("Cancel").clicked() {
+                    self.solving = None;
+                }
+            } else if self.solution.is_none() {
+                let solve_button = ui
+                    .button("Solve")
+                    .on_hover_text("Solve the deal and replay the solution");
+                if solve_button.clicked() {
+                    self.start_solving();
+                }
+            }
+
             if self.solution.is_some() {
                 let autoplay_button = egui::Button::new(if self.autoplay { "⏸" } else { "▶" });
                 let hover_text = if self.autoplay {
@@ -466,23 +1156,41 @@ impl KlondikeApp {
                     "Resume Autoplay (P)"
                 };
                 if ui.add(autoplay_button).on_hover_text(hover_text).clicked() {
-                    self.toggle_autoplay();
+                    self.toggle_autoplay(ctx.input(|i| i.time));
                 }
             }
 
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                ui.label(format!("Score: {}", self.score));
+                ui.label(self.score_text());
                 ui.separator();
-                ui.label(format!("Moves: {}", self.history.len()));
+                ui.label(format!("Moves: {}", self.moves_played()));
                 ui.separator();
-                let time = if let Some(end_time) = self.end_time {
-                    end_time - self.start_time
-                } else {
-                    ctx.input(|i| i.time) - self.start_time
-                };
+                let now = ctx.input(|i| i.time);
+                let time = self.elapsed(now);
                 let minutes = (time / 60.0).floor() as u32;
                 let seconds = (time % 60.0).floor() as u32;
                 ui.label(format!("Time: {:02}:{:02}", minutes.min(99), seconds));
+                if self.end_time.is_none() {
+                    let (text, hover_text) = match self.paused_at {
+                        Some(_) => ("▶", "Resume Timer"),
+                        None => ("⏸", "Pause Timer"),
+                    };
+                    if ui.button(text).on_hover_text(hover_text).clicked() {
+                        match self.paused_at {
+                            Some(_) => self.resume_timer(now),
+                            None => self.pause_timer(now),
+                        }
+                    }
+                }
+                if let Some(seed) = self.seed {
+                    ui.separator();
+                    let seed_label = ui
+                        .add(egui::Label::new(format!("Game #{seed}")).sense(Sense::click()))
+                        .on_hover_text("Click to copy the game number");
+                    if seed_label.clicked() {
+                        ctx.copy_text(seed.to_string());
+                    }
+                }
             });
         });
     }
@@ -501,15 +1209,30 @@ impl KlondikeApp {
 
         for (idx, anim) in self.animations.iter().enumerate() {
             let elapsed = now - anim.start_time;
+            if elapsed < 0.0 {
+                continue;
+            }
             let progress = (elapsed / anim.duration).min(1.0);
 
-            let t = 1.0 - (1.0 - progress).powi(3);
-            let x = egui::lerp(anim.start_pos.x..=anim.end_pos.x, t as f32);
-            let y = egui::lerp(anim.start_pos.y..=anim.end_pos.y, t as f32);
-            let current_pos = Pos2::new(x, y);
-            let card_rect = Rect::from_min_size(current_pos, CARD_SIZE);
+            let current_pos = match anim.kind {
+                // Bounces on `end_pos.y` lower and lower while moving away
+                AnimationKind::Cascade => {
+                    let progress = progress as f32;
+                    let x = egui::lerp(anim.start_pos.x..=anim.end_pos.x, progress);
+                    let height = (anim.end_pos.y - anim.start_pos.y) * (1.0 - progress);
+                    let bounce = (std::f32::consts::PI * CASCADE_BOUNCES * progress).cos();
+                    Pos2::new(x, anim.end_pos.y - height * bounce.abs())
+                }
+                _ => {
+                    let t = 1.0 - (1.0 - progress).powi(3);
+                    let x = egui::lerp(anim.start_pos.x..=anim.end_pos.x, t as f32);
+                    let y = egui::lerp(anim.start_pos.y..=anim.end_pos.y, t as f32);
+                    Pos2::new(x, y)
+                }
+            };
+            let card_rect = Rect::from_min_size(current_pos, self.layout.card_size);
 
-            Self::paint_card(&painter, card_rect, &anim.card);
+            self.paint_card(&painter, card_rect, &anim.card);
 
             if progress >= 1.0 {
                 finished_animations.push(idx);
@@ -517,19 +1240,8 @@ impl KlondikeApp {
         }
 
         for &idx in finished_animations.iter() {
-            let anim = &self.animations[idx];
-            let card = anim.card;
-
-            match anim.destination {
-                PileId::Foundation(i) => self.board.foundations[i].push(card),
-                PileId::Tableau(i) => self.board.tableaus[i].push(card),
-                PileId::Waste => self.board.waste.push(card),
-                PileId::Stock => self.board.stock.push(card),
-            }
-
-            if !anim.reverse {
-                self.try_flip_tableau_top_card(anim.source);
-            }
+            let anim = self.animations[idx].clone();
+            self.land_animation(&anim);
         }
 
         for &idx in finished_animations.iter().rev() {
@@ -539,22 +1251,114 @@ impl KlondikeApp {
         self.hook_moved = true;
     }
 
-    /// Apply a move and record it in history
-    fn apply_and_record_move(&mut self, ctx: &egui::Context, game_move: GameMove) {
-        self.history.push(game_move.clone());
-        self.redo_stack.clear();
-        self.apply_move(ctx, game_move, false);
-    }
+    /// Put the card of a finished animation onto its destination pile
+    fn land_animation(&mut self, anim: &CardAnimation) {
+        // The cards of the cascade never left the foundations
+        if anim.kind == AnimationKind::Cascade {
+            return;
+        }
+        let card = anim.card;
 
-    /// Undo the last move
-    fn undo(&mut self, ctx: &egui::Context) {
-        if self.animations.is_empty()
-            && let Some(last_move) = self.history.pop()
-        {
-            self.apply_move(ctx, last_move.clone(), true);
-            self.redo_stack.push(last_move);
+        match anim.destination {
+            PileId::Foundation(i) => self.board.foundations[i].push(card),
+            PileId::Tableau(i) => self.board.tableaus[i].push(card),
+            PileId::Waste => self.board.waste.push(card),
+            PileId::Stock => self.board.stock.push(card),
         }
-    }
+
+        if !anim.reverse {
+            self.board.flip_tableau_top_card(anim.source);
+        }
+    }
+
+    /// Bounce the foundation cards off the bottom of `screen`, one at a time from the Kings
+    fn start_cascade(&mut self, screen: Rect, now: f64) {
+        let duration = self.animation_duration(CASCADE_DURATION);
+        let interval = self.animation_duration(CASCADE_INTERVAL);
+        let floor = screen.bottom() - self.layout.card_size.y;
+        for rank in (0..13).rev() {
+            for i in 0..4 {
+                let Some(&card) = self.board.foundations[i].get(rank) else {
+                    continue;
+                };
+                let launched = self.animations.len();
+                // Every other card goes left
+                let end_x = match launched % 2 {
+                    0 => screen.left() - self.layout.card_size.x,
+                    _ => screen.right(),
+                };
+                self.animations.push(CardAnimation {
+                    card,
+                    start_pos: self.foundation_rects[i].min,
+                    end_pos: Pos2::new(end_x, floor),
+                    start_time: now + launched as f64 * interval,
+                    duration,
+                    source: PileId::Foundation(i),
+                    destination: PileId::Foundation(i),
+                    reverse: false,
+                    kind: AnimationKind::Cascade,
+                });
+            }
+        }
+    }
+
+    /// Cards of a foundation shown at `now`, leaving out those the cascade took
+    fn foundation_shown_len(&self, foundation_idx: usize, now: f64) -> usize {
+        let pile = PileId::Foundation(foundation_idx);
+        let mut cascade = self
+            .animations
+            .iter()
+            .filter(|anim| anim.kind == AnimationKind::Cascade)
+            .peekable();
+        if cascade.peek().is_none() {
+            return self.board.foundations[foundation_idx].len();
+        }
+        cascade
+            .filter(|anim| anim.source == pile && anim.start_time > now)
+            .count()
+    }
+
+    /// Land all snap back animations immediately
+    fn finish_snap_back_animations(&mut self) {
+        let (snap_backs, others): (Vec<_>, Vec<_>) = std::mem::take(&mut self.animations)
+            .into_iter()
+            .partition(|anim| anim.kind == AnimationKind::SnapBack);
+        self.animations = others;
+        for anim in snap_backs.iter() {
+            self.land_animation(anim);
+        }
+    }
+
+    /// Apply a move and record it in history
+    fn apply_and_record_move(&mut self, ctx: &egui::Context, game_move: GameMove) {
+        self.record_move(ctx.input(|i| i.time), &game_move);
+        self.apply_move(ctx, game_move, false);
+    }
+
+    /// Record a new move played at `now`, before the board changes
+    fn record_move(&mut self, now: f64, game_move: &GameMove) {
+        self.resume_timer(now);
+        self.history.push(game_move.clone());
+        self.redo_stack.clear();
+        self.drop_keyboard_pick();
+    }
+
+    /// Forget the stack picked up with the keyboard, which may not be there once the board changes
+    fn drop_keyboard_pick(&mut self) {
+        if let Some(focus) = &mut self.keyboard_focus {
+            focus.picked = None;
+        }
+    }
+
+    /// Undo the last move
+    fn undo(&mut self, ctx: &egui::Context) {
+        if self.animations.is_empty()
+            && let Some(last_move) = self.history.pop()
+        {
+            self.apply_move(ctx, last_move.clone(), true);
+            self.redo_stack.push(last_move);
+        }
+    }
 
     /// Redo the last undone move
     fn redo(&mut self, ctx: &egui::Context) {
@@ -566,8 +1370,27 @@ impl KlondikeApp {
         }
     }
 
+    /// Duration of an animation at the chosen speed, 0 when animations are off
+    fn animation_duration(&self, duration: f64) -> f64 {
+        match self.settings.animation_speed {
+            speed if speed > 0.0 => duration / speed,
+            _ => 0.0,
+        }
+    }
+
     /// Execute a game move (for new moves and redo)
     fn apply_move(&mut self, ctx: &egui::Context, game_move: GameMove, reverse: bool) {
+        self.drop_keyboard_pick();
+        let duration = self.animation_duration(MOVE_DURATION);
+        if duration <= 0.0 {
+            self.apply_move_instant(&game_move, reverse);
+            self.moved_cards = Some(match reverse {
+                false => (game_move.destination, game_move.count),
+                true => (game_move.source, game_move.count),
+            });
+            self.hook_moved = true;
+            return;
+        }
         let GameMove {
             source,
             destination,
@@ -575,10 +1398,14 @@ impl KlondikeApp {
             source_flip,
         } = game_move;
         let cards = match reverse {
-            false => self.take_cards(source, count),
-            true => self.take_cards(destination, count),
+            false => self.board.take_cards(source, count),
+            true => self.board.take_cards(destination, count),
         };
         let cards_len = cards.len();
+        self.moved_cards = Some(match reverse {
+            false => (destination, cards_len),
+            true => (source, cards_len),
+        });
         if reverse
             && source_flip
             && let PileId::Tableau(source_idx) = source
@@ -598,42 +1425,46 @@ impl KlondikeApp {
                 start_pos,
                 end_pos,
                 start_time: ctx.input(|i| i.time),
-                duration: 0.2,
+                duration,
                 source,
                 destination,
                 reverse,
+                kind: AnimationKind::Move,
             }
         };
+        // Offset of the waste top card before a move from the waste, or after undoing it
+        let history = match reverse {
+            false => &self.history[..self.history.len().saturating_sub(1)],
+            true => &self.history[..],
+        };
+        let waste_fanned = match source {
+            PileId::Stock => 0,
+            _ => self.waste_visible_count(history, self.board.waste.len() + cards_len),
+        };
+        let waste_top = waste_fanned.saturating_sub(1);
         let animations: Vec<_> = match (source, destination) {
-            (PileId::Stock, PileId::Waste) => {
-                let draw_count = (cards_len + self.board.waste.len()).min(self.board.draw_count);
-                cards
-                    .into_iter()
-                    .rev()
-                    .enumerate()
-                    .map(|(i, mut card)| {
-                        let offset = if reverse {
-                            draw_count - 1 - i
-                        } else {
-                            draw_count + i - cards_len
-                        };
-                        card.face_up = !reverse;
-                        (
-                            card,
-                            self.get_card_pos(source, None),
-                            self.get_card_pos(destination, Some(offset)),
-                        )
-                    })
-                    .map(create_animation)
-                    .collect()
-            }
+            (PileId::Stock, PileId::Waste) => cards
+                .into_iter()
+                .rev()
+                .enumerate()
+                .map(|(i, mut card)| {
+                    let offset = if reverse { cards_len - 1 - i } else { i };
+                    card.face_up = !reverse;
+                    (
+                        card,
+                        self.get_card_pos(source, None),
+                        self.get_card_pos(destination, Some(offset)),
+                    )
+                })
+                .map(create_animation)
+                .collect(),
             (PileId::Waste, PileId::Stock) => cards
                 .into_iter()
                 .rev()
                 .enumerate()
                 .map(|(i, mut card)| {
                     card.face_up = reverse;
-                    let limit = cards_len.min(self.board.draw_count);
+                    let limit = waste_fanned;
                     let offset = if reverse {
                         limit.saturating_sub(cards_len - i)
                     } else {
@@ -650,10 +1481,7 @@ impl KlondikeApp {
             (_, PileId::Foundation(_)) => {
                 let card = cards[0];
                 let start_pos = match source {
-                    PileId::Waste => self.get_card_pos(
-                        source,
-                        Some(self.board.waste.len().min(self.board.draw_count - 1)),
-                    ),
+                    PileId::Waste => self.get_card_pos(source, Some(waste_top)),
                     PileId::Tableau(source_idx) => {
                         self.get_card_pos(source, Some(self.board.tableaus[source_idx].len()))
                     }
@@ -670,10 +1498,7 @@ impl KlondikeApp {
                 .enumerate()
                 .map(|(i, card)| {
                     let start_pos = match source {
-                        PileId::Waste => self.get_card_pos(
-                            source,
-                            Some(self.board.waste.len().min(self.board.draw_count - 1)),
-                        ),
+                        PileId::Waste => self.get_card_pos(source, Some(waste_top)),
                         PileId::Foundation(_) => self.get_card_pos(source, None),
                         PileId::Tableau(source_idx) => {
                             let pile = &self.board.tableaus[source_idx];
@@ -697,6 +1522,66 @@ impl KlondikeApp {
         self.animations.extend(animations);
     }
 
+    /// Execute a game move immediately without animation
+    fn apply_move_instant(&mut self, game_move: &GameMove, reverse: bool) {
+        self.board.apply_move(game_move, reverse);
+    }
+
+    /// Whether a solution move follows the rules on the current board, those of
+    /// `klondike_common`'s `Board::is_legal`
+    fn is_legal_move(&self, (from, to, count): SolutionMove) -> bool {
+        let board = &self.board;
+        match (from, to) {
+            (PileId::Stock, PileId::Waste) => !board.stock.is_empty(),
+            (PileId::Waste, PileId::Stock) => board.stock.is_empty() && !board.waste.is_empty(),
+            (PileId::Stock, _) | (_, PileId::Stock | PileId::Waste) => false,
+            _ if from == to => false,
+            _ => {
+                let pile = board.pile(from);
+                let movable = match from {
+                    PileId::Tableau(_) => pile.iter().rev().take_while(|c| c.face_up).count(),
+                    _ => pile.len().min(1),
+                };
+                if !(1..=movable).contains(&count) {
+                    return false;
+                }
+                let card = &pile[pile.len() - count];
+                match to {
+                    PileId::Foundation(i) => {
+                        count == 1 && self.can_place_card_on_foundation(i, card)
+                    }
+                    PileId::Tableau(i) => self.can_place_card_on_tableau(i, card),
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    /// Build a game move from a solution move against the current board
+    fn build_solution_move(&self, (from, to, count): SolutionMove) -> GameMove {
+        let count = match (from, to) {
+            (PileId::Stock, PileId::Waste) => self.board.draw_count.min(self.board.stock.len()),
+            (PileId::Waste, PileId::Stock) => self.board.waste.len(),
+            _ => count,
+        };
+        self.build_game_move(from, to, count)
+    }
+
+    /// Move a clicked card to a foundation, falling back to a tableau on a single click only
+    fn handle_card_click(
+        &mut self,
+        ctx: &egui::Context,
+        source: PileId,
+        card_idx: usize,
+        response: &egui::Response,
+    ) {
+        if response.double_clicked() {
+            self.try_auto_move_to_foundation(ctx, source, card_idx);
+        } else if response.clicked() && !self.try_auto_move_to_foundation(ctx, source, card_idx) {
+            self.try_auto_move_to_tableau(ctx, source, card_idx);
+        }
+    }
+
     /// Start dragging
     fn start_drag(&mut self, source: PileId, card_idx: usize, response: &egui::Response) {
         let cards_to_drag = match source {
@@ -724,7 +1609,7 @@ impl KlondikeApp {
     }
 
     /// Handle card drop
-    fn handle_drop(&mut self, drop_pos: Pos2) {
+    fn handle_drop(&mut self, ctx: &egui::Context, drop_pos: Pos2) {
         let mut drop_target: Option<PileId> = None;
 
         for i in 0..4 {
@@ -741,12 +1626,15 @@ impl KlondikeApp {
                 }
             }
         }
+        if drop_target.is_none() {
+            drop_target = self.snap_target(drop_pos);
+        }
 
         match (self.drag_source, drop_target) {
             (Some(source), Some(destination)) => {
                 let game_move = self.build_game_move(source, destination, self.dragged_cards.len());
-                self.history.push(game_move);
-                self.redo_stack.clear();
+                self.moved_cards = Some((destination, game_move.count));
+                self.record_move(ctx.input(|i| i.time), &game_move);
 
                 match destination {
                     PileId::Foundation(i) => {
@@ -756,29 +1644,92 @@ impl KlondikeApp {
                     _ => unreachable!(),
                 }
 
-                self.try_flip_tableau_top_card(source);
+                self.board.flip_tableau_top_card(source);
                 self.dragged_cards.clear();
                 self.drag_source = None;
 
                 self.hook_moved = true;
             }
             _ => {
-                self.return_dragged_cards();
+                self.return_dragged_cards(ctx);
             }
         }
     }
 
+    /// Closest legal pile by center a near miss at `drop_pos` snaps to,
+    /// `None` when the drop is over a pile
+    fn snap_target(&self, drop_pos: Pos2) -> Option<PileId> {
+        let pile_rect = |pile: PileId| match pile {
+            PileId::Stock => self.stock_rect,
+            PileId::Waste => self.waste_rect,
+            PileId::Foundation(i) => self.foundation_rects[i],
+            PileId::Tableau(i) => self.tableau_rects[i],
+        };
+        let piles = [PileId::Stock, PileId::Waste]
+            .into_iter()
+            .chain((0..4).map(PileId::Foundation))
+            .chain((0..7).map(PileId::Tableau));
+        if piles.map(pile_rect).any(|rect| rect.contains(drop_pos)) {
+            return None;
+        }
+        self.drop_targets()
+            .into_iter()
+            .filter(|&pile| pile_rect(pile).distance_to_pos(drop_pos) <= DROP_SNAP_DISTANCE)
+            .min_by(|&a, &b| {
+                let distance = |pile| pile_rect(pile).center().distance(drop_pos);
+                distance(a).total_cmp(&distance(b))
+            })
+    }
+
     /// Return dragged cards to original place
-    fn return_dragged_cards(&mut self) {
-        if let Some(source) = self.drag_source.take() {
-            let mut cards = std::mem::take(&mut self.dragged_cards);
+    fn return_dragged_cards(&mut self, ctx: &egui::Context) {
+        let Some(source) = self.drag_source.take() else {
+            return;
+        };
+        let mut cards = std::mem::take(&mut self.dragged_cards);
+        let duration = self.animation_duration(self.settings.snap_back_duration);
+        if duration <= 0.0 {
             match source {
                 PileId::Waste => self.board.waste.append(&mut cards),
                 PileId::Foundation(i) => self.board.foundations[i].append(&mut cards),
                 PileId::Tableau(i) => self.board.tableaus[i].append(&mut cards),
                 PileId::Stock => unreachable!(),
             }
+            return;
         }
+
+        let start_time = ctx.input(|i| i.time);
+        let animations: Vec<_> = cards
+            .into_iter()
+            .enumerate()
+            .map(|(i, card)| {
+                let end_pos = match source {
+                    PileId::Waste => {
+                        let waste_len = self.board.waste.len() + 1;
+                        let fanned = self.waste_visible_count(&self.history, waste_len);
+                        self.get_card_pos(source, Some(fanned - 1))
+                    }
+                    PileId::Foundation(_) => self.get_card_pos(source, None),
+                    PileId::Tableau(source_idx) => {
+                        self.get_card_pos(source, Some(self.board.tableaus[source_idx].len() + i))
+                    }
+                    PileId::Stock => unreachable!(),
+                };
+                CardAnimation {
+                    card,
+                    start_pos: self.drag_pos
+                        + Vec2::new(0.0, i as f32 * self.layout.tableau_v_offset),
+                    end_pos,
+                    start_time,
+                    duration,
+                    source,
+                    destination: source,
+                    reverse: true,
+                    kind: AnimationKind::SnapBack,
+                }
+            })
+            .collect();
+        self.animations.extend(animations);
     }
 
     fn handle_autofinish(&mut self, ctx: &egui::Context) {
@@ -848,6 +1799,17 @@ impl KlondikeApp {
         }
     }
 
+    /// Move the next safe card to its foundation, stopping once none is left
+    fn perform_safe_move_step(&mut self, ctx: &egui::Context) {
+        match self.board.safe_foundation_moves().first() {
+            Some(action) => {
+                let game_move = self.build_solution_move(solution_move(action));
+                self.apply_and_record_move(ctx, game_move);
+            }
+            None => self.moving_safe_cards = false,
+        }
+    }
+
     fn handle_autoplay(&mut self, ctx: &egui::Context) {
         let Some((moves, index, board)) = self.solution.as_mut() else {
             self.autoplay = false;
@@ -869,7 +1831,7 @@ impl KlondikeApp {
         let now = ctx.input(|i| i.time);
 
         if self.next_play_time == 0.0 {
-            self.next_play_time = now + AUTOPLAY_INTERVAL;
+            self.next_play_time = now + self.settings.autoplay_interval;
         }
 
         if now < self.next_play_time {
@@ -932,25 +1894,250 @@ impl KlondikeApp {
             *board = None;
         }
 
-        self.next_play_time = now + AUTOPLAY_INTERVAL * factor;
+        self.next_play_time = now + self.settings.autoplay_interval * factor;
     }
 
-    fn toggle_autoplay(&mut self) {
+    fn toggle_autoplay(&mut self, now: f64) {
         if self.solution.is_none() {
             return;
         }
         self.autoplay = !self.autoplay;
         if self.autoplay {
             self.next_play_time = 0.0;
+            self.resume_timer(now);
+        }
+    }
+
+    /// Seconds played, leaving out the pauses
+    fn elapsed(&self, now: f64) -> f64 {
+        let end = self.end_time.or(self.paused_at).unwrap_or(now);
+        end - self.start_time - self.accumulated_pause
+    }
+
+    /// Freeze the timer of an unfinished game, stopping the autoplay too
+    fn pause_timer(&mut self, now: f64) {
+        if self.paused_at.is_none() && self.end_time.is_none() {
+            self.paused_at = Some(now);
+            self.autoplay = false;
+        }
+    }
+
+    /// Restart the timer, e.g. when a move is played during a pause
+    fn resume_timer(&mut self, now: f64) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.accumulated_pause += now - paused_at;
+        }
+        self.paused_by_focus = false;
+    }
+
+    /// Play with the keyboard: the arrow keys move the focus, Up and Down also grow and
+    /// shrink the focused tableau stack, Space picks up and drops a stack and Enter
+    /// sends the focused card to a foundation
+    fn handle_keyboard_play(&mut self, ctx: &egui::Context) {
+        let pressed = |key| ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key));
+        let arrows = [
+            (egui::Key::ArrowLeft, (-1, 0)),
+            (egui::Key::ArrowRight, (1, 0)),
+            (egui::Key::ArrowUp, (0, -1)),
+            (egui::Key::ArrowDown, (0, 1)),
+        ];
+        let (dx, dy) = arrows
+            .into_iter()
+            .find(|&(key, _)| pressed(key))
+            .map_or((0, 0), |(_, step)| step);
+        if (dx, dy) != (0, 0) {
+            let focus = self.keyboard_focus.get_or_insert(KeyboardFocus {
+                pile: PileId::Stock,
+                count: 1,
+                picked: None,
+            });
+            let face_up = self
+                .board
+                .pile(focus.pile)
+                .iter()
+                .filter(|c| c.face_up)
+                .count();
+            match (focus.pile, dy) {
+                (PileId::Tableau(_), -1) if focus.count < face_up => focus.count += 1,
+                (PileId::Tableau(_), 1) if focus.count > 1 => focus.count -= 1,
+                (pile, _) => {
+                    // Left goes to the next pile of the mirrored table
+                    let dx = match self.settings.left_handed {
+                        true => -dx,
+                        false => dx,
+                    };
+                    focus.pile = pile.step(dx, dy);
+                    focus.count = 1;
+                }
+            }
+        }
+
+        if self.keyboard_focus.is_none() || self.autoplay || !self.animations.is_empty() {
+            return;
+        }
+        if pressed(egui::Key::Space) {
+            self.keyboard_pick_or_drop(ctx);
+        }
+        if pressed(egui::Key::Enter)
+            && let Some(focus) = self.keyboard_focus
+        {
+            let pile_len = self.board.pile(focus.pile).len();
+            match focus.pile {
+                PileId::Stock => self.draw_from_stock(ctx),
+                pile if pile_len > 0 => {
+                    self.try_auto_move_to_foundation(ctx, pile, pile_len - 1);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Pick up the focused stack, or drop the picked up stack on the focused pile if it fits
+    fn keyboard_pick_or_drop(&mut self, ctx: &egui::Context) {
+        let Some(focus) = self.keyboard_focus else {
+            return;
+        };
+        let destination = focus.pile;
+        let Some((source, count)) = focus.picked else {
+            let count = self.keyboard_stack_len(destination, focus.count);
+            match destination {
+                PileId::Stock => self.draw_from_stock(ctx),
+                pile if count > 0 => {
+                    self.keyboard_focus = Some(KeyboardFocus {
+                        picked: Some((pile, count)),
+                        ..focus
+                    });
+                }
+                _ => {}
+            }
+            return;
+        };
+        self.keyboard_focus = Some(KeyboardFocus {
+            picked: None,
+            ..focus
+        });
+
+        // The board may have changed since the stack was picked up
+        if self.keyboard_stack_len(source, count) < count {
+            return;
+        }
+        let source_pile = self.board.pile(source);
+        let card = source_pile[source_pile.len() - count];
+        let fits = match destination {
+            PileId::Foundation(i) => count == 1 && self.can_place_card_on_foundation(i, &card),
+            PileId::Tableau(i) => source != destination && self.can_place_card_on_tableau(i, &card),
+            _ => false,
+        };
+        if fits {
+            self.apply_and_record_move(ctx, self.build_game_move(source, destination, count));
+        }
+    }
+
+    /// Number of cards of the focused stack that can be picked up
+    fn keyboard_stack_len(&self, pile: PileId, count: usize) -> usize {
+        let cards = self.board.pile(pile);
+        match pile {
+            PileId::Stock => 0,
+            PileId::Waste | PileId::Foundation(_) => cards.len().min(1),
+            PileId::Tableau(_) => count.min(cards.iter().filter(|c| c.face_up).count()),
+        }
+    }
+
+    /// Outline the pile focused with the keyboard, and the stack picked up from it
+    fn paint_keyboard_focus(&self, ctx: &egui::Context) {
+        let Some(focus) = self.keyboard_focus else {
+            return;
+        };
+        let stack_rect = |pile: PileId, count: usize| {
+            let len = self.board.pile(pile).len();
+            let first = match pile {
+                PileId::Waste => self.waste_shown_count().saturating_sub(1),
+                PileId::Tableau(_) => len.saturating_sub(count.max(1)),
+                _ => 0,
+            };
+            let last = match pile {
+                PileId::Tableau(_) => len.saturating_sub(1).max(first),
+                _ => first,
+            };
+            Rect::from_min_max(
+                self.get_card_pos(pile, Some(first)),
+                self.get_card_pos(pile, Some(last)) + self.layout.card_size,
+            )
+        };
+        let painter = ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("keyboard_focus")));
+        let outline = |rect: Rect, color| {
+            painter.rect_stroke(
+                rect.expand(3.0),
+                CornerRadius::same(7),
+                Stroke::new(2.0, color),
+                StrokeKind::Outside,
+            );
+        };
+        if let Some((pile, count)) = focus.picked {
+            outline(stack_rect(pile, count), Color32::from_rgb(255, 200, 0));
+        }
+        let count = self.keyboard_stack_len(focus.pile, focus.count);
+        outline(
+            stack_rect(focus.pile, count),
+            Color32::from_rgb(0, 160, 255),
+        );
+    }
+
+    /// Highlight the source and destination piles of a suggested move
+    fn show_hint(&mut self, ctx: &egui::Context) {
+        self.hint = None;
+        if self.autoplay || !self.animations.is_empty() {
+            return;
         }
+        let Some(action) = self
+            .board
+            .to_solver_board()
+            .ok()
+            .and_then(|board| suggest_move(&board))
+        else {
+            return;
+        };
+        let (source, destination, count) = solution_move(&action);
+        let start_time = ctx.input(|i| i.time);
+        self.hint = Some([
+            Highlight {
+                pile: source,
+                count: count.max(1),
+                start_time,
+            },
+            Highlight {
+                pile: destination,
+                count: 1,
+                start_time,
+            },
+        ]);
     }
 
     fn handle_moved(&mut self, ctx: &egui::Context) {
+        if let Some((pile, count)) = self.moved_cards.take()
+            && self.settings.highlight_duration > 0.0
+        {
+            self.highlight = Some(Highlight {
+                pile,
+                count,
+                start_time: ctx.input(|i| i.time),
+            });
+        }
         let score = self.board.score();
         let is_win = score == 52;
         if is_win {
             if self.end_time.is_none() {
-                self.end_time = Some(ctx.input(|i| i.time));
+                let end_time = ctx.input(|i| i.time);
+                self.end_time = Some(end_time);
+                self.compute_par();
+                if self.settings.win_cascade && self.settings.animation_speed > 0.0 {
+                    self.start_cascade(ctx.screen_rect(), end_time);
+                }
+                if self.is_tracked() {
+                    let time = self.elapsed(end_time);
+                    self.stats.record_win(self.board.draw_count, time);
+                    self.save_stats();
+                }
             }
         } else if !self.autoplay
             && self.autofinish == Autofinish::Idle
@@ -959,9 +2146,284 @@ impl KlondikeApp {
             self.autofinish = Autofinish::Asking;
         }
         self.score = score;
+        self.stuck = !is_win && !self.has_productive_moves();
         self.hook_moved = false;
     }
 
+    /// Whether a move can still make progress: a card of the stock or waste can be played
+    /// after cycling the stock, a card can go to a foundation, or a tableau move turns up,
+    /// frees or clears something. Moves off the foundations are not counted.
+    fn has_productive_moves(&self) -> bool {
+        let to_foundation =
+            |card: &Card| (0..4).any(|i| self.can_place_card_on_foundation(i, card));
+        let to_tableau = |card: &Card| (0..7).any(|i| self.can_place_card_on_tableau(i, card));
+        // Without a redeal, only the stock and the waste top are left to play
+        let waste = match self.can_redeal() {
+            true => &self.board.waste[..],
+            false => &self.board.waste[self.board.waste.len().saturating_sub(1)..],
+        };
+        let mut talon = self.board.stock.iter().chain(waste);
+        if talon.any(|card| to_foundation(card) || to_tableau(card)) {
+            return true;
+        }
+
+        self.board.tableaus.iter().enumerate().any(|(from, pile)| {
+            if pile
+                .last()
+                .is_some_and(|card| card.face_up && to_foundation(card))
+            {
+                return true;
+            }
+            let mut face_up = pile.iter().enumerate().filter(|(_, card)| card.face_up);
+            face_up.any(|(j, card)| {
+                (0..7)
+                    .filter(|&to| to != from && self.can_place_card_on_tableau(to, card))
+                    .any(|to| match j {
+                        // Moving a whole pile only helps when it clears the column
+                        0 => !self.board.tableaus[to].is_empty(),
+                        _ => !pile[j - 1].face_up || to_foundation(&pile[j - 1]),
+                    })
+            })
+        })
+    }
+
+    /// Moves played, redeals not counted like in the par
+    fn moves_played(&self) -> usize {
+        self.history.iter().filter(|m| !m.is_redeal()).count()
+    }
+
+    /// Compute the par of the game, using the known solution or a background solve
+    fn compute_par(&mut self) {
+        if !matches!(self.par, Par::Unknown) {
+            return;
+        }
+        self.par = match &self.solution {
+            // Redeals don't count, like in the par of a background solve. A loaded
+            // solution may come from a fast solve, so it isn't taken as minimal
+            Some((moves, _, _)) => Par::Known {
+                moves: moves
+                    .iter()
+                    .filter(|(source, destination, _)| {
+                        !(*source == PileId::Waste && *destination == PileId::Stock)
+                    })
+                    .count(),
+                minimal: false,
+            },
+            None => match BackgroundSolve::spawn(
+                &self.init_board,
+                PAR_MAX_STATES,
+                None,
+                self.settings.redeal_limit,
+            ) {
+                Ok(worker) => Par::Solving(worker),
+                Err(_) => Par::Unavailable,
+            },
+        };
+    }
+
+    fn popup_resume(&mut self, ctx: &egui::Context) {
+        let (mut resume, mut new_game) = (false, false);
+        egui::Window::new("Resume")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("Resume the game left unfinished last time?");
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    resume = ui.button("Resume").clicked();
+                    new_game = ui.button("New Game").clicked();
+                });
+            });
+        if new_game {
+            self.resume = None;
+            if let Err(err) = session::clear() {
+                self.error = Some(format!("{err:#}"));
+            }
+        } else if resume && let Some(content) = self.resume.take() {
+            match Self::import_session(&content, ctx.input(|i| i.time)) {
+                Ok(mut app) => {
+                    app.settings = self.settings.clone();
+                    app.stats = std::mem::take(&mut self.stats);
+                    *self = app;
+                }
+                Err(err) => {
+                    // A game that can't be resumed is dropped, so the prompt doesn't come back
+                    self.error = Some(format!("Failed to resume the last game; {err:#}"));
+                    if let Err(err) = session::clear() {
+                        self.error = Some(format!("{err:#}"));
+                    }
+                }
+            }
+        }
+    }
+
+    fn popup_seed_input(&mut self, ctx: &egui::Context) {
+        let Some(input) = self.seed_input.as_mut() else {
+            return;
+        };
+        let (mut ok, mut cancel) = (false, false);
+        egui::Window::new("New Game from Seed")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("Game ID or URL from greenfelt.net/klondike:");
+                let edit = ui.text_edit_singleline(input);
+                edit.request_focus();
+                ok = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ok |= ui.button("OK").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if cancel {
+            self.seed_input = None;
+        } else if ok {
+            match greenfelt_seed(input) {
+                Ok(seed) => {
+                    self.seed_input = None;
+                    self.deal(seed);
+                }
+                Err(err) => self.error = Some(err.to_string()),
+            }
+        }
+    }
+
+    fn popup_error(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Error")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(self.error.as_deref().unwrap_or_default());
+                ui.add_space(10.0);
+                if ui.button("Close").clicked() {
+                    self.error = None;
+                }
+            });
+    }
+
+    /// Tell the player that no move makes progress, without blocking the table
+    fn show_stuck_banner(&self, ctx: &egui::Context) {
+        egui::Area::new(Id::new("stuck_banner"))
+            .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -50.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label("No more moves — try undo or a new game.");
+                });
+            });
+    }
+
+    fn popup_stats(&mut self, ctx: &egui::Context) {
+        let format_time = |time: Option<f64>| match time {
+            Some(time) => format!("{:02}:{:02}", (time / 60.0) as u32, (time % 60.0) as u32),
+            None => "-".into(),
+        };
+        egui::Window::new("Statistics")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                egui::Grid::new("stats").striped(true).show(ui, |ui| {
+                    let (draw1, draw3) = (self.stats.draw(1), self.stats.draw(3));
+                    let rows = [
+                        ("", "Draw 1".into(), "Draw 3".into()),
+                        ("Played", draw1.played.to_string(), draw3.played.to_string()),
+                        ("Won", draw1.won.to_string(), draw3.won.to_string()),
+                        (
+                            "Win rate",
+                            format!("{:.0}%", draw1.win_rate() * 100.0),
+                            format!("{:.0}%", draw3.win_rate() * 100.0),
+                        ),
+                        (
+                            "Best time",
+                            format_time(draw1.best_time),
+                            format_time(draw3.best_time),
+                        ),
+                        ("Streak", draw1.streak.to_string(), draw3.streak.to_string()),
+                        (
+                            "Best streak",
+                            draw1.best_streak.to_string(),
+                            draw3.best_streak.to_string(),
+                        ),
+                    ];
+                    for (label, draw1, draw3) in rows {
+                        ui.label(label);
+                        ui.label(draw1);
+                        ui.label(draw3);
+                        ui.end_row();
+                    }
+                });
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Reset").clicked() {
+                        self.stats = Stats::default();
+                        self.save_stats();
+                    }
+                    if ui.button("Close").clicked() {
+                        self.show_stats = false;
+                    }
+                });
+            });
+    }
+
+    fn popup_win(&mut self, ctx: &egui::Context) {
+        if let Par::Solving(worker) = &self.par
+            && let Some(result) = worker.poll()
+        {
+            self.par = match result {
+                Ok(result) => Par::Known {
+                    moves: result.card_moves() + result.draw_count(),
+                    minimal: result.minimal(),
+                },
+                Err(_) => Par::Unavailable,
+            };
+        }
+
+        egui::Window::new("You Win!")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("You won in {} moves.", self.moves_played()));
+                    match &self.par {
+                        Par::Known {
+                            moves,
+                            minimal: true,
+                        } => {
+                            ui.label(format!("Optimal: {moves}."));
+                        }
+                        Par::Known {
+                            moves,
+                            minimal: false,
+                        } => {
+                            ui.label(format!("Best found: {moves}."));
+                        }
+                        Par::Solving(_) => {
+                            ui.label("Optimal:");
+                            ui.spinner();
+                        }
+                        Par::Unknown | Par::Unavailable => {
+                            ui.label("Optimal: unknown.");
+                        }
+                    }
+                });
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button("New Game").clicked() {
+                        self.renew();
+                    }
+                    if ui.button("Close").clicked() {
+                        self.win_dismissed = true;
+                    }
+                });
+            });
+    }
+
     /// Try to auto-move card to foundation pile
     fn try_auto_move_to_foundation(
         &mut self,
@@ -1040,49 +2502,87 @@ impl KlondikeApp {
         false
     }
 
-    fn try_flip_tableau_top_card(&mut self, source: PileId) {
-        if let PileId::Tableau(i) = source
-            && let Some(card) = self.board.tableaus[i].last_mut()
-        {
-            card.face_up = true;
-        }
-    }
-
-    fn take_cards(&mut self, source: PileId, count: usize) -> Vec<Card> {
-        match source {
-            PileId::Stock => {
-                let start_idx = self.board.stock.len() - count;
-                self.board.stock.drain(start_idx..).collect()
-            }
-            PileId::Waste => {
-                let start_idx = self.board.waste.len() - count;
-                self.board.waste.drain(start_idx..).collect()
-            }
-            PileId::Foundation(i) => {
-                let start_idx = self.board.foundations[i].len() - count;
-                self.board.foundations[i].drain(start_idx..).collect()
-            }
-            PileId::Tableau(i) => {
-                let start_idx = self.board.tableaus[i].len() - count;
-                self.board.tableaus[i].drain(start_idx..).collect()
-            }
-        }
-    }
-
     fn get_card_pos(&self, pile_id: PileId, offset: Option<usize>) -> Pos2 {
         let offset = offset.unwrap_or(0);
         match pile_id {
             PileId::Stock => self.stock_rect.min,
             PileId::Waste => {
-                self.waste_rect.min + Vec2::new(offset as f32 * WASTE_CARD_H_OFFSET, 0.0)
+                self.waste_rect.min + Vec2::new(offset as f32 * self.layout.waste_h_offset, 0.0)
             }
             PileId::Foundation(i) => self.foundation_rects[i].min,
             PileId::Tableau(i) => {
-                self.tableau_rects[i].min + Vec2::new(0.0, offset as f32 * TABLEAU_CARD_V_OFFSET)
+                self.tableau_rects[i].min + Vec2::new(0.0, self.tableau_card_y(i, offset))
             }
         }
     }
 
+    /// Vertical offsets of the face-down and face-up cards of a tableau pile, shrunk
+    /// so the pile fits in `tableau_height`, the face-down cards first
+    fn tableau_v_offsets(&self, tableau_idx: usize) -> (f32, f32) {
+        let pile = &self.board.tableaus[tableau_idx];
+        // The last card takes a whole card height, the others their offset
+        let covered = &pile[..pile.len().saturating_sub(1)];
+        let face_down = covered.iter().filter(|card| !card.face_up).count() as f32;
+        let face_up = covered.len() as f32 - face_down;
+        let Layout {
+            card_size,
+            tableau_v_offset: offset,
+            min_face_down_v_offset,
+            min_face_up_v_offset,
+            ..
+        } = self.layout;
+        let room = self.tableau_height - card_size.y;
+        if face_down * offset + face_up * offset <= room {
+            return (offset, offset);
+        }
+        let down =
+            ((room - face_up * offset) / face_down.max(1.0)).clamp(min_face_down_v_offset, offset);
+        let up = ((room - face_down * down) / face_up.max(1.0)).clamp(min_face_up_v_offset, offset);
+        (down, up)
+    }
+
+    /// Distance from the top of a tableau pile to its card at `offset`
+    fn tableau_card_y(&self, tableau_idx: usize, offset: usize) -> f32 {
+        let (down, up) = self.tableau_v_offsets(tableau_idx);
+        let pile = &self.board.tableaus[tableau_idx];
+        let face_down = pile
+            .iter()
+            .take(offset)
+            .filter(|card| !card.face_up)
+            .count();
+        face_down as f32 * down + (offset - face_down) as f32 * up
+    }
+
+    /// Cards of the last draw still on the waste after `history`
+    fn waste_fan(history: &[GameMove]) -> WasteFan {
+        let mut fan = WasteFan::default();
+        for game_move in history {
+            game_move.update_waste_fan(&mut fan);
+        }
+        fan
+    }
+
+    /// Number of waste cards fanned out after `history`, at least the top card
+    fn waste_visible_count(&self, history: &[GameMove], waste_len: usize) -> usize {
+        Self::waste_fan(history).shown(self.board.draw_count, waste_len)
+    }
+
+    /// Number of waste cards fanned out now, leaving out the dragged card
+    /// and the cards still flying to the waste
+    fn waste_shown_count(&self) -> usize {
+        let mut fan = Self::waste_fan(&self.history);
+        let landing = self
+            .animations
+            .iter()
+            .filter(|anim| anim.destination == PileId::Waste)
+            .count();
+        let dragged = usize::from(self.drag_source == Some(PileId::Waste));
+        for _ in 0..landing + dragged {
+            fan.take();
+        }
+        fan.shown(self.board.draw_count, self.board.waste.len())
+    }
+
     fn build_game_move(&self, source: PileId, destination: PileId, count: usize) -> GameMove {
         let mut source_flip = false;
         if let PileId::Tableau(source_idx) = source {
@@ -1100,6 +2600,20 @@ impl KlondikeApp {
         }
     }
 
+    /// Piles other than the source where the dragged cards can be dropped
+    fn drop_targets(&self) -> Vec<PileId> {
+        let foundations = (0..4)
+            .filter(|&i| self.can_place_on_foundation(i))
+            .map(PileId::Foundation);
+        let tableaus = (0..7)
+            .filter(|&i| self.can_place_on_tableau(i))
+            .map(PileId::Tableau);
+        foundations
+            .chain(tableaus)
+            .filter(|&pile| Some(pile) != self.drag_source)
+            .collect()
+    }
+
     fn can_place_on_foundation(&self, foundation_idx: usize) -> bool {
         if self.dragged_cards.len() != 1 {
             return false;
@@ -1136,3 +2650,389 @@ impl KlondikeApp {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_productive_moves() {
+        let app = KlondikeApp::new(Board::new(283409412, 1));
+        assert!(app.has_productive_moves());
+
+        // F2:T1 is the only legal move
+        const STUCK: &str = "Foundation2: 4♣\nTableau1: |5♦\nDrawCount: 1";
+        let app = KlondikeApp::new(Board::parse(STUCK).unwrap());
+        assert!(!app.has_productive_moves());
+
+        // 4♠ can be played once the stock is cycled
+        let board = Board::parse(&format!("Stock: 4♠2♠\n{STUCK}")).unwrap();
+        assert!(KlondikeApp::new(board).has_productive_moves());
+
+        // Moving a King between empty columns changes nothing
+        let board = Board::parse("Tableau1: |K♦\nDrawCount: 1").unwrap();
+        assert!(!KlondikeApp::new(board).has_productive_moves());
+        let board = Board::parse("Tableau1: 2♣|K♦\nDrawCount: 1").unwrap();
+        assert!(KlondikeApp::new(board).has_productive_moves());
+    }
+
+    #[test]
+    fn test_animated_draws_land_like_instant_ones() {
+        let Layout {
+            card_size,
+            waste_h_offset,
+            ..
+        } = Layout::default();
+        // Draws of 3 leaving 2 cards in the stock, then 1
+        for (board, tail) in [
+            ("Stock: 2♠3♠4♠5♠6♠\nTableau1: |K♦\nDrawCount: 3", 2),
+            ("Stock: 3♠4♠5♠6♠\nTableau1: |K♦\nDrawCount: 3", 1),
+        ] {
+            let ctx = egui::Context::default();
+            let mut animated = KlondikeApp::new(Board::parse(board).unwrap());
+            animated.stock_rect =
+                Rect::from_min_size(Pos2::new(-2.0 * card_size.x, 0.0), card_size);
+            let settings = Settings {
+                animation_speed: 0.0,
+                ..Default::default()
+            };
+            let mut instant = KlondikeApp::with_settings(Board::parse(board).unwrap(), settings);
+
+            // Waste fan offsets the cards fly to, or from when undoing, in the order they land
+            let waste_offset = |pos: Pos2| (pos.x / waste_h_offset).round() as usize;
+            let mut play = |undo: bool| {
+                for app in [&mut animated, &mut instant] {
+                    match undo {
+                        true => app.undo(&ctx),
+                        false => app.draw_from_stock(&ctx),
+                    }
+                }
+                let animations = std::mem::take(&mut animated.animations);
+                for anim in &animations {
+                    animated.land_animation(anim);
+                }
+                assert_eq!(animated.board, instant.board);
+                animations
+                    .iter()
+                    .map(|anim| match anim.start_pos == animated.stock_rect.min {
+                        true => waste_offset(anim.end_pos),
+                        false => waste_offset(anim.start_pos),
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            let fanned: Vec<_> = (0..tail).collect();
+            // The redeal takes the fanned cards from their offsets, the others from under them
+            let mut redeal: Vec<_> = fanned.iter().rev().copied().collect();
+            redeal.resize(tail + 3, 0);
+
+            assert_eq!(play(false), [0, 1, 2]);
+            assert_eq!(play(false), fanned);
+            assert_eq!(play(false), redeal);
+            redeal.reverse();
+            assert_eq!(play(true), redeal);
+            assert_eq!(play(true), fanned.iter().rev().copied().collect::<Vec<_>>());
+            assert_eq!(play(true), [2, 1, 0]);
+            assert_eq!(animated.board, Board::parse(board).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_redeal_limit() {
+        let ctx = egui::Context::default();
+        let settings = Settings {
+            animation_speed: 0.0,
+            redeal_limit: Some(1),
+            ..Default::default()
+        };
+        let board = Board::parse("Stock: 2♠3♠\nTableau1: |K♦\nDrawCount: 1").unwrap();
+        let mut app = KlondikeApp::with_settings(board, settings);
+        for _ in 0..3 {
+            app.draw_from_stock(&ctx);
+        }
+        assert!(app.board.waste.is_empty());
+        assert!(!app.can_redeal());
+
+        app.draw_from_stock(&ctx);
+        app.draw_from_stock(&ctx);
+        app.draw_from_stock(&ctx);
+        assert_eq!(app.board.waste.len(), 2);
+        assert_eq!(app.history.len(), 5);
+        // 2♠ is buried under 3♠ for good
+        assert!(!app.has_productive_moves());
+
+        for _ in 0..3 {
+            app.undo(&ctx);
+        }
+        assert!(app.can_redeal());
+    }
+
+    #[test]
+    fn test_pause_timer() {
+        let mut app = KlondikeApp::new(Board::new(283409412, 1));
+        app.solution = Some((vec![], 0, None));
+        app.autoplay = true;
+        app.start_time = 10.0;
+        app.pause_timer(20.0);
+        assert!(!app.autoplay);
+        assert_eq!(app.elapsed(30.0), 10.0);
+
+        app.resume_timer(25.0);
+        assert_eq!(app.elapsed(30.0), 15.0);
+        app.pause_timer(30.0);
+        app.toggle_autoplay(32.0);
+        assert!(app.autoplay);
+        assert_eq!(app.elapsed(40.0), 23.0);
+
+        app.end_time = Some(50.0);
+        app.pause_timer(60.0);
+        assert_eq!(app.paused_at, None);
+        assert_eq!(app.elapsed(70.0), 33.0);
+    }
+
+    #[test]
+    fn test_drop_targets() {
+        let board = "Waste: A♣
+Foundation1: A♠
+Tableau1: |T♥9♠
+Tableau2: |T♦
+Tableau3: |2♠
+Tableau4: |K♣";
+        let mut app = KlondikeApp::new(Board::parse(board).unwrap());
+        let drag = |app: &mut KlondikeApp, source, count| {
+            app.dragged_cards = app.board.take_cards(source, count);
+            app.drag_source = Some(source);
+        };
+        drag(&mut app, PileId::Tableau(0), 1);
+        assert_eq!(app.drop_targets(), [PileId::Tableau(1)]);
+
+        app.return_dragged_cards(&egui::Context::default());
+        drag(&mut app, PileId::Tableau(2), 1);
+        assert_eq!(app.drop_targets(), [PileId::Foundation(0)]);
+
+        app.finish_snap_back_animations();
+        drag(&mut app, PileId::Waste, 1);
+        let empty_foundations = [1, 2, 3].map(PileId::Foundation);
+        assert_eq!(app.drop_targets(), empty_foundations);
+    }
+
+    #[test]
+    fn test_drop_resumes_timer() {
+        let mut app = KlondikeApp::new(Board::parse("Waste: A♠").unwrap());
+        let ctx = egui::Context::default();
+        let card_size = Layout::default().card_size;
+        app.foundation_rects = std::array::from_fn(|i| {
+            Rect::from_min_size(Pos2::new(i as f32 * 100.0, 0.0), card_size)
+        });
+        app.keyboard_focus = Some(KeyboardFocus {
+            pile: PileId::Tableau(0),
+            count: 1,
+            picked: Some((PileId::Waste, 1)),
+        });
+        app.pause_timer(0.0);
+        app.dragged_cards = app.board.take_cards(PileId::Waste, 1);
+        app.drag_source = Some(PileId::Waste);
+
+        app.handle_drop(&ctx, app.foundation_rects[0].center());
+        assert_eq!(app.board.foundations[0].len(), 1);
+        assert_eq!(app.history.len(), 1);
+        assert_eq!(app.paused_at, None);
+        assert_eq!(app.keyboard_focus.unwrap().picked, None);
+    }
+
+    #[test]
+    fn test_snap_target() {
+        let board = "Tableau1: |T♥9♠
+Tableau2: |T♦
+Tableau3: |T♥
+Tableau4: |2♠";
+        let mut app = KlondikeApp::new(Board::parse(board).unwrap());
+        // Piles spaced by 60px, the tableaus under the top row
+        let card_size = Layout::default().card_size;
+        let rect_at = |x: usize, y: f32| {
+            Rect::from_min_size(Pos2::new(x as f32 * (card_size.x + 60.0), y), card_size)
+        };
+        app.stock_rect = rect_at(0, 0.0);
+        app.waste_rect = rect_at(1, 0.0);
+        app.foundation_rects = std::array::from_fn(|i| rect_at(i + 3, 0.0));
+        app.tableau_rects = std::array::from_fn(|i| rect_at(i, 500.0));
+        app.dragged_cards = app.board.take_cards(PileId::Tableau(0), 1);
+        app.drag_source = Some(PileId::Tableau(0));
+
+        let gap = |i: usize, dx: f32| app.tableau_rects[i].right_center() + Vec2::new(dx, 0.0);
+        assert_eq!(app.snap_target(gap(1, 20.0)), Some(PileId::Tableau(1)));
+        assert_eq!(app.snap_target(gap(1, 40.0)), Some(PileId::Tableau(2)));
+        // Too far from any pile, or over one that doesn't accept the card
+        assert_eq!(app.snap_target(gap(1, 20.0) + Vec2::new(0.0, 200.0)), None);
+        assert_eq!(app.snap_target(app.tableau_rects[3].center()), None);
+        assert_eq!(app.snap_target(gap(2, 20.0)), Some(PileId::Tableau(2)));
+        assert_eq!(app.snap_target(gap(3, 20.0)), None);
+    }
+
+    #[test]
+    fn test_tableau_v_offsets() {
+        let board = "Tableau1: 5♠6♠7♠8♠9♠T♠|K♥Q♠J♥T♣9♥8♣7♥6♣5♥4♣3♥2♣
+Tableau2: 5♥6♥|K♠Q♥";
+        let mut app = KlondikeApp::new(Board::parse(board).unwrap());
+        let Layout {
+            card_size,
+            tableau_v_offset: offset,
+            min_face_down_v_offset,
+            ..
+        } = Layout::default();
+        let height = |app: &KlondikeApp, i: usize| {
+            card_size.y + app.tableau_card_y(i, app.board.tableaus[i].len() - 1)
+        };
+        assert_eq!(app.tableau_v_offsets(0), (offset, offset));
+        assert_eq!(height(&app, 0), card_size.y + 17.0 * offset);
+
+        // The face-down cards are compacted first
+        app.tableau_height = card_size.y + 11.0 * offset + 60.0;
+        assert_eq!(app.tableau_v_offsets(0), (10.0, offset));
+        assert_eq!(height(&app, 0), app.tableau_height);
+        assert_eq!(app.tableau_card_y(0, 7), 6.0 * 10.0 + offset);
+        assert_eq!(app.tableau_v_offsets(1), (offset, offset));
+
+        app.tableau_height = card_size.y + 6.0 * min_face_down_v_offset + 11.0 * 20.0;
+        assert_eq!(app.tableau_v_offsets(0), (min_face_down_v_offset, 20.0));
+        assert_eq!(height(&app, 0), app.tableau_height);
+    }
+
+    #[test]
+    fn test_autosave_content() {
+        let board = "Waste: K♠\nFoundation1: Q♠\nFoundation2: K♦\nFoundation3: K♣\nFoundation4: K♥";
+        let ctx = egui::Context::default();
+        let settings = Settings {
+            animation_speed: 0.0,
+            ..Default::default()
+        };
+        let mut app = KlondikeApp::with_settings(Board::parse(board).unwrap(), settings);
+        assert_eq!(app.unfinished_history(), None);
+
+        app.try_auto_move_to_foundation(&ctx, PileId::Waste, 0);
+        app.seed = Some(11982);
+        app.start_time = 10.0;
+        let history = app.unfinished_history().unwrap();
+        let content = app.autosave_content(&history, 40.0);
+        let resumed = KlondikeApp::import_session(&content, 100.0).unwrap();
+        assert_eq!(resumed.board, app.board);
+        assert_eq!(resumed.score, 52);
+        assert_eq!(resumed.seed, Some(11982));
+        assert_eq!(resumed.elapsed(105.0), 35.0);
+        assert_eq!(resumed.autosaved, Some(history));
+
+        app.end_time = Some(10.0);
+        assert_eq!(app.unfinished_history(), None);
+    }
+
+    #[test]
+    fn test_win_cascade() {
+        let board = "Foundation1: K♠\nFoundation2: K♦\nFoundation3: K♣\nFoundation4: K♥";
+        let mut app = KlondikeApp::new(Board::parse(board).unwrap());
+        let screen = Rect::from_min_size(Pos2::ZERO, Vec2::new(700.0, 800.0));
+        app.start_cascade(screen, 10.0);
+        assert_eq!(app.animations.len(), 52);
+        let shown = |app: &KlondikeApp, now| -> [usize; 4] {
+            std::array::from_fn(|i| app.foundation_shown_len(i, now))
+        };
+        assert_eq!(shown(&app, 10.0), [12, 13, 13, 13]);
+        assert_eq!(shown(&app, 10.0 + 5.0 * CASCADE_INTERVAL), [11, 11, 12, 12]);
+        assert_eq!(shown(&app, 100.0), [0; 4]);
+
+        for anim in std::mem::take(&mut app.animations) {
+            app.land_animation(&anim);
+        }
+        assert_eq!(app.board, Board::parse(board).unwrap());
+        assert_eq!(shown(&app, 100.0), [13; 4]);
+    }
+
+    #[test]
+    fn test_seed_survives_replay() {
+        let mut app = KlondikeApp::from_seed(283409412, 3);
+        app.replay();
+        assert_eq!(app.seed, Some(283409412));
+        assert_eq!(app.board, Board::new(283409412, 3));
+
+        app.deal(1);
+        assert_eq!(app.seed, Some(1));
+        assert_eq!(app.board, Board::new(1, 3));
+        app.renew();
+        assert_eq!(app.board, Board::new(app.seed.unwrap(), 3));
+    }
+
+    #[test]
+    fn test_import_illegal_history() {
+        let board = "Waste: A♠\nTableau1: |2♠\nTableau2: |K♣";
+        let import = |moves: &str| {
+            KlondikeApp::import_history(&format!("{board}\nHistory: {moves}"))
+                .map_err(|err| err.to_string())
+        };
+        assert!(import("W:F1 T1:T2").is_err());
+        assert_eq!(
+            import("W:F1 T1:F1@30").err().unwrap(),
+            "Illegal move T1:F1@30 at step 2"
+        );
+        assert!(import("W:F1 W:F2").is_err());
+        assert!(import("T2:T2").is_err());
+        assert!(import("T9:F1").is_err());
+        assert!(import("T0:F1").is_err());
+        assert_eq!(import("W:F1 R").err().unwrap(), "Illegal move R at step 2");
+        let app = import("W:F1 T1:F1").unwrap();
+        assert_eq!(app.history.len(), 2);
+    }
+
+    #[test]
+    fn test_keyboard_picked_cleared() {
+        let board = "Waste: A♠\nTableau1: 5♦|2♠\nTableau2: |6♣";
+        let ctx = egui::Context::default();
+        let settings = Settings {
+            animation_speed: 0.0,
+            ..Default::default()
+        };
+        let mut app = KlondikeApp::with_settings(Board::parse(board).unwrap(), settings);
+        let focus = |pile, picked| KeyboardFocus {
+            pile,
+            count: 1,
+            picked,
+        };
+        app.keyboard_focus = Some(focus(PileId::Tableau(0), None));
+        app.keyboard_pick_or_drop(&ctx);
+        assert_eq!(
+            app.keyboard_focus.unwrap().picked,
+            Some((PileId::Tableau(0), 1))
+        );
+        app.apply_and_record_move(
+            &ctx,
+            app.build_game_move(PileId::Waste, PileId::Foundation(0), 1),
+        );
+        assert_eq!(app.keyboard_focus.unwrap().picked, None);
+
+        // A stack picked up before the board changed is checked again on drop
+        let picked = Some((PileId::Tableau(0), 2));
+        app.keyboard_focus = Some(focus(PileId::Tableau(1), picked));
+        app.keyboard_pick_or_drop(&ctx);
+        assert_eq!(app.board.tableaus[1].len(), 1);
+    }
+
+    #[test]
+    fn test_waste_fan() {
+        let mov = |source, destination, count| GameMove {
+            source,
+            destination,
+            count,
+            source_flip: false,
+        };
+        let draw = mov(PileId::Stock, PileId::Waste, 3);
+        let play = mov(PileId::Waste, PileId::Tableau(2), 1);
+        let other = mov(PileId::Tableau(0), PileId::Foundation(1), 1);
+        let left = |history: &[GameMove]| KlondikeApp::waste_fan(history).left();
+        assert_eq!(left(&[]), None);
+        assert_eq!(left(std::slice::from_ref(&draw)), Some(3));
+        let history = [draw.clone(), play.clone(), other, play.clone()];
+        assert_eq!(left(&history), Some(1));
+        let history = [draw.clone(), play.clone(), play.clone(), play.clone(), play];
+        assert_eq!(left(&history), Some(0));
+        let history = [draw, mov(PileId::Waste, PileId::Stock, 3)];
+        assert_eq!(left(&history), Some(0));
+    }
+}
@@ -1,11 +1,36 @@
-use anyhow::{Context, Result};
-use egui::{Color32, Pos2};
+use anyhow::{Context, Result, anyhow};
+use egui::{Color32, Pos2, Vec2};
+use klondike_common::{
+    action::{self, Action},
+    board::{ScoreMode, greenfelt_deck},
+    render,
+    talon::WasteFan,
+};
+use klondike_solver::{SolveResult, Solver};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+    },
+    time::Duration,
+};
 
 const SUITS: [char; 5] = ['♦', '♣', '♥', '♠', '?'];
 const RANKS: [char; 14] = [
     'A', '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K', '?',
 ];
 const MAX_RANK: u8 = 13;
+const KEY_REPEAT_DELAY: f64 = 0.4; // Time a key is held before it starts repeating
+const KEY_REPEAT_INTERVAL: f64 = 0.08; // Time between repeats of a held key
+// Shared with the rasterizer of the exported replays, at scale 1
+const CARD_SIZE: Vec2 = Vec2::new(render::CARD_WIDTH as f32, render::CARD_HEIGHT as f32);
+const CARD_PADDING: f32 = render::CARD_PADDING as f32;
+const TABLEAU_CARD_V_OFFSET: f32 = render::TABLEAU_CARD_V_OFFSET as f32; // Vertical offset of cards in tableau pile
+const WASTE_CARD_H_OFFSET: f32 = render::WASTE_CARD_H_OFFSET as f32; // Horizontal offset of cards in waste pile
+const MIN_FACE_DOWN_V_OFFSET: f32 = 6.0; // Vertical offset face-down cards shrink to in a tall tableau pile
+const MIN_FACE_UP_V_OFFSET: f32 = 14.0; // Vertical offset face-up cards shrink to in a tall tableau pile
+const MIN_LAYOUT_SCALE: f32 = 0.6; // Smallest scale of the cards, under which they get hard to read
 
 #[derive(Debug, Clone)]
 pub struct CardAnimation {
@@ -17,6 +42,196 @@ pub struct CardAnimation {
     pub source: PileId,
     pub destination: PileId,
     pub reverse: bool, // Whether it is a reverse animation (undo)
+    pub kind: AnimationKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnimationKind {
+    #[default]
+    Move,
+    SnapBack, // Cards returning to their source after an illegal drop
+    Cascade,  // Foundation cards bouncing off the table after a win, down to `end_pos.y`
+}
+
+/// Cards highlighted after they moved or as part of a hint
+#[derive(Debug, Clone, Copy)]
+pub struct Highlight {
+    pub pile: PileId,
+    pub count: usize, // Number of cards on top of the pile to highlight
+    pub start_time: f64,
+}
+
+/// Sizes of the cards and their offsets on the table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub scale: f32, // Of the sizes of the exported replays
+    pub card_size: Vec2,
+    pub padding: f32, // Space between the piles
+    pub tableau_v_offset: f32,
+    pub waste_h_offset: f32,
+    pub min_face_down_v_offset: f32,
+    pub min_face_up_v_offset: f32,
+}
+
+impl Layout {
+    pub fn scaled(scale: f32) -> Self {
+        Self {
+            scale,
+            card_size: CARD_SIZE * scale,
+            padding: CARD_PADDING * scale,
+            tableau_v_offset: TABLEAU_CARD_V_OFFSET * scale,
+            waste_h_offset: WASTE_CARD_H_OFFSET * scale,
+            min_face_down_v_offset: MIN_FACE_DOWN_V_OFFSET * scale,
+            min_face_up_v_offset: MIN_FACE_UP_V_OFFSET * scale,
+        }
+    }
+
+    /// Largest layout whose 7 tableau piles fit in `width`, down to `MIN_LAYOUT_SCALE`
+    pub fn fit_width(width: f32) -> Self {
+        let scale = width / (7.0 * CARD_SIZE.x + 6.0 * CARD_PADDING);
+        Self::scaled(scale.max(MIN_LAYOUT_SCALE))
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::scaled(1.0)
+    }
+}
+
+/// Repeats the action of a held key, like undo while `Z` is held
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeyRepeat {
+    next_time: Option<f64>, // When the held key fires next, none when it is released
+}
+
+impl KeyRepeat {
+    /// Whether the key fires this frame: once when pressed, then every
+    /// `KEY_REPEAT_INTERVAL` after `KEY_REPEAT_DELAY`, but only when `ready`.
+    pub fn update(&mut self, pressed: bool, down: bool, now: f64, ready: bool) -> bool {
+        match self.next_time {
+            _ if !down => {
+                // A tap may start and end within a single frame
+                let tapped = pressed && self.next_time.is_none();
+                self.next_time = None;
+                tapped
+            }
+            None => {
+                self.next_time = Some(now + KEY_REPEAT_DELAY);
+                true
+            }
+            Some(next_time) if now >= next_time && ready => {
+                self.next_time = Some(now + KEY_REPEAT_INTERVAL);
+                true
+            }
+            Some(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub snap_back_duration: f64, // Duration of the snap back animation, 0 to disable
+    pub highlight_duration: f64, // Duration of the last move highlight, 0 to disable
+    pub score_mode: ScoreMode,   // How the score in the toolbar is counted
+    pub animation_speed: f64,    // Speed factor of the card animations, 0 to apply moves instantly
+    pub autoplay_interval: f64,  // Duration between autoplay moves
+    pub theme: ThemeKind,        // Colors of the table and the cards
+    pub max_states: u32,         // Max states to explore when solving the deal
+    pub timeout: Option<Duration>, // Stop solving the deal after this duration
+    pub redeal_limit: Option<usize>, // Times the waste can be recycled into the stock, unlimited if None
+    pub win_cascade: bool,           // Bounce the foundation cards off the table after a win
+    pub left_handed: bool, // Mirror the table, the stock on the right and the first tableau pile last
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            snap_back_duration: 0.15,
+            highlight_duration: 1.0,
+            score_mode: ScoreMode::Standard,
+            animation_speed: 1.0,
+            autoplay_interval: 3.0,
+            theme: ThemeKind::default(),
+            max_states: 10_000_000,
+            timeout: None,
+            redeal_limit: None,
+            win_cascade: true,
+            left_handed: false,
+        }
+    }
+}
+
+/// Colors of the table and the cards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub card_back: Color32,
+    pub table_bg: Color32,
+    pub face_color: Color32, // Background of face-up cards
+    pub red_suit: Color32,
+    pub black_suit: Color32,
+    pub outline: Color32,     // Border of the cards and the empty piles
+    pub drop_target: Color32, // Border of the piles the dragged cards can be dropped on
+    pub bold_red_rank: bool, // Draw the ranks of red cards in bold, so the colors aren't the only cue
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThemeKind {
+    #[default]
+    ClassicGreen,
+    Dark,
+    HighContrast,
+}
+
+impl ThemeKind {
+    pub const ALL: [ThemeKind; 3] = [
+        ThemeKind::ClassicGreen,
+        ThemeKind::Dark,
+        ThemeKind::HighContrast,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ThemeKind::ClassicGreen => "Classic green",
+            ThemeKind::Dark => "Dark",
+            ThemeKind::HighContrast => "High contrast",
+        }
+    }
+
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemeKind::ClassicGreen => Theme {
+                card_back: Color32::from_rgb(0, 128, 128),
+                table_bg: Color32::from_rgb(0, 100, 50),
+                face_color: Color32::from_gray(248),
+                red_suit: Color32::RED,
+                black_suit: Color32::BLACK,
+                outline: Color32::from_gray(100),
+                drop_target: Color32::from_rgb(255, 215, 0),
+                bold_red_rank: false,
+            },
+            ThemeKind::Dark => Theme {
+                card_back: Color32::from_rgb(60, 60, 110),
+                table_bg: Color32::from_gray(27),
+                face_color: Color32::from_gray(190),
+                red_suit: Color32::from_rgb(170, 20, 20),
+                black_suit: Color32::from_gray(10),
+                outline: Color32::from_gray(120),
+                drop_target: Color32::from_rgb(0, 200, 80),
+                bold_red_rank: false,
+            },
+            ThemeKind::HighContrast => Theme {
+                card_back: Color32::from_rgb(0, 0, 170),
+                table_bg: Color32::BLACK,
+                face_color: Color32::WHITE,
+                red_suit: Color32::from_rgb(200, 0, 0),
+                black_suit: Color32::BLACK,
+                outline: Color32::WHITE,
+                drop_target: Color32::YELLOW,
+                bold_red_rank: true,
+            },
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -27,6 +242,46 @@ pub struct GameMove {
     pub source_flip: bool,
 }
 
+impl GameMove {
+    /// Whether the move turns the waste over into the stock
+    pub fn is_redeal(&self) -> bool {
+        self.source == PileId::Waste && self.destination == PileId::Stock
+    }
+
+    /// Follow the move in the cards of the last draw fanned out on the waste
+    pub fn update_waste_fan(&self, fan: &mut WasteFan) {
+        match (self.source, self.destination) {
+            (PileId::Stock, PileId::Waste) => fan.draw(self.count),
+            (PileId::Waste, PileId::Stock) => fan.redeal(),
+            (PileId::Waste, _) => fan.take(),
+            _ => {}
+        }
+    }
+
+    /// Format the move in the solution notation, e.g. `D`, `R`, `W:F1` or `T3:T5@2`
+    pub fn to_token(&self) -> String {
+        match (self.source, self.destination) {
+            (PileId::Stock, PileId::Waste) => "D".into(),
+            (PileId::Waste, PileId::Stock) => "R".into(),
+            (source, destination) => {
+                let mut token = format!("{}:{}", source.to_token(), destination.to_token());
+                if self.count > 1 {
+                    token.push_str(&format!("@{}", self.count));
+                }
+                token
+            }
+        }
+    }
+}
+
+/// Pile focused when playing with the keyboard
+#[derive(Debug, Clone, Copy)]
+pub struct KeyboardFocus {
+    pub pile: PileId,
+    pub count: usize, // Cards of the focused tableau stack, counted from the top
+    pub picked: Option<(PileId, usize)>, // Stack picked up with Space, waiting to be dropped
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Autofinish {
     #[default]
@@ -45,6 +300,56 @@ pub enum PileId {
     Tableau(usize),
 }
 
+impl PileId {
+    const TOP_ROW: [PileId; 6] = [
+        PileId::Stock,
+        PileId::Waste,
+        PileId::Foundation(0),
+        PileId::Foundation(1),
+        PileId::Foundation(2),
+        PileId::Foundation(3),
+    ];
+
+    /// Column of the pile on the table, column 2 of the top row is empty
+    fn column(self) -> usize {
+        match self {
+            PileId::Stock => 0,
+            PileId::Waste => 1,
+            PileId::Foundation(i) => i + 3,
+            PileId::Tableau(i) => i,
+        }
+    }
+
+    /// Pile reached by moving `dx` piles sideways and `dy` rows down with the arrow keys
+    pub fn step(self, dx: isize, dy: isize) -> PileId {
+        let column = self.column();
+        match (self, dy) {
+            (PileId::Tableau(_), ..=-1) => Self::TOP_ROW
+                .into_iter()
+                .min_by_key(|pile| pile.column().abs_diff(column))
+                .unwrap_or(PileId::Stock),
+            (PileId::Tableau(_), _) => PileId::Tableau(column.saturating_add_signed(dx).min(6)),
+            (_, 1..) => PileId::Tableau(column),
+            _ => {
+                let idx = Self::TOP_ROW
+                    .iter()
+                    .position(|&pile| pile == self)
+                    .unwrap_or(0);
+                Self::TOP_ROW[idx.saturating_add_signed(dx).min(Self::TOP_ROW.len() - 1)]
+            }
+        }
+    }
+
+    pub fn to_token(self) -> String {
+        match self {
+            PileId::Stock => "S".into(),
+            PileId::Waste => "W".into(),
+            PileId::Foundation(i) => format!("F{}", i + 1),
+            PileId::Tableau(i) => format!("T{}", i + 1),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Board {
     pub stock: Vec<Card>,
@@ -57,42 +362,7 @@ pub struct Board {
 impl Board {
     pub fn new(seed: u32, draw_count: usize) -> Self {
         println!("GameId: {seed}");
-        let mut current_seed = seed;
-        let mut rnd = || {
-            current_seed = ((current_seed as u64 * 16807) % 0x7fffffff) as u32;
-            current_seed
-        };
-        let mut deck: [Card; 52] = std::array::from_fn(|i| {
-            let card = Card::new_with_id(i as u8);
-            if card.suit() == 0 {
-                Card::new_with_rank_suit(card.rank(), 1)
-            } else if card.suit() == 1 {
-                Card::new_with_rank_suit(card.rank(), 0)
-            } else {
-                card
-            }
-        });
-
-        for _ in 0..7 {
-            for j in 0..52 {
-                let k = (rnd() % 52) as usize;
-                deck.swap(j, k);
-            }
-        }
-
-        deck.rotate_left(24);
-
-        let mut orig: i32 = 27;
-        for i in 0..7_i32 {
-            let mut pos = (i + 1) * (i + 2) / 2 - 1;
-            for j in (0..=(6 - i)).rev() {
-                if j >= i {
-                    deck.swap(pos as usize, orig as usize);
-                }
-                orig -= 1;
-                pos += 6 - j + 1;
-            }
-        }
+        let deck = greenfelt_deck(seed).map(|card| Card::new_with_id(card.id()));
 
         let mut tableaus: [Vec<Card>; 7] = Default::default();
 
@@ -231,23 +501,224 @@ impl Board {
         Ok(cards)
     }
 
+    pub fn to_pretty_string(&self) -> String {
+        let mut output = String::new();
+        let cards_str = |cards: &[Card]| -> String {
+            cards
+                .iter()
+                .map(|card| {
+                    let (rank, suit) = card.symbols();
+                    format!("{rank}{suit}")
+                })
+                .collect()
+        };
+
+        if !self.stock.is_empty() {
+            output.push_str(&format!("Stock: {}\n", cards_str(&self.stock)));
+        }
+        if !self.waste.is_empty() {
+            output.push_str(&format!("Waste: {}\n", cards_str(&self.waste)));
+        }
+        for (i, foundation) in self.foundations.iter().enumerate() {
+            if let Some(card) = foundation.last() {
+                output.push_str(&format!(
+                    "Foundation{}: {}\n",
+                    i + 1,
+                    cards_str(std::slice::from_ref(card))
+                ));
+            }
+        }
+        for (i, tableau) in self.tableaus.iter().enumerate() {
+            if tableau.is_empty() {
+                output.push_str(&format!("Tableau{}:\n", i + 1));
+                continue;
+            }
+            let sep = tableau
+                .iter()
+                .position(|card| card.face_up)
+                .unwrap_or(tableau.len());
+            output.push_str(&format!("Tableau{}: {}", i + 1, cards_str(&tableau[..sep])));
+            if sep < tableau.len() {
+                output.push('|');
+                output.push_str(&cards_str(&tableau[sep..]));
+            }
+            output.push('\n');
+        }
+        output.push_str(&format!("DrawCount: {}", self.draw_count));
+
+        output
+    }
+
+    /// Convert to the board used by the solver
+    pub fn to_solver_board(&self) -> Result<klondike_common::board::Board> {
+        Ok(klondike_common::board::Board::parse(
+            &self.to_pretty_string(),
+        )?)
+    }
+
+    pub fn pile(&self, pile_id: PileId) -> &Vec<Card> {
+        match pile_id {
+            PileId::Stock => &self.stock,
+            PileId::Waste => &self.waste,
+            PileId::Foundation(i) => &self.foundations[i],
+            PileId::Tableau(i) => &self.tableaus[i],
+        }
+    }
+
+    pub fn pile_mut(&mut self, pile_id: PileId) -> &mut Vec<Card> {
+        match pile_id {
+            PileId::Stock => &mut self.stock,
+            PileId::Waste => &mut self.waste,
+            PileId::Foundation(i) => &mut self.foundations[i],
+            PileId::Tableau(i) => &mut self.tableaus[i],
+        }
+    }
+
+    /// Execute a game move, or undo it when `reverse`
+    pub fn apply_move(&mut self, game_move: &GameMove, reverse: bool) {
+        let GameMove {
+            source,
+            destination,
+            count,
+            source_flip,
+        } = *game_move;
+        let (from, to) = match reverse {
+            false => (source, destination),
+            true => (destination, source),
+        };
+        let mut cards = self.take_cards(from, count);
+        if reverse
+            && source_flip
+            && let PileId::Tableau(source_idx) = source
+            && let Some(card) = self.tableaus[source_idx].last_mut()
+        {
+            card.face_up = false;
+        }
+        if matches!(
+            (source, destination),
+            (PileId::Stock, PileId::Waste) | (PileId::Waste, PileId::Stock)
+        ) {
+            cards.reverse();
+            for card in cards.iter_mut() {
+                card.face_up = to == PileId::Waste;
+            }
+        }
+        self.pile_mut(to).extend(cards);
+        if !reverse {
+            self.flip_tableau_top_card(source);
+        }
+    }
+
+    pub fn flip_tableau_top_card(&mut self, source: PileId) {
+        if let PileId::Tableau(i) = source
+            && let Some(card) = self.tableaus[i].last_mut()
+        {
+            card.face_up = true;
+        }
+    }
+
+    pub fn take_cards(&mut self, source: PileId, count: usize) -> Vec<Card> {
+        let pile = self.pile_mut(source);
+        let start_idx = pile.len() - count;
+        pile.drain(start_idx..).collect()
+    }
+
     pub fn score(&self) -> u8 {
         self.foundations.iter().map(|f| f.len() as u8).sum()
     }
 
+    /// Foundation moves that can never strand a card, see [`action::safe_foundation_moves`]
+    pub fn safe_foundation_moves(&self) -> Vec<Action> {
+        self.to_solver_board()
+            .map(|board| action::safe_foundation_moves(&board))
+            .unwrap_or_default()
+    }
+
+    /// See [`klondike_common::board::Board::can_autofinish`]
     pub fn can_autofinish(&self) -> bool {
-        self.stock.is_empty()
-            && (self.waste.len() <= self.draw_count && self.waste.is_sorted())
-            && self.score() < 51
-            && self
-                .tableaus
-                .iter()
-                .all(|pile| pile.iter().all(|card| card.face_up))
+        self.to_solver_board()
+            .is_ok_and(|board| board.can_autofinish())
     }
 }
 
 pub type SolutionMove = (PileId, PileId, usize);
 
+/// Convert a solver action to a solution move, draws and redeals have a count of 0
+pub fn solution_move(action: &Action) -> SolutionMove {
+    match *action {
+        Action::WasteToFoundation(f) => (PileId::Waste, PileId::Foundation(f), 1),
+        Action::WasteToTableau(t) => (PileId::Waste, PileId::Tableau(t), 1),
+        Action::TableauToFoundation(t, f) => (PileId::Tableau(t), PileId::Foundation(f), 1),
+        Action::FoundationToTableau(f, t) => (PileId::Foundation(f), PileId::Tableau(t), 1),
+        Action::TableauToTableau(from, to, count) => {
+            (PileId::Tableau(from), PileId::Tableau(to), count)
+        }
+        Action::Draw => (PileId::Stock, PileId::Waste, 0),
+        Action::Redeal => (PileId::Waste, PileId::Stock, 0),
+    }
+}
+
+/// A solver running on a background thread, cancelled when dropped
+pub struct BackgroundSolve {
+    receiver: Receiver<Result<SolveResult>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl BackgroundSolve {
+    pub fn spawn(
+        board: &Board,
+        max_states: u32,
+        timeout: Option<Duration>,
+        redeal_limit: Option<usize>,
+    ) -> Result<Self> {
+        let board = board.to_solver_board()?;
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancel_clone = Arc::clone(&cancel);
+        std::thread::spawn(move || {
+            let mut solver = Solver::new();
+            solver.set_board(board);
+            solver.set_cancel_token(cancel_clone);
+            if let Some(timeout) = timeout {
+                solver.set_timeout(timeout);
+            }
+            if let Some(redeal_limit) = redeal_limit {
+                solver.set_max_rounds(redeal_limit.saturating_add(1));
+            }
+            let _ = sender.send(solver.solve(max_states, true).map_err(Into::into));
+        });
+        Ok(Self { receiver, cancel })
+    }
+
+    /// Get the result if the solver has finished
+    pub fn poll(&self) -> Option<Result<SolveResult>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(anyhow!("Solver thread exited"))),
+        }
+    }
+}
+
+impl Drop for BackgroundSolve {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Fewest moves to win the game, redeals not counted
+#[derive(Default)]
+pub enum Par {
+    #[default]
+    Unknown,
+    Solving(BackgroundSolve),
+    Known {
+        moves: usize,
+        minimal: bool,
+    }, // Not minimal when only the best solution found
+    Unavailable,
+}
+
 pub fn parse_moves(s: &str) -> Result<Vec<SolutionMove>> {
     let mut moves = Vec::new();
     for part in s.split_whitespace().filter(|s| !s.is_empty()) {
@@ -288,17 +759,19 @@ fn parse_pile_id(s: &str) -> Result<PileId> {
     if s == "W" {
         return Ok(PileId::Waste);
     }
+    let index = |num: &str, count: usize| {
+        num.parse::<usize>()
+            .ok()
+            .filter(|n| (1..=count).contains(n))
+            .map(|n| n - 1)
+    };
     if let Some(stripped) = s.strip_prefix('T') {
-        let num = stripped
-            .parse::<usize>()
-            .with_context(|| format!("Invalid tableau index: {stripped}"))?
-            - 1;
+        let num =
+            index(stripped, 7).with_context(|| format!("Invalid tableau index: {stripped}"))?;
         Ok(PileId::Tableau(num))
     } else if let Some(stripped) = s.strip_prefix('F') {
-        let num = stripped
-            .parse::<usize>()
-            .with_context(|| format!("Invalid foundation index: {stripped}"))?
-            - 1;
+        let num =
+            index(stripped, 4).with_context(|| format!("Invalid foundation index: {stripped}"))?;
         Ok(PileId::Foundation(num))
     } else {
         anyhow::bail!("Invalid pile identifier: {}", s)
@@ -348,12 +821,16 @@ impl Card {
     }
 
     pub fn color(&self) -> Color32 {
-        match self.suit() {
-            0 | 2 => Color32::RED,
-            _ => Color32::BLACK,
+        match self.is_red() {
+            true => Color32::RED,
+            false => Color32::BLACK,
         }
     }
 
+    pub fn is_red(&self) -> bool {
+        matches!(self.suit(), 0 | 2)
+    }
+
     pub fn is_ace(&self) -> bool {
         self.rank() == 0
     }
@@ -372,3 +849,104 @@ impl PartialOrd for Card {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use klondike_solver::solve;
+
+    #[test]
+    fn test_key_repeat() {
+        let mut repeat = KeyRepeat::default();
+        assert!(repeat.update(true, true, 0.0, true));
+        assert!(!repeat.update(false, true, 0.3, true));
+        assert!(!repeat.update(false, true, 0.45, false));
+        assert!(repeat.update(false, true, 0.5, true));
+        assert!(!repeat.update(false, true, 0.55, true));
+        assert!(repeat.update(false, true, 0.6, true));
+        assert!(!repeat.update(true, false, 0.65, true));
+        assert!(repeat.update(true, false, 1.0, true));
+        assert!(!repeat.update(false, false, 1.1, true));
+    }
+
+    #[test]
+    fn test_layout_fit_width() {
+        let width = 7.0 * CARD_SIZE.x + 6.0 * CARD_PADDING;
+        assert_eq!(Layout::fit_width(width), Layout::default());
+        let layout = Layout::fit_width(width * 1.5);
+        assert_eq!(layout.card_size, CARD_SIZE * 1.5);
+        assert_eq!(layout.tableau_v_offset, TABLEAU_CARD_V_OFFSET * 1.5);
+        assert_eq!(Layout::fit_width(100.0).scale, MIN_LAYOUT_SCALE);
+    }
+
+    #[test]
+    fn test_pile_step() {
+        assert_eq!(PileId::Stock.step(-1, 0), PileId::Stock);
+        assert_eq!(PileId::Waste.step(1, 0), PileId::Foundation(0));
+        assert_eq!(PileId::Foundation(3).step(1, 0), PileId::Foundation(3));
+        assert_eq!(PileId::Foundation(1).step(0, 1), PileId::Tableau(4));
+        assert_eq!(PileId::Tableau(4).step(0, -1), PileId::Foundation(1));
+        assert_eq!(PileId::Tableau(2).step(0, -1), PileId::Waste);
+        assert_eq!(PileId::Tableau(0).step(-1, 0), PileId::Tableau(0));
+        assert_eq!(PileId::Tableau(6).step(1, 0), PileId::Tableau(6));
+        assert_eq!(PileId::Tableau(6).step(0, 1), PileId::Tableau(6));
+    }
+
+    #[test]
+    fn test_safe_foundation_moves() {
+        let board = Board::new(283409412, 1);
+        let actions = board.safe_foundation_moves();
+        assert_eq!(actions, [Action::TableauToFoundation(6, 0)]);
+        assert_eq!(
+            solution_move(&actions[0]),
+            (PileId::Tableau(6), PileId::Foundation(0), 1)
+        );
+    }
+
+    #[test]
+    fn test_deal_matches_common() {
+        for seed in [1, 4, 283409412, 0x7fff_fffe] {
+            let board = Board::new(seed, 1);
+            let common = klondike_common::board::Board::new_from_seed(seed);
+            let ids = |cards: &[Card]| cards.iter().map(|card| card.id).collect::<Vec<_>>();
+            let common_ids = |cards: &[klondike_common::board::Card]| {
+                cards.iter().map(|card| card.id()).collect::<Vec<_>>()
+            };
+            assert_eq!(ids(&board.stock), common_ids(&common.stock));
+            for (tableau, common) in board.tableaus.iter().zip(&common.tableaus) {
+                assert_eq!(ids(tableau), common_ids(&common.cards));
+            }
+            assert_eq!(board.to_solver_board().unwrap(), common);
+        }
+    }
+
+    #[test]
+    fn test_autofinish_position_roundtrip() {
+        const BOARD_STR: &str = r#"Waste: 9♠T♦
+Foundation1: 9♦
+Foundation2: 9♣
+Foundation3: 9♥
+Foundation4: 8♠
+Tableau1: |K♠Q♥J♣
+Tableau2: |K♥Q♣J♦T♠
+Tableau3: |K♣Q♦J♠T♥
+Tableau4: |K♦Q♠J♥T♣
+Tableau5:
+Tableau6:
+Tableau7:
+DrawCount: 3"#;
+
+        let board = Board::parse(BOARD_STR).unwrap();
+        assert!(board.can_autofinish());
+        assert_eq!(board.to_pretty_string(), BOARD_STR);
+
+        let solver_board = board.to_solver_board().unwrap();
+        assert_eq!(solver_board.to_pretty_string(), BOARD_STR);
+        let result = solve(solver_board, 10_000, true).unwrap();
+        assert_eq!(result.actions.len(), 52 - board.score() as usize);
+        assert!(result.actions.iter().all(|action| matches!(
+            action,
+            Action::WasteToFoundation(_) | Action::TableauToFoundation(_, _)
+        )));
+    }
+}
@@ -1,5 +1,7 @@
 mod app;
 mod common;
+mod session;
+mod stats;
 
 use crate::common::Board;
 use crate::{
@@ -9,20 +11,31 @@ use crate::{
 
 use anyhow::Context;
 use clap::Parser;
+use klondike_common::board::greenfelt_seed;
 use std::{
     io::{IsTerminal, Read, stdin},
     path::PathBuf,
+    time::Duration,
 };
 
 #[derive(Parser)]
 #[command(author, version, about)]
 struct Cli {
-    /// Game ID from greenfelt.net/klondike (e.g. 283409412)
-    #[arg(short, long, value_name = "SEED")]
+    /// Game ID or URL from greenfelt.net/klondike (e.g. 283409412)
+    #[arg(short, long, value_name = "SEED", value_parser = greenfelt_seed)]
     greenfelt: Option<u32>,
-    /// Cards drawn per turn (1 or 3)
+    /// Cards drawn per turn, e.g. 1 or 3
     #[arg(short, long, value_name = "NUM", default_value_t = 1)]
     draw: usize,
+    /// Max states to explore when solving the deal from the toolbar
+    #[arg(short = 's', long, default_value_t = 10_000_000, value_name = "NUM")]
+    max_states: u32,
+    /// Stop solving the deal after this many seconds
+    #[arg(short, long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+    /// Max times the waste can be recycled into the stock (0 = single pass)
+    #[arg(long, value_name = "NUM")]
+    max_redeals: Option<usize>,
     /// Path to a game state file to load
     file: Option<PathBuf>,
 }
@@ -31,36 +44,51 @@ fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
     let seed = cli.greenfelt.unwrap_or(rand::random());
     let draw_count = cli.draw;
+    if draw_count == 0 {
+        anyhow::bail!("Draw count must be at least 1.");
+    }
 
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([710.0, 775.0])
-            .with_resizable(false),
+            .with_min_inner_size([440.0, 480.0]),
         ..Default::default()
     };
 
-    let (board, solution) = match cli.file {
-        Some(path) => {
-            let content = std::fs::read_to_string(path)?;
-            parse(content)?
+    let content = match cli.file {
+        Some(path) => Some(std::fs::read_to_string(path)?),
+        None if !stdin().is_terminal() => {
+            let mut content = String::new();
+            stdin()
+                .read_to_string(&mut content)
+                .context("Failed to read from stdin")?;
+            Some(content)
+        }
+        None => None,
+    };
+    let mut app = match content {
+        Some(content) if KlondikeApp::is_history(&content) => {
+            KlondikeApp::import_history(&content)?
+        }
+        Some(content) => {
+            let (board, solution) = parse(content)?;
+            let mut app = KlondikeApp::new(board);
+            if let Some(moves) = solution {
+                app.solve(moves);
+            }
+            app
         }
         None => {
-            if !stdin().is_terminal() {
-                let mut content = String::new();
-                stdin()
-                    .read_to_string(&mut content)
-                    .context("Failed to read from stdin")?;
-                parse(content)?
-            } else {
-                (Board::new(seed, draw_count), None)
+            let mut app = KlondikeApp::from_seed(seed, draw_count);
+            if cli.greenfelt.is_none() {
+                app.offer_resume();
             }
+            app
         }
     };
-    let mut app = KlondikeApp::new(board);
-
-    if let Some(moves) = solution {
-        app.solve(moves);
-    }
+    app.load_stats();
+    app.set_solve_limits(cli.max_states, cli.timeout.map(Duration::from_secs));
+    app.set_redeal_limit(cli.max_redeals);
 
     eframe::run_native(
         "Klondike Solitaire",
@@ -0,0 +1,39 @@
+use crate::stats::config_path;
+use anyhow::{Context, Result};
+
+const SESSION_FILE: &str = "last_game.txt"; // Exported history of the game left unfinished
+
+/// Load the game left unfinished by the last session, if any
+pub fn load() -> Result<Option<String>> {
+    let path = config_path(SESSION_FILE)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    std::fs::read_to_string(&path)
+        .map(Some)
+        .with_context(|| format!("Failed to read '{}'", path.display()))
+}
+
+/// Save the game in progress, through a temporary file so that a crash mid-write
+/// never leaves a truncated game behind
+pub fn save(content: &str) -> Result<()> {
+    let path = config_path(SESSION_FILE)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create '{}'", dir.display()))?;
+    }
+    let tmp_path = path.with_extension("txt.tmp");
+    std::fs::write(&tmp_path, content)
+        .with_context(|| format!("Failed to save game to '{}'", tmp_path.display()))?;
+    std::fs::rename(&tmp_path, &path)
+        .with_context(|| format!("Failed to save game to '{}'", path.display()))
+}
+
+/// Forget the last game, once it is won or replaced by a new one
+pub fn clear() -> Result<()> {
+    let path = config_path(SESSION_FILE)?;
+    if !path.exists() {
+        return Ok(());
+    }
+    std::fs::remove_file(&path).with_context(|| format!("Failed to remove '{}'", path.display()))
+}
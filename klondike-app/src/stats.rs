@@ -0,0 +1,151 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const CONFIG_DIR: &str = "klondike"; // Directory in the config dir holding the stats and the last game
+const STATS_FILE: &str = "stats.json";
+
+/// Statistics of the games played with one draw count
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DrawStats {
+    pub played: u32, // Games won, plus games left unfinished for another one
+    pub won: u32,
+    pub best_time: Option<f64>, // Fastest win in seconds
+    pub streak: u32,            // Wins in a row up to the last game
+    pub best_streak: u32,
+}
+
+impl DrawStats {
+    pub fn win_rate(&self) -> f64 {
+        match self.played {
+            0 => 0.0,
+            played => self.won as f64 / played as f64,
+        }
+    }
+
+    fn record_win(&mut self, time: f64) {
+        self.played += 1;
+        self.won += 1;
+        self.best_time = Some(self.best_time.map_or(time, |best| best.min(time)));
+        self.streak += 1;
+        self.best_streak = self.best_streak.max(self.streak);
+    }
+
+    fn record_loss(&mut self) {
+        self.played += 1;
+        self.streak = 0;
+    }
+}
+
+/// Statistics across games, tracked separately for draw 1 and draw 3
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub draw1: DrawStats,
+    pub draw3: DrawStats,
+}
+
+impl Stats {
+    /// Load the stats from the config dir, starting afresh if there are none yet.
+    /// A file that fails to parse is moved aside, so the next save doesn't overwrite it
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        serde_json::from_str(&content).or_else(|err| {
+            let backup = path.with_extension("json.bak");
+            std::fs::rename(&path, &backup)
+                .with_context(|| format!("Failed to back up '{}'", path.display()))?;
+            Err(err).with_context(|| {
+                format!(
+                    "Failed to parse stats '{}', moved to '{}'",
+                    path.display(),
+                    backup.display()
+                )
+            })
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create '{}'", dir.display()))?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        // Renamed into place, so a crash mid-write can't leave a truncated file behind
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, content)
+            .with_context(|| format!("Failed to save stats to '{}'", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to save stats to '{}'", path.display()))
+    }
+
+    pub fn draw(&self, draw_count: usize) -> &DrawStats {
+        match draw_count {
+            3 => &self.draw3,
+            _ => &self.draw1,
+        }
+    }
+
+    fn draw_mut(&mut self, draw_count: usize) -> &mut DrawStats {
+        match draw_count {
+            3 => &mut self.draw3,
+            _ => &mut self.draw1,
+        }
+    }
+
+    /// Record a game won in `time` seconds
+    pub fn record_win(&mut self, draw_count: usize, time: f64) {
+        self.draw_mut(draw_count).record_win(time);
+    }
+
+    /// Record a game left unfinished, which breaks the win streak
+    pub fn record_loss(&mut self, draw_count: usize) {
+        self.draw_mut(draw_count).record_loss();
+    }
+
+    fn path() -> Result<PathBuf> {
+        config_path(STATS_FILE)
+    }
+}
+
+/// Path of a file kept by the app in the config dir
+pub fn config_path(file: &str) -> Result<PathBuf> {
+    let dir = dirs::config_dir().context("No config directory found")?;
+    Ok(dir.join(CONFIG_DIR).join(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut stats = Stats::default();
+        stats.record_win(3, 120.0);
+        stats.record_win(3, 90.0);
+        stats.record_loss(3);
+        stats.record_win(3, 100.0);
+        stats.record_loss(1);
+
+        let draw3 = stats.draw(3);
+        assert_eq!((draw3.played, draw3.won), (4, 3));
+        assert_eq!(draw3.best_time, Some(90.0));
+        assert_eq!((draw3.streak, draw3.best_streak), (1, 2));
+        assert_eq!(draw3.win_rate(), 0.75);
+        assert_eq!(stats.draw(1).played, 1);
+        assert_eq!(stats.draw(1).win_rate(), 0.0);
+
+        let json = serde_json::to_string(&stats).unwrap();
+        assert_eq!(serde_json::from_str::<Stats>(&json).unwrap(), stats);
+        assert_eq!(
+            serde_json::from_str::<Stats>("{}").unwrap(),
+            Stats::default()
+        );
+    }
+}
@@ -3,49 +3,377 @@ mod utils;
 use crate::utils::*;
 
 use anyhow::{Context, Result, bail};
-use clap::Parser;
-use klondike_common::{action::format_actions, board::Board};
+use clap::{Parser, Subcommand, ValueEnum};
+use klondike_common::{
+    action::{
+        Action, action_steps, action_tokens, apply_action, describe_action, format_actions,
+        parse_actions,
+    },
+    board::{Board, MAX_CARD, greenfelt_seed},
+    render::write_gif,
+    talon::WasteFan,
+};
+use klondike_solver::{SeedLimits, SolveError, Solver, solve_seeds, winnability_stats};
+use serde::Serialize;
 
 use std::{
     io::{IsTerminal, Read, stdin},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
 #[derive(Parser)]
 #[command(author, version, about)]
 struct Cli {
-    /// Game ID from greenfelt.net/klondike (e.g. 283409412)
-    #[arg(short, long, value_name = "SEED")]
+    /// Game ID or URL from greenfelt.net/klondike (e.g. 283409412)
+    #[arg(short, long, value_name = "SEED", value_parser = greenfelt_seed)]
     greenfelt: Option<u32>,
-    /// Cards drawn per turn (1 or 3)
-    #[arg(short, long, value_name = "NUM")]
+    /// Cards drawn per turn, e.g. 1 or 3
+    #[arg(short, long, value_name = "NUM", global = true)]
     draw: Option<usize>,
     /// Max states to explore (~1 GB per 64 million states)
-    #[arg(short = 's', long, default_value_t = 100_000_000, value_name = "NUM")]
+    #[arg(
+        short = 's',
+        long,
+        default_value_t = 100_000_000,
+        value_name = "NUM",
+        global = true
+    )]
     max_states: u32,
+    /// Stop solving after this many seconds
+    #[arg(short, long, value_name = "SECONDS", global = true)]
+    timeout: Option<u64>,
+    /// Max times the waste can be recycled into the stock (0 = single pass)
+    #[arg(long, value_name = "NUM", global = true)]
+    max_redeals: Option<usize>,
+    /// Only accept a solution of at most this many moves, redeals not counted
+    #[arg(long, value_name = "NUM", global = true)]
+    max_moves: Option<u8>,
     /// Stop at first found solution (may not be minimal)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     fast: bool,
+    /// Play safe foundation moves first, then solve the rest
+    #[arg(long, global = true)]
+    greedy: bool,
+    /// Never move cards back from the foundations, faster but may miss solutions
+    #[arg(long, global = true)]
+    no_foundation_undo: bool,
     /// Preview initial game state without solving
     #[arg(short, long)]
     preview: bool,
+    /// Preview initial game state with column indices and a legend
+    #[arg(long)]
+    annotated: bool,
+    /// Show the face-down cards face up in the boards printed, as in Thoughtful Klondike
+    #[arg(long)]
+    thoughtful: bool,
+    /// Format of the solution printed
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Step through the solution, printing each move with the resulting board
+    #[arg(short, long, conflicts_with = "format")]
+    verbose: bool,
+    /// Color the boards printed
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto, global = true)]
+    color: ColorChoice,
     /// Path to a game state file to solve
     file: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// The board, a summary line and the moves in columns
+    Text,
+    /// A single JSON object, e.g. for piping into `jq`
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    /// Color when stdout is a terminal and `NO_COLOR` is unset
+    Auto,
+    Always,
+    Never,
+}
+
+/// JSON output of a solve with `--format json`
+#[derive(Serialize)]
+struct SolveJson {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u32>,
+    draw_count: usize,
+    solvable: bool,
+    minimal: bool,
+    states: Option<i32>,     // Unknown for a deal proven unsolvable
+    elapsed_ms: Option<u64>, // Unknown for a deal proven unsolvable
+    moves: Vec<String>,      // Steps in the notation of `format_actions`, e.g. `3D` or `T3:T5@2`
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Solve a range of greenfelt seeds and print a CSV of the results
+    Batch {
+        /// First seed to solve
+        #[arg(long, value_name = "SEED")]
+        from: u32,
+        /// Last seed to solve (inclusive)
+        #[arg(long, value_name = "SEED")]
+        to: u32,
+    },
+    /// Count the winnable greenfelt deals of a range of seeds, solving each for the fewest moves
+    Stats {
+        /// First seed to solve
+        #[arg(long, value_name = "SEED")]
+        from: u32,
+        /// Last seed to solve (inclusive)
+        #[arg(long, value_name = "SEED")]
+        to: u32,
+    },
+    /// Render the board after each move of a solution as the frames of an animated GIF
+    Replay {
+        /// Solution in the notation printed by the solver, or a file containing it
+        solution: String,
+        /// Path of the GIF to write
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+        /// Time each frame is shown, in milliseconds
+        #[arg(long, default_value_t = 500, value_name = "MS")]
+        delay: u64,
+    },
+    /// Solve each board of a file, the boards separated by blank lines or `---` lines
+    SolveAll {
+        /// Path to the file of boards, read from stdin if omitted
+        file: Option<PathBuf>,
+    },
+    /// Print random greenfelt deals, e.g. ones that can be won in few moves
+    Generate {
+        /// Number of deals to print
+        #[arg(long, default_value_t = 1, value_name = "NUM")]
+        count: usize,
+        /// Only print deals proven winnable within `--max-states`
+        #[arg(long)]
+        solvable: bool,
+        /// Give up after trying this many deals
+        #[arg(long, default_value_t = 1000, value_name = "NUM")]
+        max_attempts: usize,
+    },
+    /// Replay a solution on a board, failing unless it legally wins the game
+    Verify {
+        /// Path to the game state file
+        board: PathBuf,
+        /// Solution in the notation printed by the solver, or a file containing it
+        solution: String,
+    },
 }
 
 fn main() -> Result<()> {
     let Cli {
         max_states,
+        timeout,
+        max_redeals,
+        max_moves,
         fast,
+        greedy,
+        no_foundation_undo,
         preview,
+        annotated,
+        thoughtful,
+        format,
+        verbose,
+        color,
         greenfelt,
         draw,
         file,
+        command,
     } = Cli::parse();
+    let color = match color {
+        ColorChoice::Auto => stdout_supports_color(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+
+    if let Some(command) = command {
+        let draw_count = draw.unwrap_or(1);
+        if draw_count == 0 {
+            bail!("Draw count must be at least 1.");
+        }
+        // The solve options are global, but only some subcommands solve with them.
+        // Rejected where unused rather than silently ignored.
+        let solves = matches!(command, Command::SolveAll { .. });
+        let solves_seeds = matches!(
+            command,
+            Command::Batch { .. } | Command::Stats { .. } | Command::Generate { .. }
+        );
+        let unsupported = [
+            ("--timeout", timeout.is_some(), solves || solves_seeds),
+            (
+                "--max-redeals",
+                max_redeals.is_some(),
+                solves || solves_seeds,
+            ),
+            (
+                "--max-moves",
+                max_moves.is_some(),
+                solves || matches!(command, Command::Generate { .. }),
+            ),
+            ("--greedy", greedy, solves),
+            ("--no-foundation-undo", no_foundation_undo, solves),
+            // The subcommands only print text
+            ("--format json", format == Format::Json, false),
+        ];
+        if let Some((flag, ..)) = unsupported.iter().find(|(_, set, used)| *set && !used) {
+            bail!("`{flag}` is not supported by this subcommand.");
+        }
+        let options = SolveOptions {
+            timeout: timeout.map(Duration::from_secs),
+            greedy,
+            max_redeals,
+            max_moves,
+            no_foundation_undo,
+            quiet: true,
+            color,
+            thoughtful: false,
+        };
+        let limits = options.seed_limits();
+        return match command {
+            Command::Batch { from, to } => batch(from, to, draw_count, max_states, limits),
+            Command::Stats { from, to } => stats(from, to, draw_count, max_states, limits),
+            Command::SolveAll { file } => {
+                let content = match file {
+                    Some(file) => std::fs::read_to_string(&file)
+                        .with_context(|| format!("Failed to read '{}'", file.display()))?,
+                    None => {
+                        let mut content = String::new();
+                        stdin()
+                            .read_to_string(&mut content)
+                            .context("Failed to read from stdin")?;
+                        content
+                    }
+                };
+                solve_all(&content, draw, max_states, !fast, options)
+            }
+            Command::Generate {
+                count,
+                solvable,
+                max_attempts,
+            } => generate(
+                count,
+                solvable,
+                max_attempts,
+                draw_count,
+                max_states,
+                &options,
+            ),
+            Command::Replay {
+                solution,
+                output,
+                delay,
+            } => {
+                let board = load_board(file, greenfelt, draw)?;
+                replay(board, &solution, &output, Duration::from_millis(delay))
+            }
+            Command::Verify { board, solution } => {
+                let board = load_board(Some(board), None, draw)?;
+                verify(board, &solution, color)
+            }
+        };
+    }
 
+    let board = load_board(file, greenfelt, draw)?;
+    let mut initial = board.clone();
+    if thoughtful {
+        initial.make_thoughtful();
+    }
+    if annotated {
+        println!("{}", initial.to_annotated_string());
+        return Ok(());
+    }
+    if preview {
+        println!("{}", format_board(&board, color, thoughtful));
+        return Ok(());
+    }
+    board.validate().context("Invalid board")?;
+    let draw_count = board.draw_count();
+    let options = SolveOptions {
+        timeout: timeout.map(Duration::from_secs),
+        greedy,
+        max_redeals,
+        max_moves,
+        no_foundation_undo,
+        quiet: format == Format::Json,
+        color,
+        thoughtful,
+    };
+    let result = do_solve(board, max_states, !fast, options);
+    if format == Format::Json {
+        return print_json(greenfelt, draw_count, result);
+    }
+    let actions = result?.actions;
+    println!("{}", format_actions(&actions));
+    if verbose {
+        print_steps(initial, &actions);
+    }
+
+    Ok(())
+}
+
+/// Print each step of the solution, numbered like [`format_actions`], and the board after it
+fn print_steps(mut board: Board, actions: &[Action]) {
+    for (i, step) in action_steps(actions).into_iter().enumerate() {
+        let description = match step {
+            [Action::Draw, _, ..] => {
+                step.iter()
+                    .for_each(|action| apply_action(&mut board, action));
+                let card = board.waste.last().map(|c| c.to_pretty_string());
+                format!("Draw ×{} → {}", step.len(), card.unwrap_or_default())
+            }
+            _ => {
+                let description = describe_action(&board, &step[0]);
+                apply_action(&mut board, &step[0]);
+                description
+            }
+        };
+        println!("{:>3}. {description}", i + 1);
+        for line in board.to_compact_string().lines() {
+            println!("     {line}");
+        }
+    }
+}
+
+/// Print the solve result as JSON, a deal proven unsolvable included
+fn print_json(seed: Option<u32>, draw_count: usize, result: Result<Solved>) -> Result<()> {
+    let json = match result {
+        Ok(solved) => SolveJson {
+            seed,
+            draw_count,
+            solvable: true,
+            minimal: solved.minimal,
+            states: Some(solved.states),
+            elapsed_ms: Some(solved.elapsed.as_millis() as u64),
+            moves: action_tokens(&solved.actions),
+        },
+        Err(err) if matches!(err.downcast_ref(), Some(SolveError::Unsolvable)) => SolveJson {
+            seed,
+            draw_count,
+            solvable: false,
+            minimal: false,
+            states: None,
+            elapsed_ms: None,
+            moves: vec![],
+        },
+        Err(err) => return Err(err),
+    };
+    println!("{}", serde_json::to_string(&json)?);
+    Ok(())
+}
+
+/// Load the board from the file, the greenfelt seed or stdin, in that order
+fn load_board(file: Option<PathBuf>, greenfelt: Option<u32>, draw: Option<usize>) -> Result<Board> {
     let mut board = if let Some(file) = file {
         let content = std::fs::read_to_string(file)?;
-        Board::parse(&content).context("Failed to parse board")?
+        parse_board(&content)?
     } else if let Some(seed) = greenfelt {
         Board::new_from_seed(seed)
     } else if !stdin().is_terminal() {
@@ -53,22 +381,302 @@ fn main() -> Result<()> {
         stdin()
             .read_to_string(&mut content)
             .context("Failed to read from stdin")?;
-        Board::parse(&content).context("Failed to parse board")?
+        parse_board(&content)?
     } else {
         bail!("No game state `file` or `--greenfelt` provided.");
     };
     if let Some(draw_count) = draw {
-        if draw_count != 1 && draw_count != 3 {
-            bail!("Draw count must be 1 or 3.");
+        if draw_count == 0 {
+            bail!("Draw count must be at least 1.");
         }
         board.set_draw_count(draw_count);
     }
-    if preview {
-        println!("{}", board.to_pretty_string());
-        return Ok(());
+    Ok(board)
+}
+
+/// Print a CSV of the solve results of the seeds from `from` to `to`
+fn batch(from: u32, to: u32, draw_count: usize, max_states: u32, limits: SeedLimits) -> Result<()> {
+    if from > to {
+        bail!("`--from` must not be greater than `--to`.");
+    }
+    let seeds: Vec<u32> = (from..=to).collect();
+    println!("seed,solvable,moves,states");
+    for (seed, result) in solve_seeds(&seeds, draw_count, max_states, limits) {
+        match result {
+            Ok(result) => println!(
+                "{seed},true,{},{}",
+                result.card_moves() + result.draw_count(),
+                result.states
+            ),
+            Err(err) => {
+                let solvable = match err {
+                    SolveError::Unsolvable => "false",
+                    _ => "unknown",
+                };
+                println!("{seed},{solvable},,");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the winnability of the seeds from `from` to `to`
+fn stats(from: u32, to: u32, draw_count: usize, max_states: u32, limits: SeedLimits) -> Result<()> {
+    if from > to {
+        bail!("`--from` must not be greater than `--to`.");
+    }
+    let stats = winnability_stats(from..=to, draw_count, max_states, limits);
+    let percent = |count: usize| count as f64 * 100.0 / stats.total() as f64;
+    println!("Deals: {}", stats.total());
+    println!("Solved: {} ({:.2}%)", stats.solved, percent(stats.solved));
+    println!(
+        "Unsolvable: {} ({:.2}%)",
+        stats.unsolvable,
+        percent(stats.unsolvable)
+    );
+    println!(
+        "Undecided: {} ({:.2}%, reached max states or the timeout)",
+        stats.undecided,
+        percent(stats.undecided)
+    );
+    if !stats.move_counts.is_empty() {
+        println!(
+            "\nMoves of the solved deals ({} proven minimal):",
+            stats.proven_minimal
+        );
+        for (moves, count) in &stats.move_counts {
+            println!("{moves:>5}: {count}");
+        }
+    }
+    Ok(())
+}
+
+/// Solve each board of the content, printing its solution as it goes and a summary table at the end.
+/// A board that fails to parse or solve is reported without stopping the others.
+fn solve_all(
+    content: &str,
+    draw: Option<usize>,
+    max_states: u32,
+    minimal: bool,
+    options: SolveOptions,
+) -> Result<()> {
+    let boards = split_boards(content);
+    if boards.is_empty() {
+        bail!("No board found in the input.");
+    }
+    let mut summary = vec![];
+    for (i, chunk) in boards.iter().enumerate() {
+        println!("Board {}:", i + 1);
+        let board = parse_board(chunk).and_then(|mut board| {
+            if let Some(draw_count) = draw {
+                board.set_draw_count(draw_count);
+            }
+            board.validate().context("Invalid board")?;
+            Ok(board)
+        });
+        let row = match board {
+            Ok(board) => match do_solve(board, max_states, minimal, options.clone()) {
+                Ok(solved) => {
+                    println!("{}", format_actions(&solved.actions));
+                    let moves = solved
+                        .actions
+                        .iter()
+                        .filter(|action| !action.is_redeal())
+                        .count();
+                    let moves = match solved.minimal {
+                        true => moves.to_string(),
+                        false => format!("{moves}?"),
+                    };
+                    ("solved", moves, solved.states.to_string())
+                }
+                Err(err) => {
+                    println!("{err:#}\n");
+                    let status = match err.downcast_ref() {
+                        Some(SolveError::Unsolvable) => "unsolvable",
+                        _ => "undecided",
+                    };
+                    (status, String::new(), String::new())
+                }
+            },
+            Err(err) => {
+                println!("{err:#}\n");
+                ("invalid", String::new(), String::new())
+            }
+        };
+        summary.push(row);
     }
-    let actions = do_solve(board, max_states, !fast)?;
-    println!("{}", format_actions(&actions));
 
+    println!("{:<7}{:<12}{:<7}States", "Board", "Result", "Moves");
+    for (i, (status, moves, states)) in summary.iter().enumerate() {
+        let line = format!("{:<7}{status:<12}{moves:<7}{states}", i + 1);
+        println!("{}", line.trim_end());
+    }
+    println!("\nA `?` marks a move count that may not be minimal.");
     Ok(())
 }
+
+/// Split the content into boards on blank lines and `---` lines
+fn split_boards(content: &str) -> Vec<String> {
+    let mut boards = vec![];
+    let mut board = String::new();
+    for line in content.lines() {
+        if matches!(line.trim(), "" | "---") {
+            if !board.is_empty() {
+                boards.push(std::mem::take(&mut board));
+            }
+        } else {
+            board.push_str(line);
+            board.push('\n');
+        }
+    }
+    if !board.is_empty() {
+        boards.push(board);
+    }
+    boards
+}
+
+/// Print `count` random deals passing the filters, with the moves of their solution
+fn generate(
+    count: usize,
+    solvable: bool,
+    max_attempts: usize,
+    draw_count: usize,
+    max_states: u32,
+    options: &SolveOptions,
+) -> Result<()> {
+    let max_moves = options.max_moves.map(usize::from);
+    let mut solver = Solver::new();
+    options.seed_limits().apply(&mut solver);
+    let mut found = 0;
+    let mut attempts = 0;
+    while found < count {
+        if attempts == max_attempts {
+            bail!("Found {found} of {count} deals in {attempts} attempts.");
+        }
+        attempts += 1;
+        let seed = rand::random_range(1..0x7fff_ffff);
+        let mut board = Board::new_from_seed(seed);
+        board.set_draw_count(draw_count);
+
+        // Moves of the solution found, and whether they are the fewest possible
+        let mut moves = None;
+        if solvable || max_moves.is_some() {
+            // The solve for the fewest moves decides the deal too, so `--max-moves`
+            // skips proving it winnable first
+            if max_moves.is_none() {
+                solver.set_board(board.clone());
+                match solver.is_solvable(max_states) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        eprintln!("Attempt {attempts}: seed {seed} is unsolvable");
+                        continue;
+                    }
+                    Err(SolveError::TimedOut { .. }) => {
+                        eprintln!("Attempt {attempts}: seed {seed} is undecided, timed out");
+                        continue;
+                    }
+                    Err(_) => {
+                        eprintln!(
+                            "Attempt {attempts}: seed {seed} is undecided, reached max states"
+                        );
+                        continue;
+                    }
+                }
+            }
+            solver.set_board(board.clone());
+            match solver.solve(max_states, true) {
+                Ok(result) => {
+                    moves = Some((result.card_moves() + result.draw_count(), result.minimal()));
+                }
+                Err(SolveError::Unsolvable) => {
+                    eprintln!("Attempt {attempts}: seed {seed} is unsolvable");
+                    continue;
+                }
+                Err(_) => {}
+            }
+        }
+        if let Some(max_moves) = max_moves
+            && moves.is_none_or(|(moves, _)| moves > max_moves)
+        {
+            eprintln!("Attempt {attempts}: seed {seed} has no solution of {max_moves} moves found");
+            continue;
+        }
+
+        found += 1;
+        eprintln!("Attempt {attempts}: seed {seed} found ({found}/{count})");
+        println!("Seed: {seed}");
+        match moves {
+            Some((moves, true)) => println!("Moves: {moves}"),
+            Some((moves, false)) => println!("Moves: {moves} (may not be minimal)"),
+            None => {}
+        }
+        println!("{}\n", format_board(&board, options.color, false));
+    }
+    Ok(())
+}
+
+/// Write a GIF of the board before the solution and after each of its moves
+fn replay(mut board: Board, solution: &str, output: &Path, delay: Duration) -> Result<()> {
+    board.validate().context("Invalid board")?;
+    let actions = parse_actions(&read_solution(solution)?)?;
+    let mut fan = WasteFan::default();
+    let mut frames = vec![(board.clone(), fan)];
+    for (i, action) in actions.iter().enumerate() {
+        let record = board
+            .try_apply(action)
+            .with_context(|| format!("Failed to apply move {}", i + 1))?;
+        fan.follow(&record);
+        frames.push((board.clone(), fan));
+    }
+    write_gif(output, &frames, delay)?;
+    eprintln!("Wrote {} frames to '{}'", frames.len(), output.display());
+    Ok(())
+}
+
+/// Check that the solution wins the game, printing the board where it goes wrong
+fn verify(mut board: Board, solution: &str, color: bool) -> Result<()> {
+    board.validate().context("Invalid board")?;
+    let actions = parse_actions(&read_solution(solution)?)?;
+    for (i, action) in actions.iter().enumerate() {
+        if let Err(err) = board.try_apply(action) {
+            println!("{}\n", format_board(&board, color, false));
+            return Err(err.context(format!("Move {} of the solution is illegal", i + 1)));
+        }
+    }
+    // Counted like the solver's move counts, redeals left out
+    let moves = actions.iter().filter(|action| !action.is_redeal()).count();
+    let score = board.foundation_score();
+    if score < MAX_CARD {
+        println!("{}\n", format_board(&board, color, false));
+        bail!(
+            "The solution is incomplete, {score} of {MAX_CARD} cards are on the foundations after {moves} moves"
+        );
+    }
+    println!("✓ The solution wins in {moves} moves");
+    Ok(())
+}
+
+/// Read the solution from the file at `solution`, or take it as the solution itself.
+/// The whole output of the solver is accepted too, keeping the moves after the summary line.
+fn read_solution(solution: &str) -> Result<String> {
+    let path = Path::new(solution);
+    let content = if path.is_file() {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read solution '{}'", path.display()))?
+    } else {
+        solution.to_string()
+    };
+    match content.find("✓ Solved in") {
+        Some(idx) => Ok(content[idx..].lines().skip(2).collect::<Vec<_>>().join(" ")),
+        None => Ok(content),
+    }
+}
+
+/// Parse a game state file, either in our format or an AisleRiot slot dump
+fn parse_board(content: &str) -> Result<Board> {
+    if content.trim_start().starts_with('(') {
+        Board::parse_aisleriot(content).context("Failed to parse AisleRiot game state")
+    } else {
+        Board::parse(content).context("Failed to parse board")
+    }
+}
@@ -1,47 +1,182 @@
 use anyhow::Result;
 use klondike_common::{action::Action, board::Board};
-use klondike_solver::{SolveResult, solve};
+use klondike_solver::{GreedySolveResult, SeedLimits, SolveProgress, Solver, SolverConfig};
 
 use std::{
     io::{IsTerminal, Write, stderr},
     sync::{
-        Arc,
+        Arc, Mutex,
         atomic::{AtomicBool, Ordering},
     },
     time::Duration,
 };
 
-pub fn do_solve(board: Board, max_states: u32, minimal: bool) -> Result<Vec<Action>> {
-    let board_str = board.to_pretty_string();
-    println!("{board_str}\n");
-    let SolveResult {
-        actions,
-        elapsed,
-        states,
-        minimal,
-    } = with_spinner("Solving the game...", move || {
-        solve(board, max_states, minimal)
+#[derive(Debug, Default, Clone)]
+pub struct SolveOptions {
+    pub timeout: Option<Duration>,  // Stop solving after this duration
+    pub greedy: bool,               // Play safe foundation moves before solving
+    pub max_redeals: Option<usize>, // Limit the times the waste is recycled into the stock
+    pub max_moves: Option<u8>,      // Only accept solutions of at most this many moves
+    pub no_foundation_undo: bool,   // Never move cards back from the foundations
+    pub quiet: bool,                // Leave stdout to the caller, e.g. for JSON output
+    pub color: bool,                // Print the board with ANSI colors
+    pub thoughtful: bool,           // Print the face-down cards face up
+}
+
+impl SolveOptions {
+    /// The limits that also apply to each deal of `batch`, `stats` and `generate`
+    pub fn seed_limits(&self) -> SeedLimits {
+        SeedLimits {
+            timeout: self.timeout,
+            max_redeals: self.max_redeals,
+        }
+    }
+}
+
+/// Whether stdout is a terminal, and `NO_COLOR` is unset or empty
+pub fn stdout_supports_color() -> bool {
+    std::io::stdout().is_terminal()
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// The board as printed, noting when it can be auto-finished.
+/// With `thoughtful` its face-down cards are shown face up.
+pub fn format_board(board: &Board, color: bool, thoughtful: bool) -> String {
+    let mut shown = board.clone();
+    if thoughtful {
+        shown.make_thoughtful();
+    }
+    let mut output = match color {
+        true => shown.to_pretty_string_colored(),
+        false => shown.to_pretty_string(),
+    };
+    if board.can_autofinish() {
+        output.push_str("\n(auto-finishable)");
+    }
+    output
+}
+
+/// Solution found by [`do_solve`]
+#[derive(Debug)]
+pub struct Solved {
+    pub actions: Vec<Action>,
+    pub minimal: bool, // Proven to have the fewest moves
+    pub states: i32,
+    pub elapsed: Duration,
+}
+
+pub fn do_solve(
+    board: Board,
+    max_states: u32,
+    minimal: bool,
+    options: SolveOptions,
+) -> Result<Solved> {
+    if !options.quiet {
+        println!(
+            "{}\n",
+            format_board(&board, options.color, options.thoughtful)
+        );
+    }
+
+    let mut solver = Solver::new();
+    solver.set_board(board);
+    if let Some(timeout) = options.timeout {
+        solver.set_timeout(timeout);
+    }
+    if let Some(max_redeals) = options.max_redeals {
+        solver.set_max_rounds(max_redeals.saturating_add(1));
+    }
+    if let Some(max_moves) = options.max_moves {
+        solver.set_max_moves(max_moves);
+    }
+    if options.no_foundation_undo {
+        solver.set_config(SolverConfig {
+            allow_foundation_to_tableau: false,
+            ..Default::default()
+        });
+    }
+
+    if options.greedy {
+        let result = with_spinner("Solving the game...", move |_| {
+            solver.solve_greedy_prefix(max_states, minimal)
+        })?;
+        let GreedySolveResult { prefix, tail } = &result;
+        let actions = result.actions();
+        if !options.quiet {
+            println!(
+                "✓ Solved in {} — Greedy prefix: {}, Optimal tail: {}, Minimal tail: {}, Time: {}, States: {}\n",
+                format_steps(
+                    prefix.len() + tail.card_moves() + tail.draw_count(),
+                    tail.redeal_count()
+                ),
+                prefix.len(),
+                tail.card_moves() + tail.draw_count(),
+                tail.minimal(),
+                format_elapsed(tail.elapsed),
+                tail.states
+            );
+        }
+        return Ok(Solved {
+            minimal: prefix.is_empty() && tail.minimal(),
+            states: tail.states,
+            elapsed: tail.elapsed,
+            actions,
+        });
+    }
+
+    let result = with_spinner("Solving the game...", move |set_status| {
+        solver.solve_with_progress(max_states, minimal, |progress| {
+            set_status(format_progress(&progress))
+        })
     })?;
-    let total_actions = actions.len();
-    let redeal_count = actions.iter().filter(|a| a.is_redeal()).count();
-    let elapsed_str = format_elapsed(elapsed);
-    let mut steps_str = format!("{} Moves", total_actions - redeal_count);
+    if !options.quiet {
+        println!(
+            "✓ Solved in {} — Minimal: {}, Time: {}, States: {}\n",
+            format_steps(
+                result.card_moves() + result.draw_count(),
+                result.redeal_count()
+            ),
+            result.minimal(),
+            format_elapsed(result.elapsed),
+            result.states
+        );
+    }
+    Ok(Solved {
+        minimal: result.minimal(),
+        states: result.states,
+        elapsed: result.elapsed,
+        actions: result.actions,
+    })
+}
+
+/// The moves of a solution, draws included, and its redeals
+fn format_steps(moves: usize, redeal_count: usize) -> String {
+    let mut steps_str = format!("{moves} Moves");
     if redeal_count > 0 {
         steps_str.push_str(&format!(", {redeal_count} Redeal"));
         if redeal_count > 1 {
             steps_str.push('s');
         }
     };
-    println!(
-        "✓ Solved in {steps_str} — Minimal: {minimal}, Time: {elapsed_str}, States: {states}\n"
-    );
-    Ok(actions)
+    steps_str
 }
 
-fn with_spinner<T, F: FnOnce() -> T>(message: &str, f: F) -> T {
+fn format_progress(progress: &SolveProgress) -> String {
+    format!(
+        "{} states, best {}/52, {}",
+        progress.states,
+        progress.best_score,
+        format_elapsed(progress.elapsed)
+    )
+}
+
+/// Run `f` while showing a spinner, `f` can update the status shown after the message
+fn with_spinner<T, F: FnOnce(&dyn Fn(String)) -> T>(message: &str, f: F) -> T {
     if stderr().is_terminal() {
         let spinning = Arc::new(AtomicBool::new(true));
         let spinning_clone = Arc::clone(&spinning);
+        let status = Arc::new(Mutex::new(String::new()));
+        let status_clone = Arc::clone(&status);
         let message = message.to_string();
 
         let handle = std::thread::spawn(move || {
@@ -55,7 +190,8 @@ fn with_spinner<T, F: FnOnce() -> T>(message: &str, f: F) -> T {
 
             while spinning_clone.load(Ordering::Relaxed) {
                 let spinner_char = spinner_chars[i % spinner_chars.len()];
-                let _ = write!(handle, "\r{spinner_char} {message}",);
+                let status = status_clone.lock().map(|s| s.clone()).unwrap_or_default();
+                let _ = write!(handle, "\r\x1b[2K{spinner_char} {message} {status}",);
                 let _ = handle.flush();
                 std::thread::sleep(Duration::from_millis(100));
                 i += 1;
@@ -65,12 +201,16 @@ fn with_spinner<T, F: FnOnce() -> T>(message: &str, f: F) -> T {
             let _ = handle.flush();
         });
 
-        let result = f();
+        let result = f(&|value| {
+            if let Ok(mut status) = status.lock() {
+                *status = value;
+            }
+        });
         spinning.store(false, Ordering::Relaxed);
         let _ = handle.join();
         result
     } else {
-        f()
+        f(&|_| {})
     }
 }
 
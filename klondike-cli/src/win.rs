@@ -2,6 +2,7 @@
 mod utils;
 
 use clap::Parser;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(author, version, about)]
@@ -18,6 +19,25 @@ struct Cli {
     /// Delay between moves in milliseconds
     #[arg(short, long, default_value_t = 3000, value_name = "MS")]
     interval: u64,
+    /// Interval multipliers by move kind, e.g. `redeal=2,multi_card=1.5`
+    /// (kinds: draw, redeal, foundation, tableau, multi_card)
+    #[arg(long, value_name = "KIND=FACTOR,...")]
+    pace: Option<String>,
+    /// Play area of a letterboxed game window, as printed by --calibrate
+    #[arg(long, value_name = "SCALE,LEFT,TOP")]
+    calibration: Option<String>,
+    /// Find the play area by pointing at the stock and the last foundation
+    #[arg(long)]
+    calibrate: bool,
+    /// Print the clicks of --play without moving the mouse
+    #[arg(long)]
+    dry_run: bool,
+    /// TOML file with the process name and memory offsets of another game version
+    #[arg(long, value_name = "FILE")]
+    offsets: Option<PathBuf>,
+    /// Dump each step of the pointer chains, to re-find the offsets after a game update
+    #[arg(long)]
+    probe: bool,
 }
 
 #[cfg(windows)]
@@ -27,11 +47,49 @@ fn main() -> anyhow::Result<()> {
         fast,
         play,
         interval,
+        pace,
+        calibration,
+        calibrate,
+        dry_run,
+        offsets,
+        probe,
     } = Cli::parse();
-    let board = klondike_win::inspect()?;
-    let actions = crate::utils::do_solve(board.clone(), max_states, !fast)?;
-    if play {
-        klondike_win::autoplay(board, actions, interval)?;
+    let config = klondike_win::InspectConfig::load(offsets.as_deref())?;
+    let pacing = pace
+        .as_deref()
+        .map(str::parse::<klondike_win::Pacing>)
+        .transpose()?
+        .unwrap_or_default();
+    let calibration = calibration
+        .as_deref()
+        .map(str::parse::<klondike_win::Calibration>)
+        .transpose()?;
+    if calibrate {
+        let calibration = klondike_win::calibrate(&config.process_name)?;
+        println!("Pass `--calibration {calibration}` to play in this window");
+        return Ok(());
+    }
+    if probe {
+        print!("{}", klondike_win::probe(&config)?);
+        return Ok(());
+    }
+    let board = klondike_win::inspect(&config)?;
+    let options = crate::utils::SolveOptions {
+        color: crate::utils::stdout_supports_color(),
+        ..Default::default()
+    };
+    let actions = crate::utils::do_solve(board.clone(), max_states, !fast, options)?.actions;
+    if dry_run {
+        klondike_win::autoplay_dry_run(board, actions, calibration.as_ref(), &config.process_name)?;
+    } else if play {
+        klondike_win::autoplay(
+            board,
+            actions,
+            interval,
+            pacing,
+            calibration.as_ref(),
+            &config,
+        )?;
     } else {
         println!("{}", klondike_common::action::format_actions(&actions));
     }
@@ -1,6 +1,13 @@
-use crate::board::{Board, Card};
+use crate::board::{Board, Card, MAX_RANK, MAX_SUIT, TOTAL_FOUNDATIONS, TOTAL_TABLEAUS};
+
+use anyhow::{Context, Result, bail};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "ActionRepr", into = "ActionRepr")
+)]
 pub enum Action {
     WasteToFoundation(usize),
     WasteToTableau(usize),
@@ -11,30 +18,116 @@ pub enum Action {
     Redeal,
 }
 
+/// Serialized form of [`Action`], with named fields instead of positional ones
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+enum ActionRepr {
+    WasteToFoundation {
+        foundation: usize,
+    },
+    WasteToTableau {
+        tableau: usize,
+    },
+    TableauToFoundation {
+        tableau: usize,
+        foundation: usize,
+    },
+    FoundationToTableau {
+        foundation: usize,
+        tableau: usize,
+    },
+    TableauToTableau {
+        from: usize,
+        to: usize,
+        count: usize,
+    },
+    Draw,
+    Redeal,
+}
+
+#[cfg(feature = "serde")]
+impl From<Action> for ActionRepr {
+    fn from(action: Action) -> Self {
+        match action {
+            Action::WasteToFoundation(foundation) => ActionRepr::WasteToFoundation { foundation },
+            Action::WasteToTableau(tableau) => ActionRepr::WasteToTableau { tableau },
+            Action::TableauToFoundation(tableau, foundation) => ActionRepr::TableauToFoundation {
+                tableau,
+                foundation,
+            },
+            Action::FoundationToTableau(foundation, tableau) => ActionRepr::FoundationToTableau {
+                foundation,
+                tableau,
+            },
+            Action::TableauToTableau(from, to, count) => {
+                ActionRepr::TableauToTableau { from, to, count }
+            }
+            Action::Draw => ActionRepr::Draw,
+            Action::Redeal => ActionRepr::Redeal,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ActionRepr> for Action {
+    fn from(repr: ActionRepr) -> Self {
+        match repr {
+            ActionRepr::WasteToFoundation { foundation } => Action::WasteToFoundation(foundation),
+            ActionRepr::WasteToTableau { tableau } => Action::WasteToTableau(tableau),
+            ActionRepr::TableauToFoundation {
+                tableau,
+                foundation,
+            } => Action::TableauToFoundation(tableau, foundation),
+            ActionRepr::FoundationToTableau {
+                foundation,
+                tableau,
+            } => Action::FoundationToTableau(foundation, tableau),
+            ActionRepr::TableauToTableau { from, to, count } => {
+                Action::TableauToTableau(from, to, count)
+            }
+            ActionRepr::Draw => Action::Draw,
+            ActionRepr::Redeal => Action::Redeal,
+        }
+    }
+}
+
 impl Action {
     pub fn is_redeal(&self) -> bool {
         matches!(self, Action::Redeal)
     }
 }
 
-pub fn format_actions(actions: &[Action]) -> String {
-    let mut list = vec![];
+/// Splits actions into the steps shown by [`format_actions`], where consecutive draws are one step.
+pub fn action_steps(actions: &[Action]) -> Vec<&[Action]> {
+    let mut steps = vec![];
     let mut i = 0;
     while i < actions.len() {
-        match actions[i] {
+        let mut count = 1;
+        if matches!(actions[i], Action::Draw) {
+            while i + count < actions.len() && matches!(actions[i + count], Action::Draw) {
+                count += 1;
+            }
+        }
+        steps.push(&actions[i..i + count]);
+        i += count;
+    }
+    steps
+}
+
+/// The notation of each step of the actions, e.g. `3D`, `W:F1` or `T3:T5@2`.
+pub fn action_tokens(actions: &[Action]) -> Vec<String> {
+    let mut list = vec![];
+    for step in action_steps(actions) {
+        match step[0] {
             Action::Draw => {
-                let mut count = 1;
-                while i + count < actions.len() && matches!(actions[i + count], Action::Draw) {
-                    count += 1;
-                }
+                let count = step.len();
                 let str = if count == 1 {
                     "D".into()
                 } else {
                     format!("{count}D")
                 };
                 list.push(str);
-                i += count;
-                continue;
             }
             Action::WasteToFoundation(idx) => {
                 list.push(format!("W:F{}", idx + 1));
@@ -59,9 +152,12 @@ pub fn format_actions(actions: &[Action]) -> String {
                 list.push("R".into());
             }
         }
-        i += 1;
     }
+    list
+}
 
+pub fn format_actions(actions: &[Action]) -> String {
+    let list = action_tokens(actions);
     let mut output = String::new();
     let column_width = list.iter().map(|s| s.len()).max().unwrap_or_default() + 1;
     for chunk in list.chunks(10) {
@@ -74,6 +170,75 @@ pub fn format_actions(actions: &[Action]) -> String {
     output
 }
 
+/// Parses the notation of [`format_actions`] back into actions, e.g. `3D W:F1 T3:T5@2 R`.
+///
+/// A `nD` token expands into `n` separate draws.
+pub fn parse_actions(s: &str) -> Result<Vec<Action>> {
+    let mut actions = vec![];
+    for part in s.split_whitespace() {
+        let part_ctx = || format!("Failed to parse action '{part}'");
+        if part == "R" {
+            actions.push(Action::Redeal);
+        } else if let Some(num_str) = part.strip_suffix('D') {
+            let num = match num_str {
+                "" => 1,
+                _ => num_str.parse::<usize>().with_context(part_ctx)?,
+            };
+            actions.extend(std::iter::repeat_n(Action::Draw, num));
+        } else if let Some((from, to)) = part.split_once(':') {
+            let (to, count) = match to.split_once('@') {
+                Some((to, count)) => (to, count.parse::<usize>().with_context(part_ctx)?),
+                None => (to, 1),
+            };
+            let from = parse_pile(from).with_context(part_ctx)?;
+            let to = parse_pile(to).with_context(part_ctx)?;
+            let action = match (from, to, count) {
+                (PileRef::Waste, PileRef::Foundation(f), 1) => Action::WasteToFoundation(f),
+                (PileRef::Waste, PileRef::Tableau(t), 1) => Action::WasteToTableau(t),
+                (PileRef::Tableau(t), PileRef::Foundation(f), 1) => {
+                    Action::TableauToFoundation(t, f)
+                }
+                (PileRef::Foundation(f), PileRef::Tableau(t), 1) => {
+                    Action::FoundationToTableau(f, t)
+                }
+                (PileRef::Tableau(from), PileRef::Tableau(to), 1..) if from != to => {
+                    Action::TableauToTableau(from, to, count)
+                }
+                _ => bail!("Unsupported action '{part}'"),
+            };
+            actions.push(action);
+        } else {
+            bail!("Unknown action format '{part}'");
+        }
+    }
+    Ok(actions)
+}
+
+enum PileRef {
+    Waste,
+    Foundation(usize),
+    Tableau(usize),
+}
+
+fn parse_pile(s: &str) -> Result<PileRef> {
+    let index = |num: &str, total: usize| -> Result<usize> {
+        num.parse::<usize>()
+            .ok()
+            .filter(|n| (1..=total).contains(n))
+            .map(|n| n - 1)
+            .with_context(|| format!("Invalid pile '{s}'"))
+    };
+    if s == "W" {
+        Ok(PileRef::Waste)
+    } else if let Some(num) = s.strip_prefix('T') {
+        Ok(PileRef::Tableau(index(num, TOTAL_TABLEAUS)?))
+    } else if let Some(num) = s.strip_prefix('F') {
+        Ok(PileRef::Foundation(index(num, TOTAL_FOUNDATIONS)?))
+    } else {
+        bail!("Invalid pile '{s}'")
+    }
+}
+
 pub fn apply_action(board: &mut Board, action: &Action) {
     match action {
         Action::WasteToFoundation(foundation_index) => {
@@ -97,6 +262,339 @@ pub fn apply_action(board: &mut Board, action: &Action) {
     }
 }
 
+/// What [`Board::undo`] needs to reverse an action applied with [`Board::apply`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UndoRecord {
+    pub action: Action,
+    pub flipped: bool, // Whether the move turned up a face-down card of its source tableau
+    pub(crate) drawn: usize, // Cards the draw moved from the stock, 0 when it recycled the waste
+}
+
+impl Board {
+    /// Applies the action, returning the record to undo it.
+    pub fn apply(&mut self, action: &Action) -> UndoRecord {
+        let source = match *action {
+            Action::TableauToFoundation(idx, _) => Some((idx, 1)),
+            Action::TableauToTableau(idx, _, count) => Some((idx, count)),
+            _ => None,
+        };
+        let flipped = source.is_some_and(|(idx, count)| {
+            let tableau = &self.tableaus[idx];
+            tableau.len() > count && tableau.face_up_count == count
+        });
+        let drawn = match action {
+            Action::Draw | Action::Redeal => self.draw_count().min(self.stock.len()),
+            _ => 0,
+        };
+        apply_action(self, action);
+        UndoRecord {
+            action: *action,
+            flipped,
+            drawn,
+        }
+    }
+
+    /// Applies the action if it is legal on the board, leaving the board unchanged otherwise.
+    pub fn try_apply(&mut self, action: &Action) -> Result<UndoRecord> {
+        if !self.is_legal(action) {
+            bail!(
+                "Illegal action {}",
+                action_tokens(std::slice::from_ref(action)).concat()
+            );
+        }
+        Ok(self.apply(action))
+    }
+
+    /// Whether the action follows the rules: foundations build up by suit from the Ace,
+    /// tableaus build down in alternating colours from a King, and indices are in bounds.
+    pub fn is_legal(&self, action: &Action) -> bool {
+        let fits_foundation = |card: &Card, foundation_idx: usize| {
+            foundation_idx < self.foundations.len() && self.fits_foundation(card, foundation_idx)
+        };
+        let fits_tableau = |card: &Card, tableau_idx: usize| {
+            tableau_idx < self.tableaus.len() && self.fits_tableau(card, tableau_idx)
+        };
+        let tableau_top = |tableau_idx: usize| {
+            self.tableaus
+                .get(tableau_idx)
+                .and_then(|tableau| tableau.peek_top())
+        };
+
+        match *action {
+            Action::WasteToFoundation(foundation_idx) => self
+                .waste
+                .last()
+                .is_some_and(|card| fits_foundation(card, foundation_idx)),
+            Action::WasteToTableau(tableau_idx) => self
+                .waste
+                .last()
+                .is_some_and(|card| fits_tableau(card, tableau_idx)),
+            Action::TableauToFoundation(tableau_idx, foundation_idx) => {
+                tableau_top(tableau_idx).is_some_and(|card| fits_foundation(card, foundation_idx))
+            }
+            Action::FoundationToTableau(foundation_idx, tableau_idx) => self
+                .foundations
+                .get(foundation_idx)
+                .copied()
+                .flatten()
+                .is_some_and(|card| fits_tableau(&card, tableau_idx)),
+            Action::TableauToTableau(from_idx, to_idx, count) => {
+                let Some(from) = self.tableaus.get(from_idx) else {
+                    return false;
+                };
+                from_idx != to_idx
+                    && (1..=from.face_up_count.min(from.len())).contains(&count)
+                    && fits_tableau(&from.cards[from.len() - count], to_idx)
+            }
+            Action::Draw => !self.stock.is_empty() || !self.waste.is_empty(),
+            Action::Redeal => self.stock.is_empty() && !self.waste.is_empty(),
+        }
+    }
+
+    /// Lists every legal action: moves to the foundations, from the waste, between tableaus
+    /// with each count of their face-up run, from the foundations, then the draw or redeal.
+    ///
+    /// Moving a whole tableau onto an empty one changes nothing and is left out.
+    pub fn legal_actions(&self) -> Vec<Action> {
+        let mut candidates = vec![];
+        for foundation_idx in 0..TOTAL_FOUNDATIONS {
+            candidates.push(Action::WasteToFoundation(foundation_idx));
+            candidates.extend(
+                (0..TOTAL_TABLEAUS)
+                    .map(|tableau_idx| Action::TableauToFoundation(tableau_idx, foundation_idx)),
+            );
+        }
+        candidates.extend((0..TOTAL_TABLEAUS).map(Action::WasteToTableau));
+        for (from_idx, from) in self.tableaus.iter().enumerate() {
+            for count in 1..=from.face_up_count.min(from.len()) {
+                candidates.extend((0..TOTAL_TABLEAUS).filter_map(|to_idx| {
+                    let pointless = count == from.len() && self.tableaus[to_idx].is_empty();
+                    (!pointless).then_some(Action::TableauToTableau(from_idx, to_idx, count))
+                }));
+            }
+        }
+        for foundation_idx in 0..TOTAL_FOUNDATIONS {
+            candidates.extend(
+                (0..TOTAL_TABLEAUS)
+                    .map(|tableau_idx| Action::FoundationToTableau(foundation_idx, tableau_idx)),
+            );
+        }
+        candidates.push(match self.stock.is_empty() {
+            true => Action::Redeal,
+            false => Action::Draw,
+        });
+        candidates.retain(|action| self.is_legal(action));
+        candidates
+    }
+
+    /// Reverses the action of the record, which must be the last one applied to the board.
+    pub fn undo(&mut self, record: UndoRecord) {
+        match record.action {
+            Action::WasteToFoundation(foundation_idx) => {
+                let card = self.pop_foundation(foundation_idx);
+                self.waste.push(card);
+            }
+            Action::WasteToTableau(tableau_idx) => {
+                let card = self.tableaus[tableau_idx].pop_unchecked();
+                self.waste.push(card);
+            }
+            Action::TableauToFoundation(tableau_idx, foundation_idx) => {
+                let card = self.pop_foundation(foundation_idx);
+                self.unflip(tableau_idx, record.flipped);
+                self.tableaus[tableau_idx].push(card);
+            }
+            Action::FoundationToTableau(foundation_idx, tableau_idx) => {
+                let card = self.tableaus[tableau_idx].pop_unchecked();
+                self.foundations[foundation_idx] = Some(card);
+            }
+            Action::TableauToTableau(from_idx, to_idx, count) => {
+                let cards = self.tableaus[to_idx].drain_unchecked(count);
+                self.unflip(from_idx, record.flipped);
+                let from = &mut self.tableaus[from_idx];
+                from.face_up_count += cards.len();
+                from.cards.extend(cards);
+            }
+            Action::Draw | Action::Redeal => {
+                if record.drawn == 0 {
+                    self.waste.extend(self.stock.drain(..).rev());
+                } else {
+                    let start = self.waste.len() - record.drawn;
+                    self.stock.extend(self.waste.drain(start..).rev());
+                }
+            }
+        }
+    }
+
+    fn fits_foundation(&self, card: &Card, foundation_idx: usize) -> bool {
+        !card.is_unknown()
+            && match self.foundations[foundation_idx] {
+                Some(top) => top.suit() == card.suit() && top.rank() + 1 == card.rank(),
+                None => card.rank() == 0,
+            }
+    }
+
+    fn fits_tableau(&self, card: &Card, tableau_idx: usize) -> bool {
+        !card.is_unknown()
+            && match self.tableaus[tableau_idx].peek_top() {
+                Some(top) => (top.suit() ^ card.suit()) & 1 == 1 && top.rank() == card.rank() + 1,
+                None => card.rank() == MAX_RANK - 1,
+            }
+    }
+
+    fn pop_foundation(&mut self, idx: usize) -> Card {
+        let card = self.foundations[idx].unwrap_or_default();
+        self.foundations[idx] = match card.rank() {
+            0 => None,
+            rank => Some(Card::new_with_rank_suit(rank - 1, card.suit())),
+        };
+        card
+    }
+
+    fn unflip(&mut self, tableau_idx: usize, flipped: bool) {
+        if flipped {
+            self.tableaus[tableau_idx].face_up_count -= 1;
+        }
+    }
+}
+
+/// Counts how many times the top card of the waste was passed over, i.e. covered
+/// by a draw or sent back to the stock by a redeal, without being played.
+///
+/// A high churn explains why a draw-3 solution needs many draws and redeals.
+/// Only the top card can be played, so the cards a draw-3 turns up beneath it are not counted.
+pub fn talon_churn(initial: &Board, actions: &[Action]) -> usize {
+    let mut board = initial.clone();
+    let mut churn = 0;
+    for action in actions {
+        let top = board.waste.last().copied();
+        let played = matches!(
+            action,
+            Action::WasteToFoundation(_) | Action::WasteToTableau(_)
+        );
+        apply_action(&mut board, action);
+        if top.is_some() && !played && board.waste.last().copied() != top {
+            churn += 1;
+        }
+    }
+    churn
+}
+
+/// Lists the foundation moves that can never hurt, i.e. no card still in play
+/// could need the promoted card to build on.
+///
+/// A card is safe to promote when it is an Ace or a Two. Otherwise, with `rank` the
+/// card's 0-based [`Card::rank`] (Ace is 0), both opposite-colour foundations must hold
+/// at least `rank - 1` cards and the other foundation of its colour at least `rank - 2`;
+/// e.g. a Five needs both opposite colours up to their Three, and its own colour up to the Two.
+pub fn safe_foundation_moves(board: &Board) -> Vec<Action> {
+    let foundation_ranks: [u8; MAX_SUIT as usize] = std::array::from_fn(|suit| {
+        board
+            .foundations
+            .iter()
+            .flatten()
+            .find(|card| card.suit() == suit as u8)
+            .map_or(0, |card| card.rank() + 1)
+    });
+    let is_safe = |card: &Card| {
+        let (rank, suit) = (card.rank(), card.suit() as usize);
+        if foundation_ranks[suit] != rank {
+            return false;
+        }
+        if rank <= 1 {
+            return true;
+        }
+        let opposite_ready = [(suit + 1) % 4, (suit + 3) % 4]
+            .iter()
+            .all(|&s| foundation_ranks[s] + 1 >= rank);
+        opposite_ready && foundation_ranks[suit ^ 2] + 2 >= rank
+    };
+    let mut empty_foundations =
+        (0..board.foundations.len()).filter(|&i| board.foundations[i].is_none());
+    let mut foundation_for = |card: &Card| match card.rank() {
+        0 => empty_foundations.next(),
+        rank => board.foundations.iter().position(|top| {
+            top.is_some_and(|top| top.suit() == card.suit() && top.rank() + 1 == rank)
+        }),
+    };
+
+    let mut actions = vec![];
+    if let Some(card) = board.waste.last()
+        && is_safe(card)
+        && let Some(foundation_idx) = foundation_for(card)
+    {
+        actions.push(Action::WasteToFoundation(foundation_idx));
+    }
+    for (tableau_idx, tableau) in board.tableaus.iter().enumerate() {
+        if let Some(card) = tableau.peek_top()
+            && is_safe(card)
+            && let Some(foundation_idx) = foundation_for(card)
+        {
+            actions.push(Action::TableauToFoundation(tableau_idx, foundation_idx));
+        }
+    }
+    actions
+}
+
+/// Suggests a productive move as a hint, `None` when no move makes progress.
+///
+/// Safe foundation moves come first, then moves turning up a face-down card (from the
+/// tallest face-down stack), other foundation moves and plays from the waste. A draw is
+/// only suggested when a card of the talon can be played. Moves that merely shuffle
+/// cards, like foundation to tableau, are never suggested.
+pub fn suggest_move(board: &Board) -> Option<Action> {
+    if let Some(&action) = safe_foundation_moves(board).first() {
+        return Some(action);
+    }
+
+    let mut reveal = None;
+    let mut most_hidden = 0;
+    for (from_idx, tableau) in board.tableaus.iter().enumerate() {
+        let count = tableau.face_up_count;
+        let hidden = tableau.len().saturating_sub(count);
+        if hidden <= most_hidden {
+            continue;
+        }
+        let mut candidates = (0..TOTAL_FOUNDATIONS)
+            .filter(|_| count == 1)
+            .map(|foundation_idx| Action::TableauToFoundation(from_idx, foundation_idx))
+            .chain(
+                (0..TOTAL_TABLEAUS).map(|to_idx| Action::TableauToTableau(from_idx, to_idx, count)),
+            );
+        if let Some(action) = candidates.find(|action| board.is_legal(action)) {
+            reveal = Some(action);
+            most_hidden = hidden;
+        }
+    }
+    if reveal.is_some() {
+        return reveal;
+    }
+
+    let mut plays = (0..TOTAL_FOUNDATIONS)
+        .flat_map(|foundation_idx| {
+            std::iter::once(Action::WasteToFoundation(foundation_idx)).chain(
+                (0..TOTAL_TABLEAUS).map(move |tableau_idx| {
+                    Action::TableauToFoundation(tableau_idx, foundation_idx)
+                }),
+            )
+        })
+        .chain((0..TOTAL_TABLEAUS).map(Action::WasteToTableau));
+    if let Some(action) = plays.find(|action| board.is_legal(action)) {
+        return Some(action);
+    }
+
+    let playable = |card: &Card| {
+        (0..TOTAL_FOUNDATIONS).any(|idx| board.fits_foundation(card, idx))
+            || (0..TOTAL_TABLEAUS).any(|idx| board.fits_tableau(card, idx))
+    };
+    if !board.stock.iter().chain(&board.waste).any(playable) {
+        return None;
+    }
+    match board.stock.is_empty() {
+        true => Some(Action::Redeal),
+        false => Some(Action::Draw),
+    }
+}
+
 pub fn describe_action(board: &Board, action: &Action) -> String {
     let format_card =
         |card: Option<&Card>| -> String { card.map(|c| c.to_pretty_string()).unwrap_or_default() };
@@ -161,3 +659,173 @@ pub fn describe_action(board: &Board, action: &Action) -> String {
         Action::Redeal => "Redeal".to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_actions() {
+        let actions = vec![
+            Action::Draw,
+            Action::Draw,
+            Action::Draw,
+            Action::WasteToFoundation(0),
+            Action::WasteToTableau(6),
+            Action::TableauToFoundation(2, 3),
+            Action::FoundationToTableau(1, 4),
+            Action::TableauToTableau(2, 4, 2),
+            Action::TableauToTableau(0, 1, 1),
+            Action::Draw,
+            Action::Redeal,
+        ];
+        assert_eq!(parse_actions(&format_actions(&actions)).unwrap(), actions);
+        assert_eq!(parse_actions("").unwrap(), []);
+
+        for s in ["T8:F1", "F5:T1", "W:F1@2", "T1:T1", "T1:T2@0", "X", "xD"] {
+            assert!(parse_actions(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_is_legal() {
+        // Tableau tops: K♦ 4♥ Q♦ 3♣ 6♦ T♦ A♣
+        let mut board = Board::new_from_seed(283409412);
+        let legal = [
+            Action::Draw,
+            Action::TableauToFoundation(6, 0),
+            Action::TableauToTableau(3, 1, 1),
+        ];
+        let illegal = [
+            Action::Redeal,
+            Action::WasteToFoundation(0),
+            Action::TableauToFoundation(0, 0),
+            Action::TableauToFoundation(6, 4),
+            Action::FoundationToTableau(0, 0),
+            Action::TableauToTableau(2, 0, 1),
+            Action::TableauToTableau(3, 1, 2),
+            Action::TableauToTableau(3, 3, 1),
+            Action::TableauToTableau(3, 7, 1),
+        ];
+        assert!(legal.iter().all(|action| board.is_legal(action)));
+        assert!(!illegal.iter().any(|action| board.is_legal(action)));
+
+        let before = board.clone();
+        let err = board
+            .try_apply(&Action::TableauToTableau(2, 0, 1))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Illegal action T3:T1");
+        assert_eq!(board, before);
+        board.try_apply(&Action::TableauToFoundation(6, 0)).unwrap();
+        assert!(!board.is_legal(&Action::FoundationToTableau(0, 0)));
+        assert_eq!(board.foundation_score(), 1);
+    }
+
+    #[test]
+    fn test_legal_actions() {
+        // Tableau tops: K♦ 4♥ Q♦ 3♣ 6♦ T♦ A♣
+        let board = Board::new_from_seed(283409412);
+        assert_eq!(
+            board.legal_actions(),
+            [
+                Action::TableauToFoundation(6, 0),
+                Action::TableauToFoundation(6, 1),
+                Action::TableauToFoundation(6, 2),
+                Action::TableauToFoundation(6, 3),
+                Action::TableauToTableau(3, 1, 1),
+                Action::Draw,
+            ]
+        );
+
+        let board = Board::parse(
+            "Foundation1: 2♥\nTableau1: |K♠Q♥J♣\nTableau2: 8♣|Q♦\nWaste: 3♥\nDrawCount: 1",
+        )
+        .unwrap();
+        assert_eq!(
+            board.legal_actions(),
+            [
+                Action::WasteToFoundation(0),
+                Action::TableauToTableau(0, 1, 1),
+                Action::Redeal,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_suggest_move() {
+        // A♣ on Tableau7 is a safe foundation move
+        let board = Board::new_from_seed(283409412);
+        assert_eq!(
+            suggest_move(&board),
+            Some(Action::TableauToFoundation(6, 0))
+        );
+
+        // Turning up a card beats a foundation move, the tallest face-down stack first
+        let board = Board::parse(
+            "Foundation1: 2♥\nTableau1: |5♠\nTableau2: 8♣|6♥\nTableau3: |7♠\nTableau4: 2♣3♣|4♦\nTableau5: |3♥\nDrawCount: 1",
+        )
+        .unwrap();
+        assert_eq!(
+            suggest_move(&board),
+            Some(Action::TableauToTableau(3, 0, 1))
+        );
+
+        let board = Board::parse(
+            "Foundation1: 2♥\nFoundation2: 4♣\nTableau1: |5♦\nTableau5: |3♥\nDrawCount: 1",
+        )
+        .unwrap();
+        assert_eq!(
+            suggest_move(&board),
+            Some(Action::TableauToFoundation(4, 0))
+        );
+
+        // F2:T1 is legal but pointless
+        const STUCK: &str = "Foundation2: 4♣\nTableau1: |5♦\nDrawCount: 1";
+        assert_eq!(suggest_move(&Board::parse(STUCK).unwrap()), None);
+        let board = Board::parse(&format!("Stock: 9♠\n{STUCK}")).unwrap();
+        assert_eq!(suggest_move(&board), None);
+        let board = Board::parse(&format!("Stock: 4♠\n{STUCK}")).unwrap();
+        assert_eq!(suggest_move(&board), Some(Action::Draw));
+        let board = Board::parse(&format!("Waste: 4♠8♠\n{STUCK}")).unwrap();
+        assert_eq!(suggest_move(&board), Some(Action::Redeal));
+    }
+
+    #[test]
+    fn test_board_undo() {
+        let mut board = Board::new_from_seed(283409412);
+        board.set_draw_count(3);
+        let mut history = vec![];
+        while let Some(action) = suggest_move(&board)
+            && history.len() < 100
+        {
+            history.push((board.clone(), board.try_apply(&action).unwrap()));
+        }
+        let played = |action| history.iter().any(|(_, record)| record.action == action);
+        assert!(played(Action::Draw) && played(Action::Redeal));
+        assert!(history.iter().any(|(_, record)| record.flipped));
+        while let Some((before, record)) = history.pop() {
+            board.undo(record);
+            assert_eq!(board, before);
+        }
+    }
+
+    #[test]
+    fn test_talon_churn() {
+        let board = Board::parse("Foundation1: 2♥\nStock: 9♠8♠7♠3♥4♣5♣\nDrawCount: 3").unwrap();
+        let actions = [
+            Action::Draw,                 // 5♣ 4♣ 3♥, 3♥ on top
+            Action::WasteToFoundation(0), // 3♥ played, 4♣ on top
+            Action::Draw,                 // 4♣ passed over, 9♠ on top
+            Action::Redeal,               // 9♠ passed over
+            Action::Draw,                 // 7♠ on top
+            Action::Draw,                 // 7♠ passed over, 9♠ on top
+        ];
+        let mut played = board.clone();
+        for action in &actions {
+            played.try_apply(action).unwrap();
+        }
+        assert_eq!(talon_churn(&board, &actions[..2]), 0);
+        assert_eq!(talon_churn(&board, &actions), 3);
+        assert_eq!(talon_churn(&board, &[]), 0);
+    }
+}
@@ -0,0 +1,165 @@
+//! Reading game state from GNOME AisleRiot.
+//!
+//! AisleRiot keeps its game state in Guile scheme, where each slot is a list of
+//! cards and each card is `(value suit face-up)`, e.g. `(12 heart #t)`. The
+//! supported input is a dump of the Klondike slots in that notation:
+//!
+//! ```text
+//! (deal-three #f)
+//! (0 ((5 club #f) (3 club #f) ...))
+//! (1 ())
+//! ...
+//! ```
+//!
+//! - Slot 0 is the stock, 1 the waste, 2-5 the foundations and 6-12 the tableaus.
+//! - Cards in a slot are listed top first, as returned by `get-cards`.
+//! - `(deal-three #t)` sets the draw count to 3; without it, the draw count is 1.
+//!
+//! Other expressions are ignored, so unrelated variables in the dump are skipped.
+
+use crate::board::{Board, Card, MAX_RANK, TOTAL_FOUNDATIONS, TOTAL_TABLEAUS};
+
+use anyhow::{Context, Result, bail};
+
+const SLOT_STOCK: usize = 0;
+const SLOT_WASTE: usize = 1;
+const SLOT_FOUNDATION_START: usize = 2;
+const SLOT_TABLEAU_START: usize = SLOT_FOUNDATION_START + TOTAL_FOUNDATIONS;
+const SLOT_TABLEAU_END: usize = SLOT_TABLEAU_START + TOTAL_TABLEAUS;
+const SUITS: [&str; 4] = ["diamond", "club", "heart", "spade"];
+
+#[derive(Debug)]
+enum Sexp {
+    Atom(String),
+    List(Vec<Sexp>),
+}
+
+impl Board {
+    /// Parse a dump of the AisleRiot Klondike slots, see the module docs for the supported format.
+    pub fn parse_aisleriot(content: &str) -> Result<Self> {
+        let mut board = Board::new();
+        for expr in parse_sexps(content)? {
+            let Sexp::List(items) = expr else {
+                continue;
+            };
+            match items.as_slice() {
+                [Sexp::Atom(name), Sexp::Atom(value)] if name == "deal-three" => {
+                    board.set_draw_count(if value == "#t" { 3 } else { 1 });
+                }
+                [Sexp::Atom(slot), Sexp::List(cards)] => {
+                    let Ok(slot) = slot.parse::<usize>() else {
+                        continue;
+                    };
+                    let mut cards = cards
+                        .iter()
+                        .map(parse_card)
+                        .collect::<Result<Vec<_>>>()
+                        .with_context(|| format!("Failed to parse slot {slot}"))?;
+                    cards.reverse();
+                    board.set_slot(slot, cards)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(board)
+    }
+
+    fn set_slot(&mut self, slot: usize, cards: Vec<(Card, bool)>) -> Result<()> {
+        match slot {
+            SLOT_STOCK => self.stock = cards.into_iter().map(|(card, _)| card).collect(),
+            SLOT_WASTE => self.waste = cards.into_iter().map(|(card, _)| card).collect(),
+            SLOT_FOUNDATION_START..SLOT_TABLEAU_START => {
+                self.foundations[slot - SLOT_FOUNDATION_START] = cards.last().map(|(card, _)| *card)
+            }
+            SLOT_TABLEAU_START..SLOT_TABLEAU_END => {
+                let tableau = &mut self.tableaus[slot - SLOT_TABLEAU_START];
+                tableau.face_up_count = cards.iter().filter(|(_, face_up)| *face_up).count();
+                tableau.cards = cards.into_iter().map(|(card, _)| card).collect();
+            }
+            _ => bail!("Unknown slot {slot}"),
+        }
+        Ok(())
+    }
+}
+
+fn parse_card(expr: &Sexp) -> Result<(Card, bool)> {
+    let Sexp::List(items) = expr else {
+        bail!("Invalid card {expr:?}");
+    };
+    let [Sexp::Atom(value), Sexp::Atom(suit), Sexp::Atom(face_up)] = items.as_slice() else {
+        bail!("Invalid card {expr:?}");
+    };
+    let rank = value
+        .parse::<u8>()
+        .ok()
+        .filter(|v| (1..=MAX_RANK).contains(v))
+        .with_context(|| format!("Invalid card value {value}"))?;
+    let suit = SUITS
+        .iter()
+        .position(|s| s == suit)
+        .with_context(|| format!("Invalid card suit {suit}"))?;
+    Ok((
+        Card::new_with_rank_suit(rank - 1, suit as u8),
+        face_up == "#t",
+    ))
+}
+
+fn parse_sexps(content: &str) -> Result<Vec<Sexp>> {
+    let mut stack: Vec<Vec<Sexp>> = vec![vec![]];
+    let mut atom = String::new();
+    for c in content.chars() {
+        if c == '(' || c == ')' || c.is_whitespace() {
+            if !atom.is_empty() {
+                let last = stack.last_mut().context("Unbalanced parentheses")?;
+                last.push(Sexp::Atom(std::mem::take(&mut atom)));
+            }
+            if c == '(' {
+                stack.push(vec![]);
+            } else if c == ')' {
+                let list = stack.pop().context("Unbalanced parentheses")?;
+                let last = stack.last_mut().context("Unbalanced parentheses")?;
+                last.push(Sexp::List(list));
+            }
+        } else {
+            atom.push(c);
+        }
+    }
+    if stack.len() != 1 {
+        bail!("Unbalanced parentheses");
+    }
+    Ok(stack.pop().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deal of greenfelt game 283409412, dumped in AisleRiot notation
+    const SAVE: &str = r#"(deal-three #t)
+(0 ((6 club #f) (7 diamond #f) (8 diamond #f) (4 spade #f) (10 spade #f) (11 spade #f) (2 club #f) (2 spade #f) (8 heart #f) (13 spade #f) (5 club #f) (9 spade #f) (9 diamond #f) (3 spade #f) (12 club #f) (6 heart #f) (5 diamond #f) (7 spade #f) (8 spade #f) (7 heart #f) (8 club #f) (3 diamond #f) (1 heart #f) (4 diamond #f)))
+(1 ())
+(2 ())
+(3 ())
+(4 ())
+(5 ())
+(6 ((13 diamond #t)))
+(7 ((4 heart #t) (12 heart #f)))
+(8 ((12 diamond #t) (10 club #f) (9 heart #f)))
+(9 ((3 club #t) (5 heart #f) (11 diamond #f) (6 spade #f)))
+(10 ((6 diamond #t) (11 club #f) (13 heart #f) (1 diamond #f) (12 spade #f)))
+(11 ((10 diamond #t) (5 spade #f) (1 spade #f) (3 heart #f) (11 heart #f) (2 heart #f)))
+(12 ((1 club #t) (9 club #f) (2 diamond #f) (13 club #f) (7 club #f) (10 heart #f) (4 club #f)))
+"#;
+
+    #[test]
+    fn test_parse_aisleriot() {
+        let board = Board::parse_aisleriot(SAVE).unwrap();
+        let mut expected = Board::new_from_seed(283409412);
+        expected.set_draw_count(3);
+        assert!(board.is_valid());
+        assert_eq!(board.to_pretty_string(), expected.to_pretty_string());
+
+        assert!(Board::parse_aisleriot("(0 ((14 club #f)))").is_err());
+        assert!(Board::parse_aisleriot("(0 ((1 club #f))").is_err());
+    }
+}
@@ -1,5 +1,5 @@
-use anyhow::{Context, Result};
 use smallvec::SmallVec;
+use std::{fmt, str::FromStr};
 
 pub const TOTAL_FOUNDATIONS: usize = 4;
 pub const TOTAL_TABLEAUS: usize = 7;
@@ -13,8 +13,11 @@ const RANKS: [char; 14] = [
     'A', '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K', '?',
 ];
 const TABLEAU_SIZE: usize = 19;
+const VEGAS_BET: i32 = 52; // Dollars paid for each deal in Vegas scoring
+const VEGAS_CARD_VALUE: i32 = 5; // Dollars won per card on the foundations in Vegas scoring
+const DEALT_FACE_DOWN: usize = TALON_SIZE + TOTAL_TABLEAUS * (TOTAL_TABLEAUS - 1) / 2; // Stock and hidden tableau cards of a new deal
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct Board {
     pub stock: SmallVec<[Card; TALON_SIZE]>,
     pub waste: SmallVec<[Card; TALON_SIZE]>,
@@ -32,43 +35,24 @@ impl Board {
     }
 
     pub fn new_from_seed(seed: u32) -> Self {
-        let mut current_seed = seed;
-        let mut rnd = || {
-            current_seed = ((current_seed as u64 * 16807) % 0x7fffffff) as u32;
-            current_seed
-        };
-        let mut deck: [Card; 52] = std::array::from_fn(|i| {
-            let card = Card::new_with_id(i as u8);
-            if card.suit() == 0 {
-                Card::new_with_rank_suit(card.rank(), 1)
-            } else if card.suit() == 1 {
-                Card::new_with_rank_suit(card.rank(), 0)
-            } else {
-                card
-            }
-        });
-
-        for _ in 0..7 {
-            for j in 0..52 {
-                let k = (rnd() % 52) as usize;
-                deck.swap(j, k);
-            }
-        }
-
-        deck.rotate_left(24);
+        Self::deal(&greenfelt_deck(seed))
+    }
 
-        let mut orig: i32 = 27;
-        for i in 0..7_i32 {
-            let mut pos = (i + 1) * (i + 2) / 2 - 1;
-            for j in (0..=(6 - i)).rev() {
-                if j >= i {
-                    deck.swap(pos as usize, orig as usize);
-                }
-                orig -= 1;
-                pos += 6 - j + 1;
-            }
+    /// Deal an ordered deck, e.g. from another game or hand-crafted, failing unless it is a full deck.
+    ///
+    /// Tableau N takes the next N cards with the last one face up, so the first 28 cards fill
+    /// the tableaus; the rest go to the stock, whose top is the last card of the deck.
+    pub fn from_deck(cards: [Card; 52], draw_count: usize) -> Result<Self, BoardParseError> {
+        if draw_count == 0 {
+            return Err(BoardParseError::BadDrawCount(draw_count.to_string()));
         }
+        let mut board = Self::deal(&cards);
+        board.set_draw_count(draw_count);
+        board.validate()?;
+        Ok(board)
+    }
 
+    fn deal(deck: &[Card]) -> Self {
         let mut board = Board::new();
 
         let mut m = 0;
@@ -86,8 +70,15 @@ impl Board {
         board
     }
 
+    /// Deal the greenfelt game of a URL like `https://greenfelt.net/klondike?game=283409412`,
+    /// see [`greenfelt_seed`] for the accepted forms.
+    pub fn from_greenfelt_url(url: &str) -> Result<Self, BoardParseError> {
+        Ok(Self::new_from_seed(greenfelt_seed(url)?))
+    }
+
+    /// Cards turned over from the stock per draw, at least 1
     pub fn draw_count(&self) -> usize {
-        if self.draw_count == 3 { 3 } else { 1 }
+        self.draw_count.max(1)
     }
 
     pub fn set_draw_count(&mut self, value: usize) {
@@ -104,52 +95,232 @@ impl Board {
             .sum()
     }
 
-    pub fn is_valid(&self) -> bool {
-        let draw_count = self.draw_count();
-        if draw_count != 1 && draw_count != 3 {
+    /// Cards not seen yet: the face-down cards of the tableaus and the stock
+    pub fn face_down_count(&self) -> usize {
+        let hidden: usize = self
+            .tableaus
+            .iter()
+            .map(|tableau| tableau.len() - tableau.face_up_count.min(tableau.len()))
+            .sum();
+        hidden + self.stock.len()
+    }
+
+    /// Turn every tableau card face up, as in Thoughtful Klondike where the player sees them all.
+    ///
+    /// Meant for display: the solver reads the face-up cards as runs to move together.
+    pub fn make_thoughtful(&mut self) {
+        for tableau in &mut self.tableaus {
+            tableau.face_up_count = tableau.len();
+        }
+    }
+
+    /// Progress from 0.0 for a new deal to 1.0 for a win, averaging the cards on the
+    /// foundations and the cards revealed since the deal
+    pub fn progress_fraction(&self) -> f32 {
+        let foundations = self.foundation_score() as f32 / MAX_CARD as f32;
+        let revealed =
+            DEALT_FACE_DOWN.saturating_sub(self.face_down_count()) as f32 / DEALT_FACE_DOWN as f32;
+        ((foundations + revealed) / 2.0).clamp(0.0, 1.0)
+    }
+
+    /// Whether the game is won by moving the cards to the foundations in order: the stock
+    /// is empty, a draw turns the whole waste over and every tableau card is face up.
+    /// A board a move away from winning, or won, doesn't need it.
+    pub fn can_autofinish(&self) -> bool {
+        self.stock.is_empty()
+            && self.waste.len() <= self.draw_count()
+            && self
+                .waste
+                .is_sorted_by_key(|card| (card.rank(), card.suit()))
+            && self.foundation_score() < MAX_CARD - 1
+            && self
+                .tableaus
+                .iter()
+                .all(|tableau| tableau.face_up_count >= tableau.len())
+    }
+
+    /// Whether some tableau card can never be uncovered, so the game is lost whatever the moves.
+    ///
+    /// The cards leave the stock, the waste and the face-up runs freely, but the card resting on
+    /// face-down ones only leaves when it can go to the foundation, onto a card of the other color
+    /// one rank higher or, for a King, to an empty column. A card needing cards buried under it,
+    /// directly or through other columns, blocks its column for good. `false` proves nothing.
+    pub fn is_obviously_unwinnable(&self) -> bool {
+        let mut cards = self.cards().chain(self.foundations.iter().flatten());
+        if cards.any(Card::is_unknown) {
             return false;
         }
 
-        let mut seen = [false; MAX_CARD as usize];
-        let mut count = 0;
-        let mut check_cards = |cards: &[Card]| -> bool {
-            for &card in cards {
-                if card.is_unknown() {
-                    return false;
-                }
-                let id = card.id() as usize;
-                if seen[id] {
-                    return false;
+        let mut reachable = [false; MAX_CARD as usize];
+        for card in self.stock.iter().chain(&self.waste) {
+            reachable[card.id() as usize] = true;
+        }
+        for card in self.foundations.iter().flatten() {
+            for rank in 0..=card.rank() {
+                reachable[Card::new_with_rank_suit(rank, card.suit()).id() as usize] = true;
+            }
+        }
+        // Cards of each column still buried under the card that has to leave first
+        let mut buried = [0; TOTAL_TABLEAUS];
+        for (tableau, buried) in self.tableaus.iter().zip(&mut buried) {
+            *buried = tableau.len().saturating_sub(tableau.face_up_count.max(1));
+            for card in &tableau.cards[*buried..] {
+                reachable[card.id() as usize] = true;
+            }
+        }
+
+        let can_leave = |card: &Card, reachable: &[bool]| {
+            let (rank, suit) = (card.rank(), card.suit());
+            rank == MAX_RANK - 1
+                || (0..rank).all(|r| reachable[Card::new_with_rank_suit(r, suit).id() as usize])
+                || (0..MAX_SUIT).any(|s| {
+                    (s ^ suit) & 1 == 1
+                        && reachable[Card::new_with_rank_suit(rank + 1, s).id() as usize]
+                })
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (tableau, buried) in self.tableaus.iter().zip(&mut buried) {
+                while *buried > 0 && can_leave(&tableau.cards[*buried], &reachable) {
+                    *buried -= 1;
+                    reachable[tableau.cards[*buried].id() as usize] = true;
+                    changed = true;
                 }
-                seen[id] = true;
-                count += 1;
             }
-            true
+        }
+        buried.iter().any(|&buried| buried > 0)
+    }
+
+    /// Dollars won in Vegas scoring, from -52 with no card on the foundations to 208 for a win
+    pub fn vegas_score(&self) -> i32 {
+        ScoreMode::Vegas.score(self.foundation_score())
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Check that the board holds each card of the deck exactly once, see
+    /// [`BoardParseError::InvalidDeck`] for the problems found otherwise.
+    pub fn validate(&self) -> Result<(), BoardParseError> {
+        let mut counts = [0usize; MAX_CARD as usize];
+        let mut unknown = 0;
+        let mut total = 0;
+        let mut count = |card: Card| {
+            total += 1;
+            if card.is_unknown() {
+                unknown += 1;
+            } else {
+                counts[card.id() as usize] += 1;
+            }
         };
+        self.stock.iter().copied().for_each(&mut count);
+        self.waste.iter().copied().for_each(&mut count);
+        for card in self.foundations.iter().flatten() {
+            for rank in 0..=card.rank() {
+                count(Card::new_with_rank_suit(rank, card.suit()));
+            }
+        }
+        for tableau in &self.tableaus {
+            tableau.cards.iter().copied().for_each(&mut count);
+        }
 
-        if !check_cards(&self.stock) {
-            return false;
+        let cards_with_count = |matches: fn(usize) -> bool| -> Vec<Card> {
+            (0..MAX_CARD)
+                .filter(|&id| matches(counts[id as usize]))
+                .map(Card::new_with_id)
+                .collect()
+        };
+        let duplicates = cards_with_count(|n| n > 1);
+        let missing = cards_with_count(|n| n == 0);
+        if unknown > 0
+            || !duplicates.is_empty()
+            || !missing.is_empty()
+            || total != MAX_CARD as usize
+        {
+            return Err(BoardParseError::InvalidDeck {
+                unknown,
+                duplicates,
+                missing,
+                total,
+            });
         }
-        if !check_cards(&self.waste) {
-            return false;
+        Ok(())
+    }
+
+    /// Check that the unknown cards can be filled with the cards missing from the board,
+    /// i.e. no card is found twice and as many cards are unknown as are missing.
+    pub fn validate_hidden(&self) -> Result<(), BoardParseError> {
+        match self.validate() {
+            Err(BoardParseError::InvalidDeck {
+                unknown,
+                ref duplicates,
+                ref missing,
+                ..
+            }) if duplicates.is_empty() && unknown == missing.len() => Ok(()),
+            result => result,
         }
-        for &card in &self.foundations {
-            let Some(card) = card else {
-                continue;
-            };
-            let cards: Vec<_> = (0..=card.rank())
-                .map(|r| Card::new_with_rank_suit(r, card.suit()))
-                .collect();
-            if !check_cards(&cards) {
-                return false;
+    }
+
+    /// Replace the unknown cards with a random arrangement of the cards missing from the board,
+    /// failing unless they fit as checked by [`Board::validate_hidden`].
+    ///
+    /// The same seed always gives the same arrangement.
+    pub fn sample_hidden(&self, seed: u32) -> Result<Self, BoardParseError> {
+        self.validate_hidden()?;
+        let mut current_seed = seed.max(1);
+        let mut rnd = || {
+            current_seed = ((current_seed as u64 * 16807) % 0x7fffffff) as u32;
+            current_seed
+        };
+
+        let mut missing = self.missing_cards();
+        for i in (1..missing.len()).rev() {
+            let j = rnd() as usize % (i + 1);
+            missing.swap(i, j);
+        }
+        Ok(self.fill_unknown(missing.into_iter().rev()))
+    }
+
+    /// Cards of the deck that appear nowhere on the board, in id order
+    pub fn missing_cards(&self) -> Vec<Card> {
+        let mut seen = [false; MAX_CARD as usize];
+        let mut mark = |card: &Card| {
+            if !card.is_unknown() {
+                seen[card.id() as usize] = true;
+            }
+        };
+        self.stock.iter().for_each(&mut mark);
+        self.waste.iter().for_each(&mut mark);
+        for card in self.foundations.iter().flatten() {
+            for rank in 0..=card.rank() {
+                mark(&Card::new_with_rank_suit(rank, card.suit()));
             }
         }
         for tableau in &self.tableaus {
-            if !check_cards(&tableau.cards) {
-                return false;
+            tableau.cards.iter().for_each(&mut mark);
+        }
+        (0..MAX_CARD)
+            .filter(|&id| !seen[id as usize])
+            .map(Card::new_with_id)
+            .collect()
+    }
+
+    /// Replace the unknown cards, stock first, then waste and tableaus, with `cards` in order.
+    pub fn fill_unknown(&self, cards: impl IntoIterator<Item = Card>) -> Self {
+        let mut cards = cards.into_iter();
+        let mut board = self.clone();
+        let piles = [&mut board.stock, &mut board.waste]
+            .into_iter()
+            .map(|pile| pile.as_mut_slice())
+            .chain(board.tableaus.iter_mut().map(|t| t.cards.as_mut_slice()));
+        for pile in piles {
+            for card in pile.iter_mut().filter(|card| card.is_unknown()) {
+                *card = cards.next().unwrap_or_default();
             }
         }
-        count == MAX_CARD as usize
+        board
     }
 
     pub fn need_redeal(&self) -> bool {
@@ -213,17 +384,30 @@ impl Board {
         self.draw_count = other.draw_count;
     }
 
-    pub fn parse(content: &str) -> Result<Self> {
+    /// Parse a board in the format of [`Board::to_pretty_string`].
+    ///
+    /// A foundation is given by its top card, e.g. `Foundation1: 5♦` for A♦ to 5♦, and is empty
+    /// as `Foundation1:` or `Foundation1: 0`. Given by its count of cards, e.g. `Foundation1: 5`,
+    /// it takes the one suit that is on no other foundation and whose Ace to 5 are nowhere else
+    /// on the board. Such foundations are settled one at a time, those with a single such suit
+    /// first, and fail with [`BoardParseError::AmbiguousFoundation`] once none is left with one.
+    pub fn parse(content: &str) -> Result<Self, BoardParseError> {
         let mut board: Self = Default::default();
+        // Foundations given by their count of cards, with the line
+        let mut foundation_counts: [Option<(u8, &str)>; TOTAL_FOUNDATIONS] = Default::default();
 
         for line in content
             .split('\n')
             .map(|v| v.trim())
             .filter(|l| !l.is_empty())
         {
-            let line_context = || format!("Failed to parse at '{line}'");
+            // Card errors don't tell which line they come from
+            let at_line = |source| BoardParseError::AtLine {
+                line: line.to_string(),
+                source: Box::new(source),
+            };
             if let Some(rest) = line.strip_prefix("Stock:") {
-                for card in Self::parse_cards(rest.trim()).with_context(line_context)? {
+                for card in Self::parse_cards(rest.trim()).map_err(at_line)? {
                     board.stock.push(card);
                 }
             } else if let Some(rest) = line.strip_prefix("Waste:") {
@@ -233,8 +417,8 @@ impl Board {
                 } else {
                     (rest, "")
                 };
-                let cards = Self::parse_cards(before.trim()).with_context(line_context)?;
-                let visible_cards = Self::parse_cards(after.trim()).with_context(line_context)?;
+                let cards = Self::parse_cards(before.trim()).map_err(at_line)?;
+                let visible_cards = Self::parse_cards(after.trim()).map_err(at_line)?;
                 for c in [cards, visible_cards].concat() {
                     board.waste.push(c);
                 }
@@ -245,12 +429,26 @@ impl Board {
                     .unwrap_or("")
                     .trim()
                     .parse::<usize>()
-                    .context("Invalid foundation index")
-                    .with_context(line_context)?;
+                    .ok()
+                    .filter(|idx| (1..=TOTAL_FOUNDATIONS).contains(idx))
+                    .ok_or_else(|| BoardParseError::BadFoundationIndex(line.to_string()))?;
                 let idx = idx - 1;
-                let cards = Self::parse_cards(parts.next().unwrap_or("").trim())
-                    .with_context(line_context)?;
-                board.foundations[idx] = cards.last().cloned();
+                let value = parts.next().unwrap_or("").trim();
+                foundation_counts[idx] = None;
+                if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) {
+                    let count = value
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|&count| count <= MAX_RANK)
+                        .ok_or_else(|| at_line(BoardParseError::InvalidCard(value.to_string())))?;
+                    board.foundations[idx] = None;
+                    if count > 0 {
+                        foundation_counts[idx] = Some((count, line));
+                    }
+                } else {
+                    let cards = Self::parse_cards(value).map_err(at_line)?;
+                    board.foundations[idx] = cards.last().cloned();
+                }
             } else if let Some(rest) = line.strip_prefix("Tableau") {
                 let mut parts = rest.splitn(2, ':');
                 let idx = parts
@@ -258,8 +456,9 @@ impl Board {
                     .unwrap_or("")
                     .trim()
                     .parse::<usize>()
-                    .context("Invalid tableau index")
-                    .with_context(line_context)?;
+                    .ok()
+                    .filter(|idx| (1..=TOTAL_TABLEAUS).contains(idx))
+                    .ok_or_else(|| BoardParseError::BadTableauIndex(line.to_string()))?;
                 let idx = idx - 1;
                 let cards_str = parts.next().unwrap_or("").trim();
                 let (before, after) = if let Some(idx) = cards_str.find('|') {
@@ -268,8 +467,8 @@ impl Board {
                 } else {
                     (cards_str, "")
                 };
-                let cards = Self::parse_cards(before.trim()).with_context(line_context)?;
-                let face_up_cards = Self::parse_cards(after.trim()).with_context(line_context)?;
+                let cards = Self::parse_cards(before.trim()).map_err(at_line)?;
+                let face_up_cards = Self::parse_cards(after.trim()).map_err(at_line)?;
                 board.tableaus[idx].face_up_count = face_up_cards.len();
                 for c in [cards, face_up_cards].concat() {
                     board.tableaus[idx].cards.push(c);
@@ -278,16 +477,59 @@ impl Board {
                 let value = rest
                     .trim()
                     .parse::<usize>()
-                    .context("Invalid DrawCount")
-                    .with_context(line_context)?;
+                    .ok()
+                    .filter(|&value| value > 0)
+                    .ok_or_else(|| BoardParseError::BadDrawCount(rest.trim().to_string()))?;
                 board.set_draw_count(value);
             }
         }
 
+        while foundation_counts.iter().any(Option::is_some) {
+            let suits = |count: u8| {
+                (0..MAX_SUIT)
+                    .filter(|&suit| {
+                        board.foundations.iter().flatten().all(|c| c.suit() != suit)
+                            && !board.cards().any(|c| c.suit() == suit && c.rank() < count)
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let resolved = foundation_counts
+                .iter()
+                .enumerate()
+                .find_map(|(idx, value)| {
+                    let (count, _) = (*value)?;
+                    match suits(count).as_slice() {
+                        &[suit] => Some((idx, Card::new_with_rank_suit(count - 1, suit))),
+                        _ => None,
+                    }
+                });
+            let Some((idx, card)) = resolved else {
+                let (_, line) = foundation_counts.iter().flatten().next().unwrap();
+                return Err(BoardParseError::AmbiguousFoundation(line.to_string()));
+            };
+            board.foundations[idx] = Some(card);
+            foundation_counts[idx] = None;
+        }
+
         Ok(board)
     }
 
-    fn parse_cards(s: &str) -> Result<Vec<Card>> {
+    /// Cards of the stock, the waste and the tableaus
+    fn cards(&self) -> impl Iterator<Item = &Card> {
+        self.stock
+            .iter()
+            .chain(&self.waste)
+            .chain(self.tableaus.iter().flat_map(|tableau| &tableau.cards))
+    }
+
+    /// Parse the board like [`Board::parse`], failing unless it holds each card exactly once.
+    pub fn parse_validated(content: &str) -> Result<Self, BoardParseError> {
+        let board = Self::parse(content)?;
+        board.validate()?;
+        Ok(board)
+    }
+
+    fn parse_cards(s: &str) -> Result<Vec<Card>, BoardParseError> {
         let mut cards = Vec::new();
         let mut chars = s.chars().peekable();
         while let Some(&c1) = chars.peek() {
@@ -307,13 +549,40 @@ impl Board {
     }
 
     pub fn to_pretty_string(&self) -> String {
+        self.format_cards(|card, _| card.to_pretty_string())
+    }
+
+    /// Like [`Board::to_pretty_string`], with each card drawn as its single Unicode glyph.
+    ///
+    /// Meant for terminals with a font that has the glyphs, it can't be parsed back.
+    pub fn to_pretty_string_unicode(&self) -> String {
+        self.format_cards(|card, _| card.to_unicode_glyph().to_string())
+    }
+
+    /// Like [`Board::to_pretty_string`], with ANSI colors for terminals: red suits in red,
+    /// face-down and unknown cards dimmed.
+    pub fn to_pretty_string_colored(&self) -> String {
+        self.format_cards(|card, face_up| {
+            let card_str = card.to_pretty_string();
+            if !face_up || card.is_unknown() {
+                format!("\x1b[2m{card_str}\x1b[0m")
+            } else if matches!(card.suit(), 0 | 2) {
+                format!("\x1b[31m{card_str}\x1b[0m")
+            } else {
+                card_str
+            }
+        })
+    }
+
+    /// Format each pile with `format_card`, which is also told whether the card is face up
+    fn format_cards(&self, format_card: impl Fn(&Card, bool) -> String) -> String {
         let mut output = String::new();
 
         // Stock
         if !self.stock.is_empty() {
             output.push_str("Stock: ");
             for card in &self.stock {
-                output.push_str(&card.to_pretty_string());
+                output.push_str(&format_card(card, false));
             }
             output.push('\n');
         }
@@ -322,7 +591,7 @@ impl Board {
         if !self.waste.is_empty() {
             output.push_str("Waste: ");
             for card in &self.waste {
-                output.push_str(&card.to_pretty_string());
+                output.push_str(&format_card(card, true));
             }
             output.push('\n');
         }
@@ -333,7 +602,7 @@ impl Board {
                 output.push_str(&format!(
                     "Foundation{}: {}\n",
                     i + 1,
-                    card.to_pretty_string()
+                    format_card(card, true)
                 ));
             }
         }
@@ -341,6 +610,7 @@ impl Board {
         // Tableaus
         for (i, tableau) in self.tableaus.iter().enumerate() {
             if tableau.is_empty() {
+                output.push_str(&format!("Tableau{}:\n", i + 1));
                 continue;
             }
             output.push_str(&format!("Tableau{}: ", i + 1));
@@ -351,7 +621,7 @@ impl Board {
                 if j == sep && face_up > 0 {
                     output.push('|');
                 }
-                output.push_str(&card.to_pretty_string());
+                output.push_str(&format_card(card, j >= sep));
             }
             output.push('\n');
         }
@@ -361,9 +631,187 @@ impl Board {
 
         output
     }
+
+    /// Display the board with column headers and a legend, for reading rather than parsing.
+    ///
+    /// Use [`Board::to_pretty_string`] for the round-trippable form.
+    pub fn to_annotated_string(&self) -> String {
+        let mut output = format!("Draw count: {}\n", self.draw_count());
+
+        let waste = match self.waste.last() {
+            Some(card) => format!("{}, top {}", self.waste.len(), card.to_pretty_string()),
+            None => "--".into(),
+        };
+        output.push_str(&format!("Stock: {}   Waste: {waste}\n", self.stock.len()));
+
+        let foundations: Vec<_> = self
+            .foundations
+            .iter()
+            .enumerate()
+            .map(|(i, card)| {
+                let card = card.map_or("--".into(), |c| c.to_pretty_string());
+                format!("F{}: {card}", i + 1)
+            })
+            .collect();
+        output.push_str(&format!("Foundations: {}\n\n", foundations.join("  ")));
+
+        output.push_str("   ");
+        for i in 0..TOTAL_TABLEAUS {
+            output.push_str(&format!("  T{} ", i + 1));
+        }
+        output.truncate(output.trim_end().len());
+        output.push('\n');
+        let rows = self
+            .tableaus
+            .iter()
+            .map(|t| t.len())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        for row in 0..rows {
+            output.push_str(&format!("{:>2} ", row + 1));
+            for tableau in &self.tableaus {
+                let face_down = tableau.len().saturating_sub(tableau.face_up_count);
+                let cell = match tableau.cards.get(row) {
+                    Some(card) if row < face_down => format!("[{}]", card.to_pretty_string()),
+                    Some(card) => format!(" {} ", card.to_pretty_string()),
+                    None if row == 0 => " -- ".into(),
+                    None => "    ".into(),
+                };
+                output.push(' ');
+                output.push_str(&cell);
+            }
+            output.truncate(output.trim_end().len());
+            output.push('\n');
+        }
+
+        output.push_str("\nLegend: [..] face-down, -- empty, T tableau, F foundation, rows from the bottom card");
+        output
+    }
+
+    /// A two-line summary of the board, giving each tableau as its face-up cards after
+    /// the count of its face-down cards, e.g. `3+9♣8♦`.
+    pub fn to_compact_string(&self) -> String {
+        let format_top = |card: Option<&Card>| card.map_or("--".into(), Card::to_pretty_string);
+        let foundations: Vec<_> = self
+            .foundations
+            .iter()
+            .map(|card| format_top(card.as_ref()))
+            .collect();
+        let tableaus: Vec<_> = self
+            .tableaus
+            .iter()
+            .map(|tableau| {
+                let face_down = tableau.len().saturating_sub(tableau.face_up_count);
+                let face_up: String = tableau.cards[face_down..]
+                    .iter()
+                    .map(Card::to_pretty_string)
+                    .collect();
+                match (face_down, face_up.is_empty()) {
+                    (0, true) => "--".into(),
+                    (0, false) => face_up,
+                    _ => format!("{face_down}+{face_up}"),
+                }
+            })
+            .collect();
+        format!(
+            "Stock: {}  Waste: {}  Foundations: {}\nTableaus: {}",
+            self.stock.len(),
+            format_top(self.waste.last()),
+            foundations.join(" "),
+            tableaus.join(" ")
+        )
+    }
+}
+
+/// How a game is scored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScoreMode {
+    /// One point per card on the foundations
+    #[default]
+    Standard,
+    /// $52 bet on each deal, $5 back per card on the foundations
+    Vegas,
+    /// Vegas, with the balance carried over to the next deal
+    VegasCumulative,
+}
+
+impl ScoreMode {
+    /// Score of a deal with `foundation_cards` cards on the foundations
+    pub fn score(self, foundation_cards: u8) -> i32 {
+        match self {
+            ScoreMode::Standard => foundation_cards as i32,
+            ScoreMode::Vegas | ScoreMode::VegasCumulative => {
+                foundation_cards as i32 * VEGAS_CARD_VALUE - VEGAS_BET
+            }
+        }
+    }
+}
+
+/// The deck of the greenfelt.net deal of `seed`, ordered for [`Board::from_deck`].
+///
+/// This is the only implementation of the greenfelt shuffle, the app deals from it too.
+pub fn greenfelt_deck(seed: u32) -> [Card; 52] {
+    let mut current_seed = seed;
+    let mut rnd = || {
+        current_seed = ((current_seed as u64 * 16807) % 0x7fffffff) as u32;
+        current_seed
+    };
+    // Greenfelt orders the suits ♣ ♦ ♥ ♠, ours are ♦ ♣ ♥ ♠
+    let mut deck: [Card; 52] = std::array::from_fn(|i| {
+        let card = Card::new_with_id(i as u8);
+        if card.suit() == 0 {
+            Card::new_with_rank_suit(card.rank(), 1)
+        } else if card.suit() == 1 {
+            Card::new_with_rank_suit(card.rank(), 0)
+        } else {
+            card
+        }
+    });
+
+    for _ in 0..7 {
+        for j in 0..52 {
+            let k = (rnd() % 52) as usize;
+            deck.swap(j, k);
+        }
+    }
+
+    deck.rotate_left(24);
+
+    let mut orig: i32 = 27;
+    for i in 0..7_i32 {
+        let mut pos = (i + 1) * (i + 2) / 2 - 1;
+        for j in (0..=(6 - i)).rev() {
+            if j >= i {
+                deck.swap(pos as usize, orig as usize);
+            }
+            orig -= 1;
+            pos += 6 - j + 1;
+        }
+    }
+
+    deck
+}
+
+/// Extract the game ID from a greenfelt.net seed or URL.
+///
+/// Accepts a bare number, the `?game=283409412` query or the `/klondike/283409412` path.
+pub fn greenfelt_seed(input: &str) -> Result<u32, BoardParseError> {
+    let input = input.trim();
+    if let Ok(seed) = input.parse() {
+        return Ok(seed);
+    }
+    let url = input.split('#').next().unwrap_or_default();
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("game="))
+        .or_else(|| path.trim_end_matches('/').rsplit('/').next())
+        .and_then(|seed| seed.parse().ok())
+        .ok_or_else(|| BoardParseError::InvalidGreenfeltUrl(input.to_string()))
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct Tableau {
     pub cards: SmallVec<[Card; TABLEAU_SIZE]>,
     pub face_up_count: usize,
@@ -420,7 +868,7 @@ impl Tableau {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Card(u8);
 
 impl Card {
@@ -438,15 +886,19 @@ impl Card {
         Self(suit * MAX_RANK + rank)
     }
 
-    pub fn parse(rank: char, suit: char) -> Result<Self> {
+    pub fn parse(rank: char, suit: char) -> Result<Self, BoardParseError> {
+        let card = || format!("{rank}{suit}");
         let rank = RANKS
             .iter()
             .position(|&r| r == rank)
-            .with_context(|| format!("Invalid rank at card {rank}{suit}"))?;
+            .ok_or_else(|| BoardParseError::InvalidRank(card()))?;
         let suit = SUITS
             .iter()
             .position(|&s| s == suit)
-            .with_context(|| format!("Invalid suit at card {rank}{suit}"))?;
+            .ok_or_else(|| BoardParseError::InvalidSuit(card()))?;
+        if rank == MAX_RANK as usize || suit == MAX_SUIT as usize {
+            return Ok(Card::UNKNOWN);
+        }
         Ok(Card::new_with_rank_suit(rank as u8, suit as u8))
     }
 
@@ -467,7 +919,36 @@ impl Card {
     }
 
     pub fn to_pretty_string(&self) -> String {
-        format!(
+        self.to_string()
+    }
+
+    /// The Unicode playing card of the card, e.g. `🂱` for A♥, or the card back `🂠` if unknown
+    pub fn to_unicode_glyph(&self) -> char {
+        if self.is_unknown() {
+            return '\u{1F0A0}';
+        }
+        let suit_base = match self.suit() {
+            0 => 0x1F0C0, // ♦
+            1 => 0x1F0D0, // ♣
+            2 => 0x1F0B0, // ♥
+            _ => 0x1F0A0, // ♠
+        };
+        // The Knight takes the code point between Jack and Queen
+        let rank = match self.rank() {
+            rank @ 0..=10 => rank as u32 + 1,
+            rank => rank as u32 + 2,
+        };
+        char::from_u32(suit_base + rank).unwrap_or('\u{1F0A0}')
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_unknown() {
+            return write!(f, "??");
+        }
+        write!(
+            f,
             "{}{}",
             RANKS[self.rank() as usize],
             SUITS[self.suit() as usize]
@@ -475,12 +956,124 @@ impl Card {
     }
 }
 
+impl FromStr for Card {
+    type Err = BoardParseError;
+
+    /// Parse a card like `T♥`, also accepting `10` for ten and the suit letters `CDSH`.
+    fn from_str(s: &str) -> Result<Self, BoardParseError> {
+        let value = s.trim();
+        let (rank, suit) = match value.strip_prefix("10") {
+            Some(suit) => ('T', suit),
+            None => {
+                let mut chars = value.chars();
+                let rank = chars.next().unwrap_or_default();
+                (rank.to_ascii_uppercase(), chars.as_str())
+            }
+        };
+        let mut chars = suit.chars();
+        let (Some(suit), None) = (chars.next(), chars.next()) else {
+            return Err(BoardParseError::InvalidCard(s.to_string()));
+        };
+        let suit = match suit.to_ascii_uppercase() {
+            'D' => '♦',
+            'C' => '♣',
+            'H' => '♥',
+            'S' => '♠',
+            suit => suit,
+        };
+        Card::parse(rank, suit)
+    }
+}
+
 impl Default for Card {
     fn default() -> Self {
         Card::UNKNOWN
     }
 }
 
+/// Why a board or a card failed to parse, or a board doesn't hold a full deck
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoardParseError {
+    InvalidRank(String),         // The card, e.g. `X♦`
+    InvalidSuit(String),         // The card, e.g. `AX`
+    InvalidCard(String),         // Text that isn't a rank followed by a suit
+    BadFoundationIndex(String),  // The line, e.g. `Foundation5: A♦`
+    AmbiguousFoundation(String), // The line of a foundation given by count that fits no suit or several
+    BadTableauIndex(String),     // The line, e.g. `Tableau0: K♠`
+    BadDrawCount(String),        // The value, not a number of at least 1
+    InvalidGreenfeltUrl(String), // The input without a game ID
+    InvalidDeck {
+        unknown: usize,        // Cards whose rank or suit is `?`
+        duplicates: Vec<Card>, // Cards found more than once
+        missing: Vec<Card>,    // Cards found nowhere
+        total: usize,          // Cards on the board, unknown ones included
+    },
+    AtLine {
+        line: String,                 // The line of the board, e.g. `Tableau1: |X♦`
+        source: Box<BoardParseError>, // What is wrong with it, e.g. an invalid card
+    },
+}
+
+impl fmt::Display for BoardParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardParseError::InvalidRank(card) => write!(f, "Invalid rank at card {card}"),
+            BoardParseError::InvalidSuit(card) => write!(f, "Invalid suit at card {card}"),
+            BoardParseError::InvalidCard(card) => write!(f, "Invalid card {card}"),
+            BoardParseError::BadFoundationIndex(line) => {
+                write!(f, "Invalid foundation index at '{line}'")
+            }
+            BoardParseError::AmbiguousFoundation(line) => {
+                write!(
+                    f,
+                    "Cannot tell the suit of the foundation at '{line}', give its top card instead"
+                )
+            }
+            BoardParseError::BadTableauIndex(line) => {
+                write!(f, "Invalid tableau index at '{line}'")
+            }
+            BoardParseError::BadDrawCount(value) => {
+                write!(f, "Invalid draw count '{value}', it must be at least 1")
+            }
+            BoardParseError::InvalidGreenfeltUrl(input) => {
+                write!(f, "No greenfelt game ID found in `{input}`")
+            }
+            BoardParseError::InvalidDeck {
+                unknown,
+                duplicates,
+                missing,
+                total,
+            } => {
+                let cards = |cards: &[Card]| {
+                    let cards: Vec<_> = cards.iter().map(Card::to_pretty_string).collect();
+                    cards.join(", ")
+                };
+                let mut problems = vec![];
+                if *unknown > 0 {
+                    problems.push(format!("{unknown} unknown cards"));
+                }
+                match duplicates.len() {
+                    0 => {}
+                    1 => problems.push(format!("duplicate card {}", duplicates[0])),
+                    _ => problems.push(format!("duplicate cards: {}", cards(duplicates))),
+                }
+                match missing.len() {
+                    0 => {}
+                    1 => problems.push(format!("missing card {}", missing[0])),
+                    _ => problems.push(format!("missing cards: {}", cards(missing))),
+                }
+                if *total != MAX_CARD as usize {
+                    problems.push(format!("{total} cards total"));
+                }
+                write!(f, "{}", problems.join("; "))
+            }
+            BoardParseError::AtLine { line, source } => write!(f, "{source} in '{line}'"),
+        }
+    }
+}
+
+impl std::error::Error for BoardParseError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -505,6 +1098,111 @@ DrawCount: 3"#;
         assert_eq!(BOARD_STR, board.to_pretty_string());
     }
 
+    #[test]
+    fn test_parse_foundation_count() {
+        const BOARD_STR: &str = "Waste: 9♠T♦
+Foundation1: 9♦
+Foundation2: 9♣
+Foundation3: 9♥
+Foundation4: 8♠
+Tableau1: |K♠Q♥J♣
+Tableau2: |K♥Q♣J♦T♠
+Tableau3: |K♣Q♦J♠T♥
+Tableau4: |K♦Q♠J♥T♣
+DrawCount: 3";
+        let board = Board::parse(BOARD_STR).unwrap();
+        let parse = |from: &[&str], to: &[&str]| {
+            let content = from
+                .iter()
+                .zip(to)
+                .fold(BOARD_STR.to_string(), |content, (f, t)| {
+                    content.replace(f, t)
+                });
+            Board::parse(&content)
+        };
+
+        // ♠ is the only suit left
+        assert_eq!(parse(&["8♠"], &["8"]), Ok(board.clone()));
+        // 9♠ is in the waste, so the 9 cards are ♥ and the 8 cards the remaining ♠
+        assert_eq!(parse(&["8♠", "9♥"], &["8", "9"]), Ok(board.clone()));
+        // The 9 cards could be ♦, ♣ or ♥
+        assert_eq!(
+            parse(&["9♦", "9♣", "9♥", "8♠"], &["9", "9", "9", "8"]),
+            Err(BoardParseError::AmbiguousFoundation(
+                "Foundation1: 9".to_string()
+            ))
+        );
+        assert_eq!(
+            parse(&["9♦"], &["14"]),
+            Err(BoardParseError::AtLine {
+                line: "Foundation1: 14".to_string(),
+                source: Box::new(BoardParseError::InvalidCard("14".to_string())),
+            })
+        );
+
+        for empty in ["", " 0"] {
+            let board = Board::parse(&format!("Foundation2:{empty}\nTableau1: |A♦")).unwrap();
+            assert_eq!(board.foundations, [None; TOTAL_FOUNDATIONS]);
+        }
+    }
+
+    #[test]
+    fn test_board_hash() {
+        let mut played = Board::new_from_seed(283409412);
+        played.draw();
+        played.move_tableau_to_foundation(6, 0);
+        let parsed = Board::parse(&played.to_pretty_string()).unwrap();
+        assert_eq!(parsed, played);
+
+        let boards: std::collections::HashSet<Board> = [played, Board::new_from_seed(283409412)]
+            .into_iter()
+            .collect();
+        assert!(boards.contains(&parsed));
+        assert_eq!(boards.len(), 2);
+    }
+
+    #[test]
+    fn test_to_annotated_string() {
+        let mut board = Board::new_from_seed(283409412);
+        board.draw();
+        board.move_tableau_to_foundation(6, 0);
+        assert_eq!(
+            board.to_annotated_string(),
+            r#"Draw count: 1
+Stock: 23   Waste: 1, top 6♣
+Foundations: F1: A♣  F2: --  F3: --  F4: --
+
+     T1   T2   T3   T4   T5   T6   T7
+ 1   K♦  [Q♥] [9♥] [6♠] [Q♠] [2♥] [4♣]
+ 2        4♥  [T♣] [J♦] [A♦] [J♥] [T♥]
+ 3             Q♦  [5♥] [K♥] [3♥] [7♣]
+ 4                  3♣  [J♣] [A♠] [K♣]
+ 5                       6♦  [5♠] [2♦]
+ 6                            T♦   9♣
+
+Legend: [..] face-down, -- empty, T tableau, F foundation, rows from the bottom card"#
+        );
+    }
+
+    #[test]
+    fn test_to_compact_string() {
+        let mut board = Board::new_from_seed(283409412);
+        assert_eq!(
+            board.to_compact_string(),
+            "Stock: 24  Waste: --  Foundations: -- -- -- --\n\
+             Tableaus: K♦ 1+4♥ 2+Q♦ 3+3♣ 4+6♦ 5+T♦ 6+A♣"
+        );
+        board.move_tableau_to_foundation(6, 1);
+        board.move_tableau_to_tableau(3, 1, 1);
+        board.move_tableau_to_tableau(0, 6, 1);
+        board.draw();
+        assert_eq!(
+            board.to_compact_string(),
+            "Stock: 23  Waste: 6♣  Foundations: -- A♣ -- --\n\
+             Tableaus: -- 1+4♥3♣ 2+Q♦ 2+5♥ 4+6♦ 5+T♦ 5+9♣K♦"
+        );
+    }
+
     #[test]
     fn test_new_board() {
         let board = Board::new();
@@ -513,6 +1211,220 @@ DrawCount: 3"#;
         assert!(!board.is_valid());
     }
 
+    #[test]
+    fn test_parse_validated() {
+        let board_str = Board::new_from_seed(283409412).to_pretty_string();
+        assert!(Board::parse_validated(&board_str).is_ok());
+
+        let duplicate = board_str.replace("Tableau1: |K♦", "Tableau1: |K♦A♠");
+        let err = Board::parse_validated(&duplicate).unwrap_err();
+        assert_eq!(err.to_string(), "duplicate card A♠; 53 cards total");
+        let ace = Card::new_with_rank_suit(0, 3);
+        assert!(
+            matches!(err, BoardParseError::InvalidDeck { duplicates, .. } if duplicates == [ace])
+        );
+
+        let missing = board_str
+            .replace("Tableau1: |K♦", "Tableau1:")
+            .replace("3♦8♣", "");
+        let err = Board::parse_validated(&missing).unwrap_err();
+        assert_eq!(err.to_string(), "missing cards: 3♦, K♦, 8♣; 49 cards total");
+        assert!(Board::parse(&missing).is_ok());
+    }
+
+    #[test]
+    fn test_parse_empty_tableau() {
+        let mut board = Board::new_from_seed(283409412);
+        let king = board.tableaus[0].pop_unchecked();
+        board.waste.push(king);
+        let board_str = board.to_pretty_string();
+        assert!(board_str.contains("\nTableau1:\nTableau2: "));
+        assert_eq!(Board::parse(&board_str).unwrap(), board);
+    }
+
+    #[test]
+    fn test_vegas_score() {
+        let mut board = Board::new_from_seed(283409412);
+        assert_eq!(board.vegas_score(), -52);
+        board.foundations[1] = Some(Card::new_with_rank_suit(2, 1));
+        board.foundations[3] = Some(Card::new_with_rank_suit(0, 3));
+        assert_eq!(board.vegas_score(), -32);
+        assert_eq!(ScoreMode::Standard.score(board.foundation_score()), 4);
+        assert_eq!(ScoreMode::VegasCumulative.score(52), 208);
+    }
+
+    #[test]
+    fn test_progress_fraction() {
+        let board = Board::new_from_seed(283409412);
+        assert_eq!(board.face_down_count(), 45);
+        assert_eq!(board.progress_fraction(), 0.0);
+
+        let board = Board::parse(
+            "Foundation1: K♦\nFoundation2: K♣\nFoundation3: K♥\nFoundation4: Q♠\nTableau1: |K♠\nDrawCount: 1",
+        )
+        .unwrap();
+        assert_eq!(board.face_down_count(), 0);
+        assert_eq!(board.progress_fraction(), (51.0 / 52.0 + 1.0) / 2.0);
+
+        let mut board = Board::parse("Stock: 2♠A♠\nTableau1: 3♠4♠5♠|6♠\nDrawCount: 1").unwrap();
+        board.tableaus[0].face_up_count = 9;
+        assert_eq!(board.face_down_count(), 2);
+    }
+
+    #[test]
+    fn test_make_thoughtful() {
+        let mut board = Board::new_from_seed(283409412);
+        board.make_thoughtful();
+        assert_eq!(board.face_down_count(), board.stock.len());
+        assert!(board.to_pretty_string().contains("Tableau7: |"));
+    }
+
+    #[test]
+    fn test_can_autofinish() {
+        const BOARD_STR: &str = "Waste: 9♠T♦
+Foundation1: 9♦
+Foundation2: 9♣
+Foundation3: 9♥
+Foundation4: 8♠
+Tableau1: |K♠Q♥J♣
+Tableau2: |K♥Q♣J♦T♠
+Tableau3: |K♣Q♦J♠T♥
+Tableau4: |K♦Q♠J♥T♣
+DrawCount: 3";
+        assert!(Board::parse(BOARD_STR).unwrap().can_autofinish());
+        let board = Board::parse(&BOARD_STR.replace("3", "1")).unwrap();
+        assert!(!board.can_autofinish());
+        let board = Board::parse(&BOARD_STR.replace("|K♠", "K♠|")).unwrap();
+        assert!(!board.can_autofinish());
+        assert!(!Board::new_from_seed(283409412).can_autofinish());
+    }
+
+    #[test]
+    fn test_is_obviously_unwinnable() {
+        // 4♠ needs A♠ or a red 5, all under it
+        let board = Board::parse("Tableau1: 5♥5♦A♠|4♠\nDrawCount: 1").unwrap();
+        assert!(board.is_obviously_unwinnable());
+        let board = Board::parse("Stock: 5♦\nTableau1: 5♥A♠|4♠\nDrawCount: 1").unwrap();
+        assert!(!board.is_obviously_unwinnable());
+        // Q♥ and Q♦ each need a black King buried under them, or A♥ and A♦ under them
+        let board = Board::parse("Tableau1: K♠A♥|Q♥\nTableau2: K♣A♦|Q♦\nDrawCount: 1").unwrap();
+        assert!(board.is_obviously_unwinnable());
+        assert!(!Board::new_from_seed(283409412).is_obviously_unwinnable());
+    }
+
+    #[test]
+    fn test_from_deck() {
+        let mut deck: [Card; 52] = std::array::from_fn(|i| Card::new_with_id(i as u8));
+        let board = Board::from_deck(deck, 3).unwrap();
+        assert_eq!(board.draw_count(), 3);
+        assert_eq!(board.tableaus[0].cards.as_slice(), &deck[..1]);
+        assert_eq!(board.tableaus[6].cards.as_slice(), &deck[21..28]);
+        assert_eq!(board.tableaus[6].face_up_count, 1);
+        assert_eq!(board.stock.as_slice(), &deck[28..]);
+
+        assert!(Board::from_deck(deck, 0).is_err());
+        assert_eq!(Board::from_deck(deck, 2).unwrap().draw_count(), 2);
+        deck[51] = deck[0];
+        let err = Board::from_deck(deck, 1).unwrap_err();
+        assert_eq!(err.to_string(), "duplicate card A♦; missing card K♠");
+    }
+
+    #[test]
+    fn test_card_from_str() {
+        let ten_of_hearts = Card::new_with_rank_suit(9, 2);
+        for s in ["T♥", "10♥", "TH", "th", "10h"] {
+            assert_eq!(s.parse::<Card>().unwrap(), ten_of_hearts);
+        }
+        assert_eq!(ten_of_hearts.to_string(), "T♥");
+        assert_eq!("As".parse::<Card>().unwrap().to_string(), "A♠");
+        assert!("??".parse::<Card>().unwrap().is_unknown());
+        assert_eq!(
+            "1♥".parse::<Card>(),
+            Err(BoardParseError::InvalidRank("1♥".to_string()))
+        );
+        assert_eq!(
+            "T".parse::<Card>(),
+            Err(BoardParseError::InvalidCard("T".to_string()))
+        );
+        assert!("T♥♥".parse::<Card>().is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let board_str = Board::new_from_seed(283409412).to_pretty_string();
+        let parse = |from: &str, to: &str| Board::parse(&board_str.replace(from, to));
+        assert_eq!(
+            parse("Tableau1:", "Tableau0:"),
+            Err(BoardParseError::BadTableauIndex(
+                "Tableau0: |K♦".to_string()
+            ))
+        );
+        assert_eq!(
+            parse("Tableau1: |K♦", "Tableau1: |K♦\nFoundation5: A♠"),
+            Err(BoardParseError::BadFoundationIndex(
+                "Foundation5: A♠".to_string()
+            ))
+        );
+        assert_eq!(
+            parse("DrawCount: 1", "DrawCount: 0"),
+            Err(BoardParseError::BadDrawCount("0".to_string()))
+        );
+        let err = parse("K♦", "KX").unwrap_err();
+        assert_eq!(
+            err,
+            BoardParseError::AtLine {
+                line: "Tableau1: |KX".to_string(),
+                source: Box::new(BoardParseError::InvalidSuit("KX".to_string())),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Invalid suit at card KX in 'Tableau1: |KX'"
+        );
+    }
+
+    #[test]
+    fn test_to_unicode_glyph() {
+        let glyph = |s: &str| s.parse::<Card>().unwrap().to_unicode_glyph();
+        assert_eq!(glyph("A♠"), '🂡');
+        assert_eq!(glyph("J♥"), '🂻');
+        assert_eq!(glyph("Q♦"), '🃍');
+        assert_eq!(glyph("K♣"), '🃞');
+        assert_eq!(Card::UNKNOWN.to_unicode_glyph(), '🂠');
+
+        let board = Board::new_from_seed(283409412);
+        let board_str = board.to_pretty_string_unicode();
+        assert!(board_str.contains("Tableau1: |🃎\nTableau2: 🂽|🂴\n"));
+    }
+
+    #[test]
+    fn test_to_pretty_string_colored() {
+        let board = Board::new_from_seed(283409412);
+        let board_str = board.to_pretty_string_colored();
+        assert!(board_str.contains(
+            "Tableau1: |\x1b[31mK♦\x1b[0m\nTableau2: \x1b[2mQ♥\x1b[0m|\x1b[31m4♥\x1b[0m\n"
+        ));
+        assert!(board_str.starts_with("Stock: \x1b[2m"));
+        let plain = board_str.replace("\x1b[2m", "").replace("\x1b[31m", "");
+        assert_eq!(plain.replace("\x1b[0m", ""), board.to_pretty_string());
+    }
+
+    #[test]
+    fn test_from_greenfelt_url() {
+        let expected = Board::new_from_seed(283409412).to_pretty_string();
+        for url in [
+            "283409412",
+            "https://greenfelt.net/klondike?game=283409412",
+            "https://greenfelt.net/klondike?lang=en&game=283409412#top",
+            "greenfelt.net/klondike/283409412/",
+        ] {
+            let board = Board::from_greenfelt_url(url).unwrap();
+            assert_eq!(board.to_pretty_string(), expected);
+        }
+        assert!(Board::from_greenfelt_url("https://greenfelt.net/klondike").is_err());
+        assert!(Board::from_greenfelt_url("https://greenfelt.net/klondike?game=abc").is_err());
+    }
+
     #[test]
     fn test_new_from_seed() {
         let board = Board::new_from_seed(283409412);
@@ -1,2 +1,7 @@
+mod aisleriot;
+
 pub mod action;
 pub mod board;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod talon;
@@ -0,0 +1,228 @@
+//! A simple rasterizer of boards, used to export a replay as an animated GIF.
+//!
+//! The layout follows the GUI: stock, waste and foundations on the top row, the
+//! foundations above the last four tableaus.
+use crate::{
+    board::{Board, Card, TOTAL_TABLEAUS},
+    talon::WasteFan,
+};
+
+use anyhow::{Context, Result};
+use std::{fs::File, io::BufWriter, path::Path, time::Duration};
+
+pub const CARD_WIDTH: usize = 90;
+pub const CARD_HEIGHT: usize = 130;
+pub const CARD_PADDING: usize = 10;
+pub const TABLEAU_CARD_V_OFFSET: usize = 25; // Vertical offset of cards in tableau pile
+pub const WASTE_CARD_H_OFFSET: usize = 20; // Horizontal offset of cards in waste pile
+
+const MAX_TABLEAU_CARDS: usize = 19; // Six face-down cards under a King-to-Ace run
+pub const FRAME_WIDTH: usize = TOTAL_TABLEAUS * (CARD_WIDTH + CARD_PADDING) + CARD_PADDING;
+pub const FRAME_HEIGHT: usize =
+    3 * CARD_PADDING + 2 * CARD_HEIGHT + (MAX_TABLEAU_CARDS - 1) * TABLEAU_CARD_V_OFFSET;
+
+const GLYPH_SCALE: usize = 4; // Pixels per dot of the rank and suit glyphs
+const GLYPH_MARGIN: usize = 4;
+
+// Palette indices, the colors of the classic green theme
+const TABLE: u8 = 0;
+const FACE: u8 = 1;
+const BACK: u8 = 2;
+const RED: u8 = 3;
+const BLACK: u8 = 4;
+const OUTLINE: u8 = 5;
+const PALETTE: [u8; 18] = [
+    0, 100, 50, // Table
+    248, 248, 248, // Face
+    0, 128, 128, // Back
+    255, 0, 0, // Red suits
+    0, 0, 0, // Black suits
+    100, 100, 100, // Outline
+];
+
+/// Rows of a 3x5 glyph, the lowest 3 bits of each row from left to right
+fn rank_glyph(c: char) -> [u8; 5] {
+    match c {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b111],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        _ => [0b111, 0b001, 0b011, 0b000, 0b010],
+    }
+}
+
+/// Rows of a 5x5 glyph of the suit, in the order ♦ ♣ ♥ ♠
+fn suit_glyph(suit: u8) -> [u8; 5] {
+    match suit {
+        0 => [0b00100, 0b01110, 0b11111, 0b01110, 0b00100],
+        1 => [0b01110, 0b01110, 0b11111, 0b11111, 0b00100],
+        2 => [0b01010, 0b11111, 0b11111, 0b01110, 0b00100],
+        _ => [0b00100, 0b01110, 0b11111, 0b00100, 0b01110],
+    }
+}
+
+/// An image of [`FRAME_WIDTH`] x [`FRAME_HEIGHT`] pixels, indexed into the palette
+struct Canvas {
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        Self {
+            pixels: vec![TABLE; FRAME_WIDTH * FRAME_HEIGHT],
+        }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: u8) {
+        for row in y..(y + height).min(FRAME_HEIGHT) {
+            let start = row * FRAME_WIDTH;
+            self.pixels[start + x.min(FRAME_WIDTH)..start + (x + width).min(FRAME_WIDTH)]
+                .fill(color);
+        }
+    }
+
+    fn outline(&mut self, x: usize, y: usize, width: usize, height: usize, color: u8) {
+        self.fill(x, y, width, 1, color);
+        self.fill(x, y + height - 1, width, 1, color);
+        self.fill(x, y, 1, height, color);
+        self.fill(x + width - 1, y, 1, height, color);
+    }
+
+    fn glyph(&mut self, x: usize, y: usize, rows: [u8; 5], width: usize, color: u8) {
+        for (dy, row) in rows.iter().enumerate() {
+            for dx in 0..width {
+                if row >> (width - 1 - dx) & 1 == 1 {
+                    self.fill(
+                        x + dx * GLYPH_SCALE,
+                        y + dy * GLYPH_SCALE,
+                        GLYPH_SCALE,
+                        GLYPH_SCALE,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+
+    fn card(&mut self, x: usize, y: usize, card: Card, face_up: bool) {
+        if !face_up || card.is_unknown() {
+            self.fill(x, y, CARD_WIDTH, CARD_HEIGHT, BACK);
+            self.outline(x, y, CARD_WIDTH, CARD_HEIGHT, OUTLINE);
+            return;
+        }
+        self.fill(x, y, CARD_WIDTH, CARD_HEIGHT, FACE);
+        self.outline(x, y, CARD_WIDTH, CARD_HEIGHT, OUTLINE);
+        let color = match card.suit() {
+            0 | 2 => RED,
+            _ => BLACK,
+        };
+        let rank = match card.rank() {
+            9 => "10".to_string(),
+            _ => card.to_string().chars().take(1).collect(),
+        };
+        let top = y + GLYPH_MARGIN;
+        for (i, c) in rank.chars().enumerate() {
+            let left = x + GLYPH_MARGIN + i * 4 * GLYPH_SCALE;
+            self.glyph(left, top, rank_glyph(c), 3, color);
+        }
+        let left = x + CARD_WIDTH - GLYPH_MARGIN - 5 * GLYPH_SCALE;
+        self.glyph(left, top, suit_glyph(card.suit()), 5, color);
+    }
+
+    fn empty_pile(&mut self, x: usize, y: usize) {
+        self.outline(x, y, CARD_WIDTH, CARD_HEIGHT, OUTLINE);
+    }
+}
+
+/// Left edge of the cards of the `column`th pile
+fn column_x(column: usize) -> usize {
+    CARD_PADDING + column * (CARD_WIDTH + CARD_PADDING)
+}
+
+/// Render the board to [`FRAME_WIDTH`] x [`FRAME_HEIGHT`] pixels, indexed into the palette,
+/// fanning out the waste cards of `fan` like the app
+pub fn render_board(board: &Board, fan: WasteFan) -> Vec<u8> {
+    let mut canvas = Canvas::new();
+    let top = CARD_PADDING;
+    match board.stock.last() {
+        Some(&card) => canvas.card(column_x(0), top, card, false),
+        None => canvas.empty_pile(column_x(0), top),
+    }
+    let fanned = fan.shown(board.draw_count(), board.waste.len());
+    let waste = &board.waste[board.waste.len() - fanned..];
+    for (i, &card) in waste.iter().enumerate() {
+        canvas.card(column_x(1) + i * WASTE_CARD_H_OFFSET, top, card, true);
+    }
+    for (i, foundation) in board.foundations.iter().enumerate() {
+        let x = column_x(TOTAL_TABLEAUS - board.foundations.len() + i);
+        match foundation {
+            Some(card) => canvas.card(x, top, *card, true),
+            None => canvas.empty_pile(x, top),
+        }
+    }
+    let top = 2 * CARD_PADDING + CARD_HEIGHT;
+    for (i, tableau) in board.tableaus.iter().enumerate() {
+        if tableau.is_empty() {
+            canvas.empty_pile(column_x(i), top);
+        }
+        let face_down = tableau.len() - tableau.face_up_count;
+        for (j, &card) in tableau.cards.iter().enumerate() {
+            let y = top + j * TABLEAU_CARD_V_OFFSET;
+            canvas.card(column_x(i), y, card, j >= face_down);
+        }
+    }
+    canvas.pixels
+}
+
+/// Write the boards, each with its fanned out waste cards, as the frames of a looping GIF,
+/// showing each one for `delay`
+pub fn write_gif(path: &Path, frames: &[(Board, WasteFan)], delay: Duration) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create '{}'", path.display()))?;
+    let (width, height) = (FRAME_WIDTH as u16, FRAME_HEIGHT as u16);
+    let mut encoder = gif::Encoder::new(BufWriter::new(file), width, height, &PALETTE)?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    let delay = (delay.as_millis() / 10).min(u16::MAX as u128) as u16;
+    for (board, fan) in frames {
+        let pixels = render_board(board, *fan);
+        let mut frame = gif::Frame::from_indexed_pixels(width, height, pixels, None);
+        frame.delay = delay;
+        encoder
+            .write_frame(&frame)
+            .with_context(|| format!("Failed to write '{}'", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_board() {
+        let board = Board::new_from_seed(283409412);
+        let pixels = render_board(&board, WasteFan::default());
+        assert_eq!(pixels.len(), FRAME_WIDTH * FRAME_HEIGHT);
+        let at = |x: usize, y: usize| pixels[y * FRAME_WIDTH + x];
+        let tableau_top = 2 * CARD_PADDING + CARD_HEIGHT;
+        // Face-down stock, the first tableau's face-up K♦ and the empty first foundation
+        assert_eq!(at(column_x(0) + 1, CARD_PADDING + 1), BACK);
+        assert_eq!(at(column_x(0) + CARD_WIDTH / 2, tableau_top + 60), FACE);
+        assert_eq!(at(column_x(3), CARD_PADDING + 1), OUTLINE);
+        assert_eq!(at(column_x(3) + 1, CARD_PADDING + 1), TABLE);
+        // The last tableau has six face-down cards under its face-up top card
+        let last = column_x(6) + CARD_WIDTH / 2;
+        assert_eq!(at(last, tableau_top + 5 * TABLEAU_CARD_V_OFFSET + 10), BACK);
+        assert_eq!(at(last, tableau_top + 6 * TABLEAU_CARD_V_OFFSET + 60), FACE);
+    }
+}
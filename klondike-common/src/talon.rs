@@ -0,0 +1,166 @@
+//! Which cards of the stock and waste can be played, and how many draws it takes.
+//!
+//! Mirrors the `TalonHelper` of the solver, which works on its own card and pile types.
+use crate::{
+    action::{Action, UndoRecord},
+    board::Card,
+};
+
+/// Each card of the talon that can reach the top of the waste, with the number of cards to draw
+/// to expose it.
+///
+/// The top of the waste comes first with 0. Cards that need the waste to be redealt
+/// have a negative count, the magnitude also counting the cards drawn before the redeal.
+/// `stock` and `waste` are ordered from the bottom card, like [`Board`](crate::board::Board).
+pub fn reachable_talon_cards(
+    stock: &[Card],
+    waste: &[Card],
+    draw_count: usize,
+) -> Vec<(Card, i32)> {
+    let draw_count = draw_count.max(1) as i32;
+    let mut cards = vec![];
+    let mut stock_used = vec![false; stock.len()];
+
+    // The top of the waste
+    if let Some(&card) = waste.last() {
+        cards.push((card, 0));
+    }
+
+    // Cards turned over from the stock
+    let stock_size = stock.len() as i32;
+    let mut position = stock_size - draw_count;
+    if position < 0 {
+        position = if stock_size > 0 { 0 } else { -1 };
+    }
+    let mut i = position;
+    while i >= 0 {
+        cards.push((stock[i as usize], stock_size - i));
+        stock_used[i as usize] = true;
+        i -= draw_count;
+    }
+
+    // Cards already in the waste, reached after a redeal
+    let mut amount_to_draw = stock_size + 1;
+    let waste_top = waste.len() as i32 - 1;
+    let mut position_waste = draw_count - 1;
+    while position_waste < waste_top {
+        cards.push((
+            waste[position_waste as usize],
+            -amount_to_draw - position_waste,
+        ));
+        position_waste += draw_count;
+    }
+
+    // Cards of the stock not turned over before, whose draw groups shift after a redeal
+    if position_waste > waste_top && waste_top >= 0 {
+        amount_to_draw += stock_size + waste_top;
+        let mut i = stock_size - position_waste + waste_top;
+        while i > 0 && !stock_used[i as usize] {
+            cards.push((stock[i as usize], i - amount_to_draw));
+            i -= draw_count;
+        }
+    }
+
+    cards
+}
+
+/// Cards of the last draw still on the waste, which are fanned out on top of it.
+///
+/// Follows the moves played from the start of the game. Before the first draw, a whole
+/// draw is fanned out, as the cards already on the waste may come from one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WasteFan {
+    left: Option<usize>, // None before the first draw
+}
+
+impl WasteFan {
+    /// `count` cards turned over from the stock
+    pub fn draw(&mut self, count: usize) {
+        self.left = Some(count);
+    }
+
+    /// The waste turned over into the stock
+    pub fn redeal(&mut self) {
+        self.left = Some(0);
+    }
+
+    /// The top card of the waste played
+    pub fn take(&mut self) {
+        if let Some(left) = &mut self.left {
+            *left = left.saturating_sub(1);
+        }
+    }
+
+    /// Follow an action applied with [`Board::apply`](crate::board::Board::apply)
+    pub fn follow(&mut self, record: &UndoRecord) {
+        match record.action {
+            Action::Draw | Action::Redeal if record.drawn == 0 => self.redeal(),
+            Action::Draw | Action::Redeal => self.draw(record.drawn),
+            Action::WasteToFoundation(_) | Action::WasteToTableau(_) => self.take(),
+            _ => {}
+        }
+    }
+
+    /// Cards of the last draw still on the waste, `None` before the first draw
+    pub fn left(&self) -> Option<usize> {
+        self.left
+    }
+
+    /// Number of waste cards fanned out, at least the top card
+    pub fn shown(&self, draw_count: usize, waste_len: usize) -> usize {
+        self.left
+            .map_or(draw_count, |left| left.max(1))
+            .min(waste_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards(s: &str) -> Vec<Card> {
+        s.split_whitespace().map(|c| c.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_reachable_talon_cards() {
+        // Draw 1: every stock card in turn, then the waste below its top after a redeal
+        let stock = cards("A♦ 2♦ 3♦");
+        let waste = cards("4♣ 5♣");
+        let reachable = reachable_talon_cards(&stock, &waste, 1);
+        let expected = cards("5♣ 3♦ 2♦ A♦ 4♣").into_iter().zip([0, 1, 2, 3, -4]);
+        assert_eq!(reachable, expected.collect::<Vec<_>>());
+
+        // Draw 3: every third card, and the stock cards that shift into reach after a redeal
+        let stock = cards("A♦ 2♦ 3♦ 4♦");
+        let waste = cards("A♣ 2♣ 3♣ 4♣ 5♣");
+        let reachable = reachable_talon_cards(&stock, &waste, 3);
+        let expected = cards("5♣ 2♦ 3♣ 4♦").into_iter().zip([0, 3, -7, -10]);
+        assert_eq!(reachable, expected.collect::<Vec<_>>());
+
+        // A short stock still turns over its bottom card
+        let reachable = reachable_talon_cards(&cards("A♦ 2♦"), &[], 3);
+        assert_eq!(
+            reachable,
+            cards("A♦").into_iter().zip([2]).collect::<Vec<_>>()
+        );
+        assert!(reachable_talon_cards(&[], &[], 3).is_empty());
+    }
+
+    #[test]
+    fn test_waste_fan() {
+        let mut fan = WasteFan::default();
+        assert_eq!((fan.left(), fan.shown(3, 5)), (None, 3));
+        fan.draw(3);
+        fan.take();
+        assert_eq!((fan.left(), fan.shown(3, 5)), (Some(2), 2));
+        fan.take();
+        fan.take();
+        assert_eq!((fan.left(), fan.shown(3, 5)), (Some(0), 1));
+        assert_eq!(fan.shown(3, 0), 0);
+        fan.draw(2);
+        assert_eq!(fan.shown(3, 1), 1);
+        fan.redeal();
+        assert_eq!(fan.left(), Some(0));
+    }
+}
@@ -0,0 +1,39 @@
+//! Deals of known seeds, guarding the LCG and the dealing math of `Board::new_from_seed`
+//! against regressions.
+//!
+//! Each fixture is a `Seed: N` line followed by the pretty string of the deal, the
+//! fixtures separated by blank lines. `fixtures/dealer_snapshots.txt` holds deals this
+//! crate produced, which only catch changes of the dealer, not a wrong one.
+use klondike_common::board::Board;
+
+const DEALER_SNAPSHOTS: &str = include_str!("fixtures/dealer_snapshots.txt");
+
+fn fixtures(content: &'static str) -> Vec<(u32, &'static str)> {
+    content
+        .split("\n\n")
+        .map(|fixture| {
+            let (seed, board) = fixture
+                .trim()
+                .split_once('\n')
+                .expect("a seed line followed by a board");
+            let seed = seed
+                .strip_prefix("Seed: ")
+                .and_then(|seed| seed.parse().ok())
+                .expect("a `Seed: N` line");
+            (seed, board)
+        })
+        .collect()
+}
+
+fn check_deals(fixtures: Vec<(u32, &str)>) {
+    for (seed, expected) in fixtures {
+        let board = Board::new_from_seed(seed);
+        assert!(board.is_valid(), "seed {seed} deals an invalid board");
+        assert_eq!(board.to_pretty_string(), expected, "seed {seed}");
+    }
+}
+
+#[test]
+fn test_dealer_snapshots() {
+    check_deals(fixtures(DEALER_SNAPSHOTS));
+}
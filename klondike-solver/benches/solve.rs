@@ -0,0 +1,41 @@
+//! Solves fixed boards of increasing difficulty, reporting the time and the states
+//! explored per second. Run with `cargo bench -p klondike-solver`.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use klondike_common::board::Board;
+use klondike_solver::Solver;
+
+const MAX_STATES: u32 = 3_000_000;
+
+// Greenfelt deals, from about 200 thousand to 2 million states for the fewest moves
+const FIXTURES: [(&str, &str); 3] = [
+    ("easy-draw1", include_str!("fixtures/easy-draw1.txt")),
+    ("medium-draw3", include_str!("fixtures/medium-draw3.txt")),
+    ("hard-draw3", include_str!("fixtures/hard-draw3.txt")),
+];
+
+fn bench_solve(c: &mut Criterion) {
+    let mut group = c.benchmark_group("solve");
+    group.sample_size(10);
+    // The solver keeps its buffers between solves, so the search is measured, not their allocation
+    let mut solver = Solver::new();
+    for (name, fixture) in FIXTURES {
+        let board = Board::parse_validated(fixture).unwrap();
+        for (mode, minimal) in [("fast", false), ("minimal", true)] {
+            // A first solve counts the states, for the throughput
+            solver.set_board(board.clone());
+            let states = solver.solve(MAX_STATES, minimal).unwrap().states;
+            group.throughput(Throughput::Elements(states as u64));
+            group.bench_with_input(BenchmarkId::new(mode, name), &board, |b, board| {
+                b.iter(|| {
+                    solver.set_board(board.clone());
+                    solver.solve(MAX_STATES, minimal).unwrap()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_solve);
+criterion_main!(benches);
@@ -0,0 +1,296 @@
+use crate::{SolveError, SolveResult, Solver, solve};
+
+use klondike_common::board::Board;
+
+use std::{
+    collections::BTreeMap,
+    panic,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
+
+/// Outcome of solving many completions of a partially known board
+#[derive(Debug, Clone, Default)]
+pub struct WinAnalysis {
+    pub samples: usize,
+    pub wins: usize,
+    pub move_counts: Vec<usize>, // Minimal move count of each winnable sample, sorted
+}
+
+impl WinAnalysis {
+    /// Fraction of the samples that could be won
+    pub fn win_rate(&self) -> f32 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.wins as f32 / self.samples as f32
+    }
+}
+
+/// Limits of each deal's solve in [`solve_seeds`] and [`winnability_stats`], besides `max_states`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SeedLimits {
+    pub timeout: Option<Duration>, // Stop solving a deal after this duration
+    pub max_redeals: Option<usize>, // Limit the times the waste is recycled into the stock
+}
+
+impl SeedLimits {
+    /// Set the limits on the solver, kept across its boards
+    pub fn apply(&self, solver: &mut Solver) {
+        if let Some(timeout) = self.timeout {
+            solver.set_timeout(timeout);
+        }
+        if let Some(max_redeals) = self.max_redeals {
+            solver.set_max_rounds(max_redeals.saturating_add(1));
+        }
+    }
+}
+
+/// Winnability of a range of greenfelt deals, see [`winnability_stats`]
+#[derive(Debug, Clone, Default)]
+pub struct WinStats {
+    pub solved: usize,
+    pub unsolvable: usize,                   // Deals proven unwinnable
+    pub undecided: usize,                    // Deals that hit a limit without a solution
+    pub proven_minimal: usize,               // Solved deals whose move count is proven minimal
+    pub move_counts: BTreeMap<usize, usize>, // Solved deals by the move count of their solution
+}
+
+impl WinStats {
+    pub fn total(&self) -> usize {
+        self.solved + self.unsolvable + self.undecided
+    }
+}
+
+/// Solves the greenfelt deals of the seeds for the fewest moves and counts the outcomes.
+///
+/// A deal that hits `max_states` or a limit is undecided rather than unsolvable, and a solution
+/// found before hitting it counts as solved, though its move count may not be minimal.
+pub fn winnability_stats(
+    seeds: impl IntoIterator<Item = u32>,
+    draw_count: usize,
+    max_states: u32,
+    limits: SeedLimits,
+) -> WinStats {
+    let seeds: Vec<u32> = seeds.into_iter().collect();
+    let mut stats = WinStats::default();
+    for (_, result) in solve_seeds_with(&seeds, draw_count, max_states, limits, true) {
+        match result {
+            Ok(result) => {
+                stats.solved += 1;
+                if result.minimal() {
+                    stats.proven_minimal += 1;
+                }
+                let moves = result.card_moves() + result.draw_count();
+                *stats.move_counts.entry(moves).or_default() += 1;
+            }
+            Err(SolveError::Unsolvable) => stats.unsolvable += 1,
+            Err(_) => stats.undecided += 1,
+        }
+    }
+    stats
+}
+
+/// Estimates how likely a partially known board is to be won.
+///
+/// See [`analyze_hidden`] for how the estimate is made and its caveats.
+pub fn win_probability(board: &Board, samples: usize, max_states: u32) -> Result<f32, SolveError> {
+    analyze_hidden(board, samples, max_states).map(|analysis| analysis.win_rate())
+}
+
+/// Solves `samples` random completions of the unknown cards of the board, in parallel.
+///
+/// Caveats when reading the result:
+/// - It is a Monte Carlo estimate, its standard error is about `sqrt(p * (1 - p) / samples)`.
+/// - Every arrangement of the unknown cards is assumed equally likely, which holds for a
+///   fair shuffle but ignores anything learned from how the game was played so far.
+/// - A sample the solver cannot finish within `max_states` counts as a loss, so the win
+///   rate is a lower bound when `max_states` is too small.
+/// - It assumes the player knows the hidden cards, so the actual odds of a player who
+///   has to guess are lower.
+///
+/// Fails with [`SolveError::InvalidBoard`] unless the unknown cards can be filled with the
+/// missing ones, see [`Board::validate_hidden`].
+pub fn analyze_hidden(
+    board: &Board,
+    samples: usize,
+    max_states: u32,
+) -> Result<WinAnalysis, SolveError> {
+    board
+        .validate_hidden()
+        .map_err(|_| SolveError::InvalidBoard)?;
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(samples.max(1));
+    let results: Vec<Option<usize>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                scope.spawn(move || {
+                    (t..samples)
+                        .step_by(threads)
+                        .map(|i| {
+                            let sample = board.sample_hidden(i as u32 + 1).ok()?;
+                            solve(sample, max_states, true)
+                                .ok()
+                                .map(|result| result.card_moves() + result.draw_count())
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|err| panic::resume_unwind(err))
+            })
+            .collect()
+    });
+
+    let mut move_counts: Vec<usize> = results.into_iter().flatten().collect();
+    move_counts.sort_unstable();
+    Ok(WinAnalysis {
+        samples,
+        wins: move_counts.len(),
+        move_counts,
+    })
+}
+
+/// Solves the greenfelt deals of the given seeds in parallel, stopping at the first solution of each.
+///
+/// Each worker thread reuses one [`Solver`] across its seeds, the results keep the order of `seeds`.
+pub fn solve_seeds(
+    seeds: &[u32],
+    draw_count: usize,
+    max_states: u32,
+    limits: SeedLimits,
+) -> Vec<(u32, Result<SolveResult, SolveError>)> {
+    solve_seeds_with(seeds, draw_count, max_states, limits, false)
+}
+
+fn solve_seeds_with(
+    seeds: &[u32],
+    draw_count: usize,
+    max_states: u32,
+    limits: SeedLimits,
+    minimal: bool,
+) -> Vec<(u32, Result<SolveResult, SolveError>)> {
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(seeds.len().max(1));
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, Result<SolveResult, SolveError>)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut solver = Solver::new();
+                    limits.apply(&mut solver);
+                    let mut results = vec![];
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(&seed) = seeds.get(idx) else {
+                            break;
+                        };
+                        let mut board = Board::new_from_seed(seed);
+                        board.set_draw_count(draw_count);
+                        solver.set_board(board);
+                        results.push((idx, solver.solve(max_states, minimal)));
+                    }
+                    results
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|err| panic::resume_unwind(err))
+            })
+            .collect()
+    });
+    results.sort_by_key(|(idx, _)| *idx);
+    results
+        .into_iter()
+        .map(|(idx, result)| (seeds[idx], result))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_win_probability() {
+        const BOARD_STR: &str = r#"Foundation1: 9♦
+Foundation2: 9♣
+Foundation3: 9♥
+Foundation4: 8♠
+Tableau1: ????|K♠Q♥J♣
+Tableau2: |K♥Q♣J♦T♠
+Tableau3: |K♣Q♦J♠T♥
+Tableau4: |K♦Q♠J♥T♣
+DrawCount: 1"#;
+
+        let board = Board::parse(BOARD_STR).unwrap();
+        assert!(!board.is_valid());
+        let sample = board.sample_hidden(1).unwrap();
+        assert!(sample.is_valid());
+        assert_eq!(
+            sample.to_pretty_string(),
+            board.sample_hidden(1).unwrap().to_pretty_string()
+        );
+
+        let analysis = analyze_hidden(&board, 8, 10_000).unwrap();
+        assert_eq!(analysis.samples, 8);
+        assert_eq!(analysis.wins, 8);
+        assert_eq!(analysis.move_counts.len(), 8);
+        assert_eq!(win_probability(&board, 4, 10_000), Ok(1.0));
+
+        // One unknown card too many, or a card found twice
+        let extra = BOARD_STR.replace("????", "??????");
+        let board = Board::parse(&extra).unwrap();
+        assert!(board.sample_hidden(1).is_err());
+        assert_eq!(
+            analyze_hidden(&board, 8, 10_000).err(),
+            Some(SolveError::InvalidBoard)
+        );
+        let twice = BOARD_STR.replace("????", "??K♠");
+        let board = Board::parse(&twice).unwrap();
+        assert!(board.validate_hidden().is_err());
+    }
+
+    #[test]
+    fn test_solve_seeds() {
+        let results = solve_seeds(&[4, 283409412], 3, 200_000, SeedLimits::default());
+        let seeds: Vec<_> = results.iter().map(|(seed, _)| *seed).collect();
+        assert_eq!(seeds, [4, 283409412]);
+        assert!(results[0].1.is_err());
+        assert!(results[1].1.is_ok());
+
+        let limits = SeedLimits {
+            timeout: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let results = solve_seeds(&[283409412], 3, 200_000, limits);
+        assert!(matches!(results[0].1, Err(SolveError::TimedOut { .. })));
+        let limits = SeedLimits {
+            max_redeals: Some(usize::MAX),
+            ..Default::default()
+        };
+        assert!(solve_seeds(&[283409412], 3, 200_000, limits)[0].1.is_ok());
+    }
+
+    #[test]
+    fn test_winnability_stats() {
+        let stats = winnability_stats([4, 283409412], 3, 200_000, SeedLimits::default());
+        assert_eq!(stats.total(), 2);
+        assert_eq!(stats.solved, 1);
+        assert_eq!(stats.unsolvable, 1);
+        assert_eq!(stats.move_counts.values().sum::<usize>(), 1);
+    }
+}
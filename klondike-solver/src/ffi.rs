@@ -0,0 +1,85 @@
+//! C ABI of the solver, built with the `ffi` feature, see `tests/ffi.c` for its use from C.
+
+use crate::solve;
+use klondike_common::{action::format_actions, board::Board};
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_char},
+    ptr,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Solves the board, given in the text format of `Board::parse`, returning its moves in the
+/// notation of `format_actions`, to release with [`klondike_free`]. The length of the moves,
+/// without the trailing NUL, is written to `out_len` unless it is null.
+///
+/// Returns null when the board can't be parsed or solved, see [`klondike_last_error`].
+///
+/// # Safety
+///
+/// `board_str` must be a NUL-terminated string, and `out_len` null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn klondike_solve(
+    board_str: *const c_char,
+    max_states: u32,
+    minimal: bool,
+    out_len: *mut usize,
+) -> *mut c_char {
+    if board_str.is_null() {
+        set_last_error("Board is null".to_string());
+        return ptr::null_mut();
+    }
+    // SAFETY: the caller passes a NUL-terminated string
+    let board_str = unsafe { CStr::from_ptr(board_str) };
+    match try_solve(board_str, max_states, minimal) {
+        Ok(moves) => {
+            if !out_len.is_null() {
+                // SAFETY: the caller passes a pointer valid for writes
+                unsafe { *out_len = moves.as_bytes().len() };
+            }
+            moves.into_raw()
+        }
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Releases a string returned by [`klondike_solve`], doing nothing for null.
+///
+/// # Safety
+///
+/// `s` must come from [`klondike_solve`] and not be released already.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn klondike_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: the string was allocated by `CString::into_raw` in `klondike_solve`
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// The error of the last failed [`klondike_solve`] on this thread, null before any.
+/// The string is owned by the library and valid until the next failure on this thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn klondike_last_error() -> *const c_char {
+    LAST_ERROR.with_borrow(|err| err.as_ref().map_or(ptr::null(), |err| err.as_ptr()))
+}
+
+fn try_solve(board_str: &CStr, max_states: u32, minimal: bool) -> Result<CString, String> {
+    let board_str = board_str
+        .to_str()
+        .map_err(|_| "Board is not UTF-8".to_string())?;
+    let board = Board::parse_validated(board_str).map_err(|err| err.to_string())?;
+    let result = solve(board, max_states, minimal).map_err(|err| err.to_string())?;
+    // The notation has no NUL
+    Ok(CString::new(format_actions(&result.actions)).unwrap_or_default())
+}
+
+fn set_last_error(err: String) {
+    let err = CString::new(err).unwrap_or_default();
+    LAST_ERROR.with_borrow_mut(|last| *last = Some(err));
+}
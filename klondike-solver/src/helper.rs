@@ -15,20 +15,37 @@ impl Estimate {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct StateMap {
     capacity: usize,
+    len: usize, // Number of occupied buckets
     buckets: Vec<Bucket>,
 }
 
 impl StateMap {
-    pub fn with_capacity(capacity: usize) -> Self {
-        let empty_bucket = Bucket {
-            key: u64::MAX,
-            value: Estimate::default(),
-        };
-        let buckets = vec![empty_bucket; capacity];
-        Self { capacity, buckets }
+    const EMPTY_BUCKET: Bucket = Bucket {
+        key: u64::MAX,
+        value: Estimate {
+            current: 0,
+            remaining: 0,
+        },
+    };
+
+    /// Empty the map and set its capacity, keeping the allocation of the buckets
+    pub fn reset(&mut self, capacity: usize) {
+        self.buckets.truncate(capacity);
+        self.buckets.fill(Self::EMPTY_BUCKET);
+        self.buckets.resize(capacity, Self::EMPTY_BUCKET);
+        self.capacity = capacity;
+        self.len = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
     }
 
     pub fn get(&self, key: u64) -> Option<(&Estimate, usize)> {
@@ -46,7 +63,8 @@ impl StateMap {
         None
     }
 
-    pub fn insert(&mut self, key: u64, value: Estimate) {
+    /// Insert the state, returns false without inserting when every bucket is taken
+    pub fn insert(&mut self, key: u64, value: Estimate) -> bool {
         let mut index = (key as usize) % self.capacity;
         for _ in 0..self.capacity {
             let bucket = &mut self.buckets[index];
@@ -54,11 +72,12 @@ impl StateMap {
                 unsafe {
                     std::ptr::write(bucket, Bucket { key, value });
                 }
-                return;
+                self.len += 1;
+                return true;
             }
             index = (index + 1) % self.capacity;
         }
-        panic!("StateMap full");
+        false
     }
 
     pub fn estimate_mut(&mut self, index: usize) -> &mut Estimate {
@@ -1,15 +1,32 @@
 //! This crate provides utilities for solving Solitaire games using the A* search algorithm.
 //!
 /// Migrated from the https://github.com/ShootMe/MinimalKlondike/blob/8983a1375aa15c5ca7f8c3df054aef37218f85c8/Entities/Board.cs
+#[cfg(not(target_arch = "wasm32"))]
+mod batch;
 mod card;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 mod helper;
 mod move_;
 mod pile;
 mod solver;
+#[cfg(feature = "wasm")]
+mod wasm;
 
 use crate::card::*;
 use crate::helper::*;
 use crate::move_::*;
 use crate::pile::*;
 
-pub use crate::solver::{SolveResult, Solver, solve};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::batch::{
+    SeedLimits, WinAnalysis, WinStats, analyze_hidden, solve_seeds, win_probability,
+    winnability_stats,
+};
+pub use crate::solver::{
+    BestEffort, GreedySolveResult, HeuristicWeights, Optimality, SolveError, SolveProgress,
+    SolveResult, Solver, SolverConfig, UnknownsResult, solve, solve_best_effort, solve_cancellable,
+    solve_greedy_prefix, solve_streaming, solve_with_config, solve_with_progress,
+};
+#[cfg(feature = "wasm")]
+pub use crate::wasm::solve_json;
@@ -2,7 +2,8 @@ use super::*;
 
 use std::cmp::Ordering;
 
-#[derive(Debug, Copy, Clone, Default)]
+/// A move between two piles, equal to another only when its count and flip match too
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
 pub struct Move {
     pub value1: u8,
     pub value2: u8,
@@ -52,17 +53,9 @@ impl Move {
     }
 }
 
-impl PartialEq for Move {
-    fn eq(&self, other: &Self) -> bool {
-        self.value1 == other.value1
-    }
-}
-
-impl Eq for Move {}
-
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct MoveIndex {
-    pub index: u32,
+    pub index: u32, // Index of the node, increasing with each insertion
     pub priority: i16,
     pub estimate: Estimate,
 }
@@ -77,9 +70,14 @@ impl MoveIndex {
     }
 }
 
+/// Lower priorities pop first from the `BinaryHeap`, ties pop in insertion (FIFO) order,
+/// so a search always explores the states in the same order.
 impl Ord for MoveIndex {
     fn cmp(&self, other: &Self) -> Ordering {
-        other.priority.cmp(&self.priority)
+        other
+            .priority
+            .cmp(&self.priority)
+            .then(other.index.cmp(&self.index))
     }
 }
 
@@ -119,3 +117,17 @@ impl MoveNode {
         index
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_eq() {
+        let mov = Move::new(7, 9, 2, true);
+        assert_eq!(mov, Move::new(7, 9, 2, true));
+        assert_ne!(mov, Move::new(7, 9, 3, true));
+        assert_ne!(mov, Move::new(7, 9, 2, false));
+        assert_ne!(mov, Move::new(9, 7, 2, true));
+    }
+}
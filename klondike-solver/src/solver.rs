@@ -1,18 +1,29 @@
 use super::*;
 
-use klondike_common::action::Action;
+use klondike_common::action::{
+    Action, action_steps, apply_action, describe_action, safe_foundation_moves,
+};
 use klondike_common::board::{Board, Card, MAX_CARD, MAX_SUIT, TOTAL_FOUNDATIONS, TOTAL_TABLEAUS};
 
 use ahash::AHasher;
-use anyhow::{Result, bail};
 use smallvec::SmallVec;
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
 use std::{
     collections::BinaryHeap,
+    fmt,
     hash::Hasher,
-    time::{Duration, Instant},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
 };
+#[cfg(feature = "wasm")]
+use web_time::Instant; // `std::time::Instant::now` panics on wasm32-unknown-unknown
 
-const MAX_ROUNDS: usize = 15;
+const MAX_ROUNDS: usize = 15; // Default passes through the stock
+const PROGRESS_INTERVAL: u32 = 10_000; // Explored nodes between progress reports
 const MAX_MOVES: usize = 255;
 const PILE_STOCK: usize = 0;
 const PILE_WASTE: usize = 1;
@@ -23,13 +34,87 @@ const PILE_TABLEAU_END: usize = PILE_TABLEAU_START + TOTAL_TABLEAUS - 1;
 const PILE_SIZE: usize = TOTAL_FOUNDATIONS + TOTAL_TABLEAUS + 2;
 
 type PossibleMoves = SmallVec<[Move; 64]>;
+type Result<T> = std::result::Result<T, SolveError>;
 
 pub fn solve(board: Board, max_states: u32, minimal: bool) -> Result<SolveResult> {
+    solve_with_progress(board, max_states, minimal, |_| {})
+}
+
+/// Solves the game, reporting the progress of the search through `progress`.
+pub fn solve_with_progress(
+    board: Board,
+    max_states: u32,
+    minimal: bool,
+    progress: impl FnMut(SolveProgress),
+) -> Result<SolveResult> {
+    let mut solver = Solver::new();
+    solver.set_board(board);
+    solver.solve_with_progress(max_states, minimal, progress)
+}
+
+/// Solves the game, calling `on_solution` with the actions and move count of every
+/// solution that improves on the previous ones.
+///
+/// Without `minimal` the search stops at the first solution, so it's reported once.
+pub fn solve_streaming(
+    board: Board,
+    max_states: u32,
+    minimal: bool,
+    on_solution: impl FnMut(&[Action], u8),
+) -> Result<SolveResult> {
+    let mut solver = Solver::new();
+    solver.set_board(board);
+    solver.solve_streaming(max_states, minimal, on_solution)
+}
+
+/// Solves the game until it finishes or `cancel` is set.
+///
+/// A cancelled solve fails with [`SolveError::Cancelled`].
+pub fn solve_cancellable(
+    board: Board,
+    max_states: u32,
+    minimal: bool,
+    cancel: Arc<AtomicBool>,
+) -> Result<SolveResult> {
     let mut solver = Solver::new();
     solver.set_board(board);
+    solver.set_cancel_token(cancel);
     solver.solve(max_states, minimal)
 }
 
+/// Solves the game with a tuned search, see [`SolverConfig`].
+pub fn solve_with_config(
+    board: Board,
+    max_states: u32,
+    minimal: bool,
+    config: SolverConfig,
+) -> Result<SolveResult> {
+    let mut solver = Solver::new();
+    solver.set_board(board);
+    solver.set_config(config);
+    solver.solve(max_states, minimal)
+}
+
+/// Solves the game, or returns the moves reaching the highest foundation score.
+pub fn solve_best_effort(board: Board, max_states: u32, minimal: bool) -> Result<BestEffort> {
+    let mut solver = Solver::new();
+    solver.set_board(board);
+    solver.solve_best_effort(max_states, minimal)
+}
+
+/// Plays the safe foundation moves greedily, then solves the remaining position.
+///
+/// The opening reads naturally for physical play, but the total may not be globally minimal.
+pub fn solve_greedy_prefix(
+    board: Board,
+    max_states: u32,
+    minimal: bool,
+) -> Result<GreedySolveResult> {
+    let mut solver = Solver::new();
+    solver.set_board(board);
+    solver.solve_greedy_prefix(max_states, minimal)
+}
+
 /// A struct representing the solver for the Solitaire game.
 #[derive(Debug, Clone)]
 pub struct Solver {
@@ -45,6 +130,12 @@ pub struct Solver {
     last_move: Move,
     moves_total: usize,
     round_count: usize,
+    max_rounds: usize,
+    cancel: Option<Arc<AtomicBool>>,
+    timeout: Option<Duration>,
+    max_moves: Option<u8>,
+    config: SolverConfig,
+    buffers: SearchBuffers,
 }
 
 impl Default for Solver {
@@ -68,6 +159,12 @@ impl Solver {
             last_move: Default::default(),
             moves_total: 0,
             round_count: 1,
+            max_rounds: MAX_ROUNDS,
+            cancel: None,
+            timeout: None,
+            max_moves: None,
+            config: SolverConfig::default(),
+            buffers: SearchBuffers::default(),
         }
     }
 
@@ -75,13 +172,256 @@ impl Solver {
         self.initial_board.draw_count()
     }
 
+    /// Stop solving as soon as the token is set
+    pub fn set_cancel_token(&mut self, cancel: Arc<AtomicBool>) {
+        self.cancel = Some(cancel);
+    }
+
+    /// Limit the passes through the stock, 1 means the stock is never recycled
+    pub fn set_max_rounds(&mut self, rounds: usize) {
+        self.max_rounds = rounds.max(1);
+    }
+
+    /// Stop solving once the wall-clock time exceeds the timeout
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    /// Only look for solutions of at most this many moves, redeals not counted.
+    ///
+    /// A search finding none fails with [`SolveError::ExceedsMaxMoves`]. The greedy prefix of
+    /// [`Solver::solve_greedy_prefix`] counts against the limit too.
+    pub fn set_max_moves(&mut self, moves: u8) {
+        self.max_moves = Some(moves);
+    }
+
+    pub fn set_config(&mut self, config: SolverConfig) {
+        self.config = config;
+    }
+
     pub fn solve(&mut self, max_nodes: u32, minimal: bool) -> Result<SolveResult> {
+        self.solve_with_progress(max_nodes, minimal, |_| {})
+    }
+
+    /// Plays the safe foundation moves greedily, then solves the remaining position.
+    ///
+    /// See [`solve_greedy_prefix`].
+    pub fn solve_greedy_prefix(
+        &mut self,
+        max_nodes: u32,
+        minimal: bool,
+    ) -> Result<GreedySolveResult> {
+        let mut board = self.initial_board.clone();
+        let mut prefix = vec![];
+        loop {
+            let actions = safe_foundation_moves(&board);
+            if actions.is_empty() {
+                break;
+            }
+            for action in actions {
+                apply_action(&mut board, &action);
+                prefix.push(action);
+            }
+        }
+        self.set_board(board);
+
+        // The prefix counts against the move limit, the tail gets what is left of it
+        let max_moves = self.max_moves;
+        let exceeded = |max_moves| SolveError::ExceedsMaxMoves { max_moves };
+        if let Some(limit) = max_moves {
+            let left = limit.checked_sub(prefix.len() as u8);
+            self.max_moves = Some(left.ok_or(exceeded(limit))?);
+        }
+        let tail = self.solve(max_nodes, minimal);
+        self.max_moves = max_moves;
+        let tail = tail.map_err(|err| match (err, max_moves) {
+            (SolveError::ExceedsMaxMoves { .. }, Some(limit)) => exceeded(limit),
+            (err, _) => err,
+        })?;
+        Ok(GreedySolveResult { prefix, tail })
+    }
+
+    /// Solves the game, calling `progress` every few explored nodes.
+    pub fn solve_with_progress(
+        &mut self,
+        max_nodes: u32,
+        minimal: bool,
+        mut progress: impl FnMut(SolveProgress),
+    ) -> Result<SolveResult> {
+        self.solve_reporting(max_nodes, minimal, &mut progress, &mut |_, _| {})
+    }
+
+    /// Solves the game, calling `on_solution` with the actions and move count of every
+    /// improving solution found.
+    pub fn solve_streaming(
+        &mut self,
+        max_nodes: u32,
+        minimal: bool,
+        mut on_solution: impl FnMut(&[Action], u8),
+    ) -> Result<SolveResult> {
+        self.solve_reporting(max_nodes, minimal, &mut |_| {}, &mut on_solution)
+    }
+
+    fn solve_reporting(
+        &mut self,
+        max_nodes: u32,
+        minimal: bool,
+        progress: &mut dyn FnMut(SolveProgress),
+        on_solution: &mut dyn FnMut(&[Action], u8),
+    ) -> Result<SolveResult> {
+        let timer = Instant::now();
+        let search = self.search(max_nodes, minimal, false, progress, on_solution)?;
+        // A fast solve stops at its first solution, leaving most of the space unexplored
+        let explored_all =
+            minimal && search.states < max_nodes && !search.timed_out && !search.saturated;
+        Ok(SolveResult {
+            optimality: match minimal && explored_all && self.config.allow_foundation_to_tableau {
+                true => Optimality::Proven,
+                false => Optimality::BestFound { explored_all },
+            },
+            states: search.states as i32,
+            elapsed: timer.elapsed(),
+            actions: self.export_actions(),
+            open_peak: search.open_peak,
+            closed_occupancy: search.closed_occupancy,
+            closed_capacity: search.closed_capacity,
+        })
+    }
+
+    /// Solves the game, falling back to the moves reaching the highest foundation score
+    /// when it can't be won within the limits.
+    ///
+    /// Only invalid boards and cancelled solves fail.
+    pub fn solve_best_effort(&mut self, max_nodes: u32, minimal: bool) -> Result<BestEffort> {
+        let timer = Instant::now();
+        let error = match self.search(max_nodes, minimal, false, &mut |_| {}, &mut |_, _| {}) {
+            Ok(_) => None,
+            Err(err @ (SolveError::InvalidBoard | SolveError::Cancelled { .. })) => {
+                return Err(err);
+            }
+            Err(err) => Some(err),
+        };
+        Ok(BestEffort {
+            actions: self.export_actions(),
+            foundation_score: self.foundation_score,
+            error,
+            elapsed: timer.elapsed(),
+        })
+    }
+
+    /// Checks whether the game can be won, stopping at the first solution found
+    /// without reconstructing its moves.
+    pub fn is_solvable(&mut self, max_nodes: u32) -> Result<bool> {
+        match self.search(max_nodes, false, true, &mut |_| {}, &mut |_, _| {}) {
+            Ok(_) => Ok(true),
+            Err(SolveError::Unsolvable) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Checks the board with every plausible completion of its unknown cards.
+    ///
+    /// All arrangements of the missing cards are tried when there are at most
+    /// `max_completions` of them, otherwise `max_completions` random ones are.
+    /// Each completion is searched up to `max_nodes` states; the board set on
+    /// the solver is restored afterwards.
+    pub fn solve_with_unknowns(
+        &mut self,
+        max_completions: usize,
+        max_nodes: u32,
+    ) -> Result<UnknownsResult> {
+        let board = self.initial_board.clone();
+        board
+            .validate_hidden()
+            .map_err(|_| SolveError::InvalidBoard)?;
+        let missing = board.missing_cards();
+
+        let arrangements = (1..=missing.len()).try_fold(1usize, |acc, n| acc.checked_mul(n));
+        let exhaustive = arrangements.is_some_and(|count| count <= max_completions);
+        let completions: Box<dyn Iterator<Item = Board>> = if exhaustive {
+            let mut order: Vec<usize> = (0..missing.len()).collect();
+            let mut done = false;
+            Box::new(std::iter::from_fn(move || {
+                if done {
+                    return None;
+                }
+                let completion = board.fill_unknown(order.iter().map(|&i| missing[i]));
+                done = !next_permutation(&mut order);
+                Some(completion)
+            }))
+        } else {
+            Box::new(
+                (0..max_completions).map_while(move |i| board.sample_hidden(i as u32 + 1).ok()),
+            )
+        };
+
+        let mut result = UnknownsResult {
+            exhaustive,
+            ..Default::default()
+        };
+        let initial_board = self.initial_board.clone();
+        for completion in completions {
+            self.set_board(completion);
+            result.completions += 1;
+            match self.is_solvable(max_nodes) {
+                Ok(true) => result.solvable += 1,
+                Ok(false) => {}
+                Err(SolveError::MaxStatesReached { .. }) => result.undecided += 1,
+                Err(err) => {
+                    self.set_board(initial_board);
+                    return Err(err);
+                }
+            }
+        }
+        self.set_board(initial_board);
+        Ok(result)
+    }
+
+    /// Runs the A* search, leaving the piles at the best state found unless `solvable_only` is set.
+    fn search(
+        &mut self,
+        max_nodes: u32,
+        minimal: bool,
+        solvable_only: bool,
+        progress: &mut dyn FnMut(SolveProgress),
+        on_solution: &mut dyn FnMut(&[Action], u8),
+    ) -> Result<SearchStats> {
         if !self.initial_board.is_valid() {
-            bail!("Invalid initial board state.");
+            return Err(SolveError::InvalidBoard);
+        }
+        self.reset();
+        // A column blocked for good is lost without exploring up to the state limit
+        if self.initial_board.is_obviously_unwinnable() {
+            return Err(SolveError::Unsolvable);
         }
-        let mut open = BinaryHeap::with_capacity((max_nodes as usize) / 10);
-        let mut closed = StateMap::with_capacity(max_nodes as usize + 1);
-        let mut node_storage: Vec<MoveNode> = vec![MoveNode::default(); max_nodes as usize + 1];
+        let mut buffers = std::mem::take(&mut self.buffers);
+        buffers.prepare(max_nodes, self.config.closed_capacity(max_nodes));
+        let result = self.search_in(
+            &mut buffers,
+            max_nodes,
+            minimal,
+            solvable_only,
+            progress,
+            on_solution,
+        );
+        self.buffers = buffers;
+        result
+    }
+
+    fn search_in(
+        &mut self,
+        buffers: &mut SearchBuffers,
+        max_nodes: u32,
+        minimal: bool,
+        solvable_only: bool,
+        progress: &mut dyn FnMut(SolveProgress),
+        on_solution: &mut dyn FnMut(&[Action], u8),
+    ) -> Result<SearchStats> {
+        let SearchBuffers {
+            open,
+            closed,
+            node_storage,
+        } = buffers;
 
         let mut node_count = 1;
         let mut max_foundation_score = 0;
@@ -90,19 +430,52 @@ impl Solver {
 
         let estimate = Estimate {
             current: 0,
-            remaining: self.minimum_moves_remaining(false),
+            remaining: self.minimum_moves_remaining(self.round_count == self.max_rounds),
         };
-        closed.insert(self.get_state(), estimate);
+        if !closed.insert(self.get_state(), estimate) {
+            return Err(SolveError::StateMapFull {
+                max_states: max_nodes,
+            });
+        }
         open.push(MoveIndex::new(node_count - 1, 0, estimate));
 
-        let mut best_solution_move_count = MAX_MOVES as u8;
+        let mut best_solution_move_count = self
+            .max_moves
+            .map_or(MAX_MOVES as u8, |moves| moves.saturating_add(1));
         let mut solution_node_index = None;
         let timer = Instant::now();
+        let mut explored: u32 = 0;
+        let mut timed_out = false;
+        let mut saturated = false;
+        let mut open_peak = open.len();
 
         while let Some(node) = open.pop() {
             if node_count >= max_nodes {
                 break;
             }
+            if let Some(timeout) = self.timeout
+                && timer.elapsed() >= timeout
+            {
+                timed_out = true;
+                break;
+            }
+            if let Some(cancel) = &self.cancel
+                && cancel.load(Ordering::Relaxed)
+            {
+                return Err(SolveError::Cancelled {
+                    states: node_count,
+                    best_score: max_foundation_score,
+                });
+            }
+
+            explored += 1;
+            if explored.is_multiple_of(PROGRESS_INTERVAL) {
+                progress(SolveProgress {
+                    states: node_count,
+                    best_score: max_foundation_score,
+                    elapsed: timer.elapsed(),
+                });
+            }
 
             let estimate = node.estimate;
             if estimate.total() >= best_solution_move_count {
@@ -110,7 +483,7 @@ impl Solver {
             }
 
             let moves_to_make =
-                node_storage[node.index as usize].copy(&mut moves_storage, &node_storage);
+                node_storage[node.index as usize].copy(&mut moves_storage, node_storage);
             self.reset();
             for i in (0..moves_to_make).rev() {
                 self.make_move(moves_storage[i]);
@@ -126,10 +499,11 @@ impl Solver {
                 let new_current = estimate.current.saturating_add(additional_moves);
                 let new_estimate = Estimate {
                     current: new_current,
-                    remaining: self.minimum_moves_remaining(self.round_count == MAX_ROUNDS),
+                    remaining: self.minimum_moves_remaining(self.round_count == self.max_rounds),
                 };
 
-                if new_estimate.total() < best_solution_move_count && self.round_count <= MAX_ROUNDS
+                if new_estimate.total() < best_solution_move_count
+                    && self.round_count <= self.max_rounds
                 {
                     let mut skip = false;
 
@@ -143,7 +517,10 @@ impl Solver {
                             }
                         }
                         None => {
-                            closed.insert(key, new_estimate);
+                            if !closed.insert(key, new_estimate) {
+                                saturated = true;
+                                break;
+                            }
                         }
                     }
                     if !skip {
@@ -153,23 +530,37 @@ impl Solver {
                         };
 
                         let solved = self.foundation_score == MAX_CARD;
+                        if solved && solvable_only {
+                            return Ok(SearchStats {
+                                states: node_count + 1,
+                                timed_out: false,
+                                saturated: false,
+                                open_peak,
+                                closed_occupancy: closed.len(),
+                                closed_capacity: closed.capacity(),
+                            });
+                        }
                         if self.foundation_score > max_foundation_score || solved {
                             solution_node_index = Some(node_count);
                             max_foundation_score = self.foundation_score;
                         }
                         if solved {
                             best_solution_move_count = new_estimate.total();
+                            on_solution(&self.export_actions(), best_solution_move_count);
                             node_count += 1;
                             if !minimal {
                                 open.clear();
                                 break;
                             }
                         } else {
-                            let heuristic = ((new_estimate.total() as i16) << 1)
-                                + additional_moves as i16
-                                + (MAX_CARD - self.foundation_score) as i16
-                                + ((self.round_count as i16) << 1);
+                            let heuristic = self.config.heuristic.heuristic(
+                                new_estimate.total(),
+                                additional_moves,
+                                self.foundation_score,
+                                self.round_count,
+                            );
                             open.push(MoveIndex::new(node_count, heuristic, new_estimate));
+                            open_peak = open_peak.max(open.len());
                             node_count += 1;
                             if node_count >= max_nodes {
                                 break;
@@ -180,30 +571,54 @@ impl Solver {
 
                 self.undo_move();
             }
+            if saturated {
+                break;
+            }
         }
 
         if let Some(node_index) = solution_node_index {
             let moves_to_make =
-                node_storage[node_index as usize].copy(&mut moves_storage, &node_storage);
+                node_storage[node_index as usize].copy(&mut moves_storage, node_storage);
             self.reset();
             for i in (0..moves_to_make).rev() {
                 self.make_move(moves_storage[i]);
             }
+        } else {
+            self.reset();
         }
 
         if max_foundation_score != MAX_CARD {
-            if node_count < max_nodes {
-                bail!("No solution found.");
+            if let Some(timeout) = self.timeout
+                && timed_out
+            {
+                return Err(SolveError::TimedOut {
+                    timeout,
+                    states: node_count,
+                });
+            } else if saturated {
+                return Err(SolveError::StateMapFull {
+                    max_states: max_nodes,
+                });
+            } else if let Some(max_moves) = self.max_moves
+                && node_count < max_nodes
+            {
+                return Err(SolveError::ExceedsMaxMoves { max_moves });
+            } else if node_count < max_nodes {
+                return Err(SolveError::Unsolvable);
             } else {
-                bail!("Unable to solve the game; reached max states {max_nodes}.");
+                return Err(SolveError::MaxStatesReached {
+                    max_states: max_nodes,
+                });
             }
         }
 
-        Ok(SolveResult {
-            minimal: minimal && node_count < max_nodes,
-            states: node_count as i32,
-            elapsed: timer.elapsed(),
-            actions: self.export_actions(),
+        Ok(SearchStats {
+            states: node_count,
+            timed_out,
+            saturated,
+            open_peak,
+            closed_occupancy: closed.len(),
+            closed_capacity: closed.capacity(),
         })
     }
 
@@ -216,6 +631,9 @@ impl Solver {
         let mut num: usize = stock_size + stock_size.div_ceil(draw_count) + waste_size;
         let mut mins = [u8::MAX; 4];
 
+        // Only when the waste will be turned over card by card, in its order, does a waste card
+        // above a lower one of its suit need a move to set it aside. With any larger draw count
+        // the draw groups shift after a redeal, unless no redeal is left.
         if draw_count == 1 || is_last_round {
             for i in 0..waste_size {
                 let card = waste_pile.get(i);
@@ -302,18 +720,19 @@ impl Solver {
 
     fn calculate_additional_moves(&self, mov: Move) -> u8 {
         let mut count = 1;
-        let mov_count = mov.count() as u8;
+        let mov_count = mov.count();
         if mov.from() == PILE_WASTE as u8 && mov_count != 0 {
-            let draw_count = self.draw_count() as u8;
+            // In usize, as any draw count is accepted and would truncate to 0 in a u8
+            let draw_count = self.draw_count();
             if !mov.flip() {
                 count += mov_count.div_ceil(draw_count);
             } else {
-                let stock_size = self.piles[PILE_STOCK].size as u8;
+                let stock_size = self.piles[PILE_STOCK].size;
                 count += stock_size.div_ceil(draw_count);
                 count += (mov_count - stock_size).div_ceil(draw_count);
             }
         }
-        count
+        count as u8
     }
 
     fn compute_possible_moves(&mut self, possible_moves: &mut PossibleMoves) {
@@ -332,7 +751,9 @@ impl Solver {
         if self.compute_move_from_waste(possible_moves) {
             return;
         }
-        self.compute_move_from_foundation(possible_moves);
+        if self.config.allow_foundation_to_tableau {
+            self.compute_move_from_foundation(possible_moves);
+        }
     }
 
     fn compute_with_last_move(&mut self, possible_moves: &mut PossibleMoves) -> bool {
@@ -370,6 +791,8 @@ impl Solver {
             }
         }
 
+        let first_empty_tableau = self.first_empty_tableau();
+
         for &src_idx in &non_empty_tableaus {
             let src_pile = &self.piles[src_idx as usize];
             let src_pile_size = src_pile.size;
@@ -394,7 +817,8 @@ impl Solver {
             let src_first_face_up_card = src_pile.peek_first_face_up_unchecked();
             let src_face_up_count =
                 src_first_face_up_card.rank as i32 - src_top_card.rank as i32 + 1;
-            let mut king_moved = !src_first_face_up_card.is_king();
+            let king_movable =
+                src_first_face_up_card.is_king() && (src_pile_size as i32) != src_face_up_count;
 
             for dest_idx in PILE_TABLEAU_START..=PILE_TABLEAU_END {
                 if src_idx == dest_idx as u8 {
@@ -402,14 +826,13 @@ impl Solver {
                 }
                 let dest_pile = &self.piles[dest_idx];
                 if dest_pile.size == 0 {
-                    if !king_moved && (src_pile_size as i32) != src_face_up_count {
+                    if king_movable && first_empty_tableau == Some(dest_idx) {
                         possible_moves.push(Move::new(
                             src_idx,
                             dest_idx as u8,
                             src_face_up_count as u8,
                             true,
                         ));
-                        king_moved = true;
                     }
                     continue;
                 }
@@ -477,9 +900,21 @@ impl Solver {
                     break;
                 }
             }
+            if talon_card.is_king() {
+                if let Some(tableau_idx) = self.first_empty_tableau() {
+                    possible_moves.push(Move::new(
+                        PILE_WASTE as u8,
+                        tableau_idx as u8,
+                        cards_to_draw as u8,
+                        flip,
+                    ));
+                }
+                continue;
+            }
             for tableau_idx in PILE_TABLEAU_START..=PILE_TABLEAU_END {
                 let tableau_top_card = self.piles[tableau_idx].peek_top();
-                if tableau_top_card.rank as i32 - talon_card.rank as i32 == 1
+                if self.piles[tableau_idx].size > 0
+                    && tableau_top_card.rank as i32 - talon_card.rank as i32 == 1
                     && talon_card.is_red != tableau_top_card.is_red
                 {
                     possible_moves.push(Move::new(
@@ -488,9 +923,6 @@ impl Solver {
                         cards_to_draw as u8,
                         flip,
                     ));
-                    if talon_card.is_king() {
-                        break;
-                    }
                 }
             }
         }
@@ -504,9 +936,21 @@ impl Solver {
                 continue;
             }
             let foundation_card = foundation_pile.peek_top_unchecked();
+            if foundation_card.is_king() {
+                if let Some(tableau_idx) = self.first_empty_tableau() {
+                    possible_moves.push(Move::new(
+                        foundation_idx as u8,
+                        tableau_idx as u8,
+                        1,
+                        false,
+                    ));
+                }
+                continue;
+            }
             for tableau_idx in PILE_TABLEAU_START..=PILE_TABLEAU_END {
                 let tableau_top_card = &self.piles[tableau_idx].peek_top();
-                if tableau_top_card.rank as i32 - foundation_card.rank as i32 == 1
+                if self.piles[tableau_idx].size > 0
+                    && tableau_top_card.rank as i32 - foundation_card.rank as i32 == 1
                     && tableau_top_card.is_red != foundation_card.is_red
                 {
                     possible_moves.push(Move::new(
@@ -515,15 +959,17 @@ impl Solver {
                         1,
                         false,
                     ));
-                    if foundation_card.is_king() {
-                        break;
-                    }
                 }
             }
         }
         false
     }
 
+    /// Empty tableaus are interchangeable, so a King only ever moves to the first one
+    fn first_empty_tableau(&self) -> Option<usize> {
+        (PILE_TABLEAU_START..=PILE_TABLEAU_END).find(|&idx| self.piles[idx].size == 0)
+    }
+
     fn can_move_to_foundation(&self, card: CardExt) -> Option<u8> {
         let idx = if card.is_unknown() {
             return None;
@@ -630,7 +1076,12 @@ impl Solver {
     }
 
     fn export_actions(&self) -> Vec<Action> {
-        let mut actions = vec![];
+        self.export_move_actions().concat()
+    }
+
+    /// The actions each move of the last solve expands to
+    fn export_move_actions(&self) -> Vec<Vec<Action>> {
+        let mut move_actions = vec![];
         let mut stock_size = self.initial_piles[PILE_STOCK].size;
         let mut waste_size = self.initial_piles[PILE_WASTE].size;
         let draw_count = self.draw_count();
@@ -639,6 +1090,7 @@ impl Solver {
         for i in 0..self.moves_total {
             let mov = self.moves[i];
             let (move_from, move_to, move_count, move_flip) = mov.values();
+            let mut actions = vec![];
             if move_from == PILE_WASTE {
                 if !move_flip {
                     for _ in 0..move_count.div_ceil(draw_count) {
@@ -701,8 +1153,48 @@ impl Solver {
                     board.move_foundation_to_tableau(from_index, to_index);
                 }
             }
+            move_actions.push(actions);
         }
-        actions
+        move_actions
+    }
+
+    /// The moves of the last solve for debugging the search: each move in the solver's
+    /// terms, see [`Solver::export_moves`], then the actions it expands to and the board after them.
+    pub fn explain_solution(&self) -> String {
+        let mut output = String::new();
+        let mut board = self.initial_board.clone();
+        let moves = self.moves[..self.moves_total].iter();
+        for (i, (mov, actions)) in moves.zip(self.export_move_actions()).enumerate() {
+            let (from, to, count, flip) = mov.values();
+            let flip = if flip { ", flip" } else { "" };
+            output.push_str(&format!(
+                "{:>3}. {} -> {}, count {count}{flip}\n",
+                i + 1,
+                pile_name(from),
+                pile_name(to)
+            ));
+            for action in &actions {
+                output.push_str(&format!("     {}\n", describe_action(&board, action)));
+                apply_action(&mut board, action);
+            }
+            for line in board.to_compact_string().lines() {
+                output.push_str(&format!("     {line}\n"));
+            }
+        }
+        output
+    }
+
+    /// The moves of the last solve as `(from, to, count, flip)`, in the solver's own terms.
+    ///
+    /// Piles are numbered 0 for the stock, 1 the waste, 2-5 the foundations and 6-12 the
+    /// tableaus. A move from the waste first draws `count` cards, `flip` meaning the draws
+    /// go through a redeal; a move from a tableau moves `count` cards, `flip` meaning it
+    /// turns up a face-down card.
+    pub fn export_moves(&self) -> Vec<(usize, usize, usize, bool)> {
+        self.moves[..self.moves_total]
+            .iter()
+            .map(Move::values)
+            .collect()
     }
 
     pub fn set_board(&mut self, board: Board) {
@@ -830,16 +1322,390 @@ impl Solver {
 
 #[derive(Debug, Clone)]
 pub struct SolveResult {
-    pub minimal: bool,
+    pub optimality: Optimality,
     pub states: i32,
     pub elapsed: Duration,
     pub actions: Vec<Action>,
+    pub open_peak: usize,        // Most nodes waiting in the open list at once
+    pub closed_occupancy: usize, // States stored in the closed state map
+    pub closed_capacity: usize,  // Buckets of the closed state map, see `SolverConfig`
+}
+
+impl SolveResult {
+    /// Whether the solution is proven to have the fewest moves
+    pub fn minimal(&self) -> bool {
+        self.optimality == Optimality::Proven
+    }
+
+    /// Actions moving a card, i.e. neither a draw nor a redeal
+    pub fn card_moves(&self) -> usize {
+        self.actions.len() - self.draw_count() - self.redeal_count()
+    }
+
+    /// Draws from the stock in the solution
+    pub fn draw_count(&self) -> usize {
+        self.actions.iter().filter(|a| **a == Action::Draw).count()
+    }
+
+    /// Times the waste is turned over into the stock in the solution
+    pub fn redeal_count(&self) -> usize {
+        self.actions.iter().filter(|a| a.is_redeal()).count()
+    }
+
+    /// The board after each step of the solution, grouped like [`format_actions`] so
+    /// consecutive draws make a single step.
+    ///
+    /// [`format_actions`]: klondike_common::action::format_actions
+    pub fn board_states(&self, initial: &Board) -> Vec<Board> {
+        let mut board = initial.clone();
+        action_steps(&self.actions)
+            .into_iter()
+            .map(|step| {
+                for action in step {
+                    apply_action(&mut board, action);
+                }
+                board.clone()
+            })
+            .collect()
+    }
+
+    /// The result as JSON, with `elapsed` in milliseconds and the actions in the compact notation.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        let json = SolveResultJson {
+            elapsed_ms: self.elapsed.as_millis() as u64,
+            states: self.states,
+            minimal: self.minimal(),
+            actions: klondike_common::action::action_tokens(&self.actions),
+        };
+        serde_json::to_string(&json)
+    }
+}
+
+/// Solvability of a board with unknown cards, see [`Solver::solve_with_unknowns`]
+#[derive(Debug, Clone, Default)]
+pub struct UnknownsResult {
+    pub completions: usize, // Completions of the unknown cards that were tried
+    pub solvable: usize,    // Completions that can be won
+    pub undecided: usize,   // Completions that reached the state limit
+    pub exhaustive: bool,   // Whether every possible completion was tried
+}
+
+impl UnknownsResult {
+    /// Whether the game can be won whatever the unknown cards are
+    pub fn is_guaranteed(&self) -> bool {
+        self.exhaustive && self.solvable == self.completions
+    }
+
+    /// Whether the game can be won for at least one arrangement of the unknown cards
+    pub fn is_possible(&self) -> bool {
+        self.solvable > 0
+    }
+}
+
+/// JSON view of a [`SolveResult`]
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct SolveResultJson {
+    elapsed_ms: u64,
+    states: i32,
+    minimal: bool,
+    actions: Vec<String>, // Steps in the notation of `format_actions`, e.g. `3D` or `T3:T5@2`
+}
+
+/// How far a solution is known to be from the fewest moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Optimality {
+    /// The search space below the solution's move count was exhausted
+    Proven,
+    /// A shorter solution may exist; `explored_all` is false when the search
+    /// stopped at the state or time limit, or at the first solution, rather than on its own
+    BestFound { explored_all: bool },
+}
+
+/// Why a solve ended without a solution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveError {
+    InvalidBoard,
+    Unsolvable,
+    MaxStatesReached { max_states: u32 },
+    Cancelled { states: u32, best_score: u8 },
+    TimedOut { timeout: Duration, states: u32 },
+    StateMapFull { max_states: u32 },
+    ExceedsMaxMoves { max_moves: u8 },
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::InvalidBoard => write!(f, "Invalid initial board state."),
+            SolveError::Unsolvable => write!(f, "No solution found."),
+            SolveError::MaxStatesReached { max_states } => {
+                write!(
+                    f,
+                    "Unable to solve the game; reached max states {max_states}."
+                )
+            }
+            SolveError::Cancelled { states, best_score } => write!(
+                f,
+                "Solving cancelled after {states} states; best foundation score {best_score}."
+            ),
+            SolveError::TimedOut { timeout, states } => write!(
+                f,
+                "Unable to solve the game; timed out after {}s with {states} states.",
+                timeout.as_secs_f64()
+            ),
+            SolveError::StateMapFull { max_states } => write!(
+                f,
+                "Unable to solve the game; the state map is saturated, increase max states {max_states}."
+            ),
+            SolveError::ExceedsMaxMoves { max_moves } => {
+                write!(f, "No solution of at most {max_moves} moves.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+/// Storage of the search, kept on the solver so repeated solves don't reallocate it
+#[derive(Debug, Clone, Default)]
+struct SearchBuffers {
+    open: BinaryHeap<MoveIndex>,
+    closed: StateMap,
+    node_storage: Vec<MoveNode>,
+}
+
+impl SearchBuffers {
+    fn prepare(&mut self, max_nodes: u32, closed_capacity: usize) {
+        let size = max_nodes as usize + 1;
+        self.open.clear();
+        self.open.reserve(size / 10);
+        self.closed.reset(closed_capacity);
+        self.node_storage.truncate(size);
+        self.node_storage.resize(size, MoveNode::default());
+        self.node_storage[0] = MoveNode::default();
+    }
+}
+
+struct SearchStats {
+    states: u32,
+    timed_out: bool,
+    saturated: bool, // Whether the search stopped because the closed state map was full
+    open_peak: usize,
+    closed_occupancy: usize,
+    closed_capacity: usize,
+}
+
+/// Tuning of the search, trading memory for speed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolverConfig {
+    /// Buckets of the closed state map per state of `max_states`, the default 1.0 sizes it
+    /// to `max_states + 1`. More headroom keeps the linear probing chains short; below 1.0
+    /// the map may fill up, which ends the search with [`SolveError::StateMapFull`].
+    pub closed_capacity_factor: f32,
+    /// Weights of the priority that orders the open list
+    pub heuristic: HeuristicWeights,
+    /// Whether cards may be pulled back from the foundations onto the tableaus. Forbidding
+    /// it usually explores far fewer states, but misses the solutions that need such a
+    /// move: a solution is then never proven minimal, and an unsolvable deal may be winnable.
+    pub allow_foundation_to_tableau: bool,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            closed_capacity_factor: 1.0,
+            heuristic: HeuristicWeights::default(),
+            allow_foundation_to_tableau: true,
+        }
+    }
+}
+
+impl SolverConfig {
+    fn closed_capacity(&self, max_nodes: u32) -> usize {
+        let size = max_nodes as usize + 1;
+        ((size as f64 * self.closed_capacity_factor as f64).ceil() as usize).max(1)
+    }
+}
+
+/// Weights of the terms of the search priority, lower priorities are explored first.
+///
+/// The defaults favour short solutions; raising `remaining_cards` against `estimate`
+/// heads for the foundations sooner, which usually finds a solution faster at the
+/// cost of exploring longer ones first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeuristicWeights {
+    pub estimate: i16,         // Moves made plus the minimum moves still needed
+    pub additional_moves: i16, // Moves taken by the last move, e.g. the draws before it
+    pub remaining_cards: i16,  // Cards not yet on the foundations
+    pub round: i16,            // Passes made through the stock
+}
+
+impl Default for HeuristicWeights {
+    fn default() -> Self {
+        Self {
+            estimate: 2,
+            additional_moves: 1,
+            remaining_cards: 1,
+            round: 2,
+        }
+    }
+}
+
+impl HeuristicWeights {
+    pub fn heuristic(
+        &self,
+        estimate: u8,
+        additional_moves: u8,
+        foundation_score: u8,
+        round_count: usize,
+    ) -> i16 {
+        // Summed in i64 and clamped, as large weights overflow an i16
+        let term = |weight: i16, value: i64| i64::from(weight) * value;
+        let heuristic = term(self.estimate, estimate.into())
+            + term(self.additional_moves, additional_moves.into())
+            + term(
+                self.remaining_cards,
+                MAX_CARD.saturating_sub(foundation_score).into(),
+            )
+            + term(self.round, round_count.min(u32::MAX as usize) as i64);
+        heuristic.clamp(i16::MIN.into(), i16::MAX.into()) as i16
+    }
+}
+
+/// The furthest a solve got, see [`Solver::solve_best_effort`]
+#[derive(Debug, Clone)]
+pub struct BestEffort {
+    pub actions: Vec<Action>, // Moves reaching `foundation_score`, a full solution when solved
+    pub foundation_score: u8, // Most cards moved to the foundations
+    pub error: Option<SolveError>, // Why the game wasn't won, none when solved
+    pub elapsed: Duration,
+}
+
+impl BestEffort {
+    pub fn solved(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// A snapshot of a running search
+#[derive(Debug, Clone, Copy)]
+pub struct SolveProgress {
+    pub states: u32,    // Number of states generated so far
+    pub best_score: u8, // Most cards moved to the foundations in any state so far
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct GreedySolveResult {
+    pub prefix: Vec<Action>, // Safe foundation moves played before solving
+    pub tail: SolveResult,   // Solution of the position after the prefix
+}
+
+impl GreedySolveResult {
+    pub fn actions(&self) -> Vec<Action> {
+        self.prefix
+            .iter()
+            .chain(&self.tail.actions)
+            .copied()
+            .collect()
+    }
+}
+
+/// Name of a pile numbered as in [`Solver::export_moves`]
+fn pile_name(pile: usize) -> String {
+    match pile {
+        PILE_STOCK => "Stock".to_string(),
+        PILE_WASTE => "Waste".to_string(),
+        PILE_FOUNDATION_START..=PILE_FOUNDATION_END => {
+            format!("Foundation{}", pile - PILE_FOUNDATION_START + 1)
+        }
+        _ => format!("Tableau{}", pile - PILE_TABLEAU_START + 1),
+    }
+}
+
+/// Rearranges `order` into the next permutation in lexicographic order, false after the last one
+fn next_permutation(order: &mut [usize]) -> bool {
+    let Some(i) = order.windows(2).rposition(|w| w[0] < w[1]) else {
+        return false;
+    };
+    let j = order.iter().rposition(|&x| x > order[i]).unwrap_or(i);
+    order.swap(i, j);
+    order[i + 1..].reverse();
+    true
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// The draw-3 deal of seed 283409412, which most tests solve
+    fn seed_draw3_board() -> Board {
+        let mut board = Board::new_from_seed(283409412);
+        board.set_draw_count(3);
+        board
+    }
+
+    /// [`seed_draw3_board`] and a solution of it, not proven minimal
+    fn seed_draw3() -> (Board, SolveResult) {
+        let board = seed_draw3_board();
+        let result = solve(board.clone(), 200_000, false).unwrap();
+        (board, result)
+    }
+
+    #[test]
+    fn test_solve_draw_2() {
+        let mut board = Board::new_from_seed(283409412);
+        board.set_draw_count(2);
+        let result = solve(board.clone(), 1_000_000, false).unwrap();
+        for action in &result.actions {
+            board.try_apply(action).unwrap();
+        }
+        assert_eq!(board.foundation_score(), 52);
+    }
+
+    #[test]
+    fn test_talon_helper_matches_common() {
+        use klondike_common::talon::reachable_talon_cards;
+
+        for draw_count in [1, 3] {
+            let mut board = Board::new_from_seed(283409412);
+            board.set_draw_count(draw_count);
+            // Every split of the talon between the stock and the waste
+            for _ in 0..=klondike_common::board::TALON_SIZE {
+                let mut solver = Solver::new();
+                solver.set_board(board.clone());
+                let count = solver.helper.calculate(
+                    draw_count,
+                    &solver.piles[PILE_WASTE],
+                    &solver.piles[PILE_STOCK],
+                );
+                let helper: Vec<_> = (0..count)
+                    .map(|i| {
+                        (
+                            solver.helper.stock_waste[i].id,
+                            solver.helper.cards_drawn[i],
+                        )
+                    })
+                    .collect();
+                let common: Vec<_> = reachable_talon_cards(&board.stock, &board.waste, draw_count)
+                    .into_iter()
+                    .map(|(card, drawn)| (card.id(), drawn))
+                    .collect();
+                assert_eq!(
+                    helper,
+                    common,
+                    "draw {draw_count}, waste {}",
+                    board.waste.len()
+                );
+                if let Some(card) = board.stock.pop() {
+                    board.waste.push(card);
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_solve() {
         const BOARD_STR: &str = r#"Stock: 5♣3♣6♦Q♦A♠5♦K♠4♥5♥4♣7♠Q♣J♣6♠2♥2♣3♠9♥K♦7♦7♥J♠A♦8♣
@@ -855,9 +1721,525 @@ DrawCount: 1
 
         let board = Board::parse(BOARD_STR).unwrap();
         let result = solve(board, 200_000, true).unwrap();
-        assert_eq!(result.states, 166066);
+        assert_eq!(result.optimality, Optimality::Proven);
+        assert_eq!(result.states, 164266);
         assert_eq!(result.actions.len(), 114);
+        assert_eq!(result.closed_capacity, 200_001);
+        assert!(result.closed_occupancy > 0 && result.closed_occupancy <= 164266);
+        assert!(result.open_peak > 0 && result.open_peak <= 164266);
         let encoded_actions = klondike_common::action::format_actions(&result.actions);
         println!("{encoded_actions}");
     }
+
+    #[test]
+    fn test_legal_actions_parity() {
+        for draw_count in 1..=3 {
+            let mut board = Board::new_from_seed(283409412);
+            board.set_draw_count(draw_count);
+            let result = solve(board.clone(), 200_000, false).unwrap();
+            for action in &result.actions {
+                assert!(
+                    board.legal_actions().contains(action),
+                    "draw {draw_count}: {action:?}"
+                );
+                board.apply(action);
+            }
+        }
+    }
+
+    #[test]
+    fn test_kings_move_to_first_empty_tableau() {
+        const BOARD_STR: &str = r#"Waste: K♣
+Foundation1: K♦
+Foundation2: 4♣
+Foundation3: 4♥
+Foundation4: 4♠
+Tableau1: 6♠|K♠Q♥
+Tableau2:
+Tableau3: 7♠|9♣
+Tableau4:
+Tableau5: 8♠|8♥
+Tableau6:
+Tableau7: 9♠|J♠
+DrawCount: 1
+"#;
+        let mut solver = Solver::new();
+        solver.set_board(Board::parse(BOARD_STR).unwrap());
+        solver.reset();
+        let mut possible_moves = PossibleMoves::new();
+        solver.compute_possible_moves(&mut possible_moves);
+        let to_empty: Vec<_> = possible_moves
+            .iter()
+            .map(|mov| mov.values())
+            .filter(|&(_, to, _, _)| {
+                (PILE_TABLEAU_START..=PILE_TABLEAU_END).contains(&to) && solver.piles[to].size == 0
+            })
+            .map(|(from, to, _, _)| (from, to))
+            .collect();
+        let first_empty = PILE_TABLEAU_START + 1;
+        assert_eq!(
+            to_empty,
+            [
+                (PILE_TABLEAU_START, first_empty),
+                (PILE_WASTE, first_empty),
+                (PILE_FOUNDATION_START, first_empty),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_explain_solution() {
+        let mut solver = Solver::new();
+        solver.set_board(seed_draw3_board());
+        let result = solver.solve(200_000, false).unwrap();
+        let explanation = solver.explain_solution();
+        let lines: Vec<_> = explanation.lines().collect();
+        let described = lines
+            .iter()
+            .filter(|line| line.starts_with("     "))
+            .filter(|line| !line.contains("Stock: ") && !line.contains("Tableaus: "))
+            .count();
+        assert_eq!(described, result.actions.len());
+        assert!(lines[0].starts_with("  1. "));
+        let foundations = lines[lines.len() - 2];
+        assert_eq!(foundations.matches('K').count(), 4, "{foundations}");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_json() {
+        let result = SolveResult {
+            optimality: Optimality::Proven,
+            states: 42,
+            elapsed: Duration::from_millis(1500),
+            actions: vec![
+                Action::Draw,
+                Action::Draw,
+                Action::TableauToTableau(2, 4, 2),
+            ],
+            open_peak: 0,
+            closed_occupancy: 0,
+            closed_capacity: 0,
+        };
+        assert_eq!(
+            result.to_json().unwrap(),
+            r#"{"elapsed_ms":1500,"states":42,"minimal":true,"actions":["2D","T3:T5@2"]}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&result.actions[2]).unwrap(),
+            r#"{"type":"TableauToTableau","from":2,"to":4,"count":2}"#
+        );
+        let action: Action = serde_json::from_str(r#"{"type":"Draw"}"#).unwrap();
+        assert_eq!(action, Action::Draw);
+    }
+
+    #[test]
+    fn test_solve_cancellable() {
+        let board = Board::new_from_seed(283409412);
+        let cancel = Arc::new(AtomicBool::new(true));
+        let err = solve_cancellable(board.clone(), 200_000, true, cancel.clone()).unwrap_err();
+        assert_eq!(
+            err,
+            SolveError::Cancelled {
+                states: 1,
+                best_score: 0
+            }
+        );
+
+        cancel.store(false, Ordering::Relaxed);
+        assert!(solve_cancellable(seed_draw3_board(), 200_000, false, cancel).is_ok());
+    }
+
+    #[test]
+    fn test_board_states() {
+        let (board, result) = seed_draw3();
+        let states = result.board_states(&board);
+        let encoded_actions = klondike_common::action::format_actions(&result.actions);
+        assert_eq!(states.len(), encoded_actions.split_whitespace().count());
+        assert_eq!(states.last().unwrap().foundation_score(), 52);
+    }
+
+    #[test]
+    fn test_is_solvable() {
+        let mut solver = Solver::new();
+        solver.set_board(seed_draw3_board());
+        assert!(solver.is_solvable(200_000).unwrap());
+
+        let mut board = Board::new_from_seed(4);
+        board.set_draw_count(3);
+        solver.set_board(board);
+        assert!(!solver.is_solvable(200_000).unwrap());
+    }
+
+    #[test]
+    fn test_solve_best_effort() {
+        let mut board = Board::new_from_seed(4);
+        board.set_draw_count(3);
+        let result = solve_best_effort(board.clone(), 200_000, false).unwrap();
+        assert!(!result.solved());
+        assert_eq!(result.error, Some(SolveError::Unsolvable));
+        assert!(result.foundation_score > 0 && result.foundation_score < 52);
+        let mut replay = board.clone();
+        for action in &result.actions {
+            replay.try_apply(action).unwrap();
+        }
+        assert_eq!(replay.foundation_score(), result.foundation_score);
+
+        let result = solve_best_effort(seed_draw3_board(), 200_000, false).unwrap();
+        assert!(result.solved());
+        assert_eq!(result.foundation_score, 52);
+    }
+
+    #[test]
+    fn test_solve_with_unknowns() {
+        const BOARD_STR: &str = r#"Foundation1: 9♦
+Foundation2: 9♣
+Foundation3: 9♥
+Foundation4: 8♠
+Tableau1: ????|K♠Q♥J♣
+Tableau2: |K♥Q♣J♦T♠
+Tableau3: |K♣Q♦J♠T♥
+Tableau4: |K♦Q♠J♥T♣
+DrawCount: 1"#;
+
+        let board = Board::parse(BOARD_STR).unwrap();
+        let mut solver = Solver::new();
+        solver.set_board(board.clone());
+        let result = solver.solve_with_unknowns(100, 10_000).unwrap();
+        assert!(result.exhaustive);
+        assert_eq!(result.completions, 2);
+        assert!(result.is_guaranteed());
+
+        let result = solver.solve_with_unknowns(1, 10_000).unwrap();
+        assert!(!result.exhaustive);
+        assert_eq!(result.completions, 1);
+        assert!(!result.is_guaranteed());
+        assert!(result.is_possible());
+        assert_eq!(
+            solver.get_board().to_pretty_string(),
+            board.to_pretty_string()
+        );
+
+        let mut order = vec![0, 1, 2];
+        let mut count = 1;
+        while next_permutation(&mut order) {
+            count += 1;
+        }
+        assert_eq!(count, 6);
+        assert_eq!(order, [2, 1, 0]);
+    }
+
+    #[test]
+    fn test_state_map_full() {
+        let mut map = StateMap::default();
+        map.reset(2);
+        assert!(map.insert(1, Estimate::default()));
+        assert!(map.insert(3, Estimate::default()));
+        assert!(!map.insert(5, Estimate::default()));
+        assert_eq!(map.len(), 2);
+        assert!(map.get(5).is_none());
+    }
+
+    #[test]
+    fn test_solver_config() {
+        let (board, default) = seed_draw3();
+        let config = SolverConfig {
+            closed_capacity_factor: 1.5,
+            ..Default::default()
+        };
+        let result = solve_with_config(board, 200_000, false, config).unwrap();
+        assert_eq!(result.closed_capacity, 300_002);
+        assert_eq!(result.states, default.states);
+        assert_eq!(result.actions, default.actions);
+
+        // Total probe distance of the same keys at 0.9 and 0.6 load
+        let probes = |load: f64| {
+            let states = 9_000;
+            let mut map = StateMap::default();
+            map.reset((states as f64 / load) as usize);
+            let keys: Vec<u64> = (0..states as u64)
+                .map(|i| (i + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 1)
+                .collect();
+            for &key in &keys {
+                assert!(map.insert(key, Estimate::default()));
+            }
+            let capacity = map.capacity();
+            keys.iter()
+                .map(|&key| {
+                    let (_, index) = map.get(key).unwrap();
+                    (index + capacity - key as usize % capacity) % capacity
+                })
+                .sum::<usize>()
+        };
+        assert!(probes(0.6) < probes(0.9));
+    }
+
+    #[test]
+    fn test_heuristic_weights() {
+        let (board, default) = seed_draw3();
+        let config = SolverConfig {
+            heuristic: HeuristicWeights {
+                remaining_cards: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let result = solve_with_config(board, 200_000, false, config).unwrap();
+        assert_ne!(result.states, default.states);
+        assert_eq!(
+            result.optimality,
+            Optimality::BestFound {
+                explored_all: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_heuristic_saturates() {
+        let weights = HeuristicWeights {
+            estimate: i16::MAX,
+            additional_moves: i16::MAX,
+            remaining_cards: i16::MAX,
+            round: i16::MAX,
+        };
+        assert_eq!(weights.heuristic(u8::MAX, u8::MAX, 0, usize::MAX), i16::MAX);
+        let weights = HeuristicWeights {
+            estimate: i16::MIN,
+            ..weights
+        };
+        assert_eq!(weights.heuristic(u8::MAX, 0, MAX_CARD, 0), i16::MIN);
+        assert_eq!(HeuristicWeights::default().heuristic(10, 1, 50, 2), 27);
+    }
+
+    #[test]
+    fn test_forbid_foundation_to_tableau() {
+        let board = Board::new_from_seed(283409412);
+        let default = solve(board.clone(), 400_000, true).unwrap();
+        let config = SolverConfig {
+            allow_foundation_to_tableau: false,
+            ..Default::default()
+        };
+        let result = solve_with_config(board.clone(), 400_000, true, config).unwrap();
+        assert!(result.states < default.states);
+        assert_eq!(result.actions.len(), default.actions.len());
+        // The minimal search ran out of states on its own, but the space lacked the
+        // moves from the foundations
+        assert_eq!(
+            result.optimality,
+            Optimality::BestFound { explored_all: true }
+        );
+        let fast = solve_with_config(board, 400_000, false, config).unwrap();
+        assert_eq!(
+            fast.optimality,
+            Optimality::BestFound {
+                explored_all: false
+            }
+        );
+        assert!(
+            !result
+                .actions
+                .iter()
+                .any(|action| matches!(action, Action::FoundationToTableau(..)))
+        );
+    }
+
+    #[test]
+    fn test_draw_count_over_u8() {
+        // The draw count used to truncate to 0 in the move estimates, dividing by zero
+        let board = Board::parse(
+            "Stock: T♦
+Waste: 9♠
+Foundation1: 9♦
+Foundation2: 9♣
+Foundation3: 9♥
+Foundation4: 8♠
+Tableau1: |K♠Q♥J♣
+Tableau2: |K♥Q♣J♦T♠
+Tableau3: |K♣Q♦J♠T♥
+Tableau4: |K♦Q♠J♥T♣
+DrawCount: 256",
+        )
+        .unwrap();
+        let result = solve(board, 100_000, true).unwrap();
+        assert!(result.minimal());
+        assert_eq!(result.draw_count(), 1);
+    }
+
+    #[test]
+    fn test_max_moves() {
+        let board = Board::parse(
+            "Waste: 9♠T♦
+Foundation1: 9♦
+Foundation2: 9♣
+Foundation3: 9♥
+Foundation4: 8♠
+Tableau1: |K♠Q♥J♣
+Tableau2: |K♥Q♣J♦T♠
+Tableau3: |K♣Q♦J♠T♥
+Tableau4: |K♦Q♠J♥T♣
+DrawCount: 3",
+        )
+        .unwrap();
+        let mut solver = Solver::new();
+        solver.set_board(board);
+        let result = solver.solve(100_000, true).unwrap();
+        assert!(result.minimal());
+        let moves = (result.card_moves() + result.draw_count()) as u8;
+
+        solver.set_max_moves(moves);
+        let bounded = solver.solve(100_000, false).unwrap();
+        assert!(bounded.card_moves() + bounded.draw_count() <= moves as usize);
+        solver.set_max_moves(moves - 1);
+        let err = solver.solve(100_000, true).unwrap_err();
+        assert_eq!(
+            err,
+            SolveError::ExceedsMaxMoves {
+                max_moves: moves - 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_max_rounds() {
+        let mut solver = Solver::new();
+        solver.set_board(seed_draw3_board());
+        solver.set_max_rounds(1);
+        let err = solver.solve(200_000, false).unwrap_err();
+        assert_eq!(err, SolveError::Unsolvable);
+
+        solver.set_max_rounds(2);
+        let result = solver.solve(200_000, false).unwrap();
+        assert_eq!(result.actions.iter().filter(|a| a.is_redeal()).count(), 1);
+    }
+
+    #[test]
+    fn test_solve_result_counts() {
+        let (_, result) = seed_draw3();
+        assert_eq!(result.redeal_count(), 1);
+        assert!(result.draw_count() > 0);
+        assert_eq!(
+            result.card_moves() + result.draw_count() + result.redeal_count(),
+            result.actions.len()
+        );
+        assert!(result.card_moves() >= MAX_CARD as usize);
+    }
+
+    #[test]
+    fn test_solver_reuse() {
+        let mut solver = Solver::new();
+        solver.set_board(seed_draw3_board());
+        let first = solver.solve(200_000, false).unwrap();
+        assert_eq!(
+            first.optimality,
+            Optimality::BestFound {
+                explored_all: false
+            }
+        );
+        assert!(solver.solve(10, false).is_err());
+        let second = solver.solve(200_000, false).unwrap();
+        assert_eq!(first.states, second.states);
+        assert_eq!(first.actions, second.actions);
+    }
+
+    #[test]
+    fn test_export_moves() {
+        let mut solver = Solver::new();
+        solver.set_board(seed_draw3_board());
+        let result = solver.solve(200_000, false).unwrap();
+        let moves = solver.export_moves();
+        let plays = result
+            .actions
+            .iter()
+            .filter(|a| !matches!(a, Action::Draw | Action::Redeal))
+            .count();
+        assert_eq!(moves.len(), plays);
+        let flips = moves
+            .iter()
+            .filter(|&&(from, _, _, flip)| flip && from >= PILE_TABLEAU_START)
+            .count();
+        assert_eq!(flips, 21);
+    }
+
+    #[test]
+    fn test_solve_timeout() {
+        let mut solver = Solver::new();
+        solver.set_board(Board::new_from_seed(283409412));
+        solver.set_timeout(Duration::ZERO);
+        let err = solver.solve(200_000, true).unwrap_err();
+        assert!(matches!(err, SolveError::TimedOut { states: 1, .. }));
+
+        let err = solve(Board::new_from_seed(283409412), 10, true).unwrap_err();
+        assert_eq!(err, SolveError::MaxStatesReached { max_states: 10 });
+    }
+
+    #[test]
+    fn test_obviously_unwinnable() {
+        // T♥ needs 9♥ or a black Jack and T♦ needs A♦ or a black Jack, all buried under them,
+        // while the search runs out of states on the deal
+        let board = Board::new_from_seed(78);
+        assert!(board.is_obviously_unwinnable());
+        assert_eq!(
+            solve(board, 1_000, true).unwrap_err(),
+            SolveError::Unsolvable
+        );
+    }
+
+    #[test]
+    fn test_solve_with_progress() {
+        let board = seed_draw3_board();
+        let mut reports = vec![];
+        let result = solve_with_progress(board, 200_000, false, |p| reports.push(p)).unwrap();
+        assert!(!reports.is_empty());
+        assert!(reports.windows(2).all(|w| w[0].states <= w[1].states));
+        assert!(reports.iter().all(|p| p.states <= result.states as u32));
+    }
+
+    #[test]
+    fn test_solve_streaming() {
+        let board = seed_draw3_board();
+        let mut solutions: Vec<(Vec<Action>, u8)> = vec![];
+        let result = solve_streaming(board, 200_000, true, |actions, move_count| {
+            solutions.push((actions.to_vec(), move_count))
+        })
+        .unwrap();
+        assert!(!solutions.is_empty());
+        assert!(solutions.windows(2).all(|w| w[0].1 > w[1].1));
+        assert_eq!(solutions.last().unwrap().0, result.actions);
+    }
+
+    #[test]
+    fn test_solve_greedy_prefix() {
+        let mut board = seed_draw3_board();
+        let result = solve_greedy_prefix(board.clone(), 200_000, false).unwrap();
+        assert!(!result.prefix.is_empty());
+
+        for action in &result.prefix {
+            assert!(safe_foundation_moves(&board).contains(action));
+            apply_action(&mut board, action);
+        }
+        for action in &result.tail.actions {
+            apply_action(&mut board, action);
+        }
+        assert_eq!(board.foundation_score(), 52);
+        assert_eq!(
+            result.actions().len(),
+            result.prefix.len() + result.tail.actions.len()
+        );
+
+        // `--max-moves` bounds the whole solution, not only the tail
+        let moves = result.prefix.len() + result.tail.card_moves() + result.tail.draw_count();
+        let board = seed_draw3_board();
+        let mut solver = Solver::new();
+        solver.set_board(board.clone());
+        solver.set_max_moves(moves as u8);
+        assert!(solver.solve_greedy_prefix(200_000, false).is_ok());
+        solver.set_board(board);
+        solver.set_max_moves(result.prefix.len() as u8 - 1);
+        assert_eq!(
+            solver.solve_greedy_prefix(200_000, false).err(),
+            Some(SolveError::ExceedsMaxMoves {
+                max_moves: result.prefix.len() as u8 - 1
+            })
+        );
+    }
 }
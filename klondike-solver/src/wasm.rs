@@ -0,0 +1,52 @@
+//! Entry point for running the solver in the browser, built with the `wasm` feature.
+
+use crate::solve;
+use klondike_common::board::Board;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Input of [`solve_json`]
+#[derive(serde::Deserialize)]
+struct SolveRequest {
+    board: String, // In the text format of `Board::parse`, e.g. as printed by the solver
+    #[serde(default)]
+    minimal: bool, // Search for the fewest moves rather than the first solution
+}
+
+/// Solves the board of a request such as `{"board": "Stock: ...", "minimal": true}`,
+/// returning the JSON of [`crate::SolveResult::to_json`], or `{"error": "..."}` when
+/// the request is invalid or the board isn't solved.
+#[wasm_bindgen]
+pub fn solve_json(board_json: &str, max_states: u32) -> String {
+    match try_solve_json(board_json, max_states) {
+        Ok(json) => json,
+        Err(err) => serde_json::json!({ "error": err }).to_string(),
+    }
+}
+
+fn try_solve_json(board_json: &str, max_states: u32) -> Result<String, String> {
+    let request: SolveRequest = serde_json::from_str(board_json).map_err(|err| err.to_string())?;
+    let board = Board::parse_validated(&request.board).map_err(|err| err.to_string())?;
+    let result = solve(board, max_states, request.minimal).map_err(|err| err.to_string())?;
+    result.to_json().map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_json() {
+        let board = Board::new_from_seed(283409412).to_pretty_string();
+        let request = serde_json::json!({ "board": board }).to_string();
+        let result: serde_json::Value =
+            serde_json::from_str(&solve_json(&request, 200_000)).unwrap();
+        assert!(!result["actions"].as_array().unwrap().is_empty());
+
+        let error = solve_json(r#"{"board": "Stock: 2♠2♠"}"#, 200_000);
+        assert!(
+            error.starts_with(r#"{"error":"duplicate card 2♠;"#),
+            "{error}"
+        );
+        assert!(solve_json("{}", 200_000).starts_with(r#"{"error":"missing field `board`"#));
+    }
+}
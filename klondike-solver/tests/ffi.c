@@ -0,0 +1,38 @@
+/* Drives the C ABI of the `ffi` feature, built and run by `tests/ffi.rs` */
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+
+char *klondike_solve(const char *board_str, uint32_t max_states, bool minimal, size_t *out_len);
+void klondike_free(char *s);
+const char *klondike_last_error(void);
+
+int main(int argc, char **argv) {
+    if (argc != 3) {
+        fprintf(stderr, "usage: ffi BOARD INVALID_BOARD\n");
+        return 2;
+    }
+
+    size_t len = 0;
+    char *moves = klondike_solve(argv[1], 100000, true, &len);
+    if (moves == NULL || len != strlen(moves)) {
+        fprintf(stderr, "solve failed: %s\n", klondike_last_error());
+        return 1;
+    }
+    printf("%s", moves);
+    klondike_free(moves);
+
+    if (klondike_solve(argv[2], 100000, true, NULL) != NULL) {
+        fprintf(stderr, "invalid board solved\n");
+        return 1;
+    }
+    const char *error = klondike_last_error();
+    if (error == NULL) {
+        fprintf(stderr, "no error\n");
+        return 1;
+    }
+    printf("error: %s\n", error);
+    return 0;
+}
@@ -0,0 +1,51 @@
+//! Builds `tests/ffi.c` against the cdylib of the solver and checks its output.
+#![cfg(unix)]
+
+use klondike_common::{action::format_actions, board::Board};
+use std::{path::PathBuf, process::Command};
+
+const BOARD: &str = "Waste: 9♠T♦
+Foundation1: 9♦
+Foundation2: 9♣
+Foundation3: 9♥
+Foundation4: 8♠
+Tableau1: |K♠Q♥J♣
+Tableau2: |K♥Q♣J♦T♠
+Tableau3: |K♣Q♦J♠T♥
+Tableau4: |K♦Q♠J♥T♣
+DrawCount: 3";
+const INVALID_BOARD: &str = "Stock: 2♠2♠";
+
+#[test]
+fn test_c_abi() {
+    // The cdylib built for the tests is next to the test binary, in `target/<profile>/deps`
+    let exe = std::env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap();
+    let program = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("ffi");
+    let status = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/ffi.c"))
+        .arg("-o")
+        .arg(&program)
+        .arg(format!("-L{}", lib_dir.display()))
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lklondike_solver")
+        .status()
+        .expect("a C compiler is needed, set CC to choose one");
+    assert!(status.success());
+
+    // Cargo puts `target/<profile>` first in the library path, where the cdylib may lack `ffi`
+    let output = Command::new(&program)
+        .env_remove("LD_LIBRARY_PATH")
+        .args([BOARD, INVALID_BOARD])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+
+    let result = klondike_solver::solve(Board::parse(BOARD).unwrap(), 100_000, true).unwrap();
+    let error = Board::parse_validated(INVALID_BOARD).unwrap_err();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{}error: {error}\n", format_actions(&result.actions))
+    );
+}
@@ -1,35 +1,90 @@
 //! This module provides functionality for autoplaying the Solitaire game using mouse movements and clicks.
 
+mod pacing;
 mod window;
 
+pub use self::pacing::Pacing;
+pub use self::window::Calibration;
 use self::window::*;
 
-use crate::inspect::get_pid;
+use crate::config::InspectConfig;
+use crate::inspect::{get_pid, inspect};
 
 use anyhow::{Context, Result, anyhow, bail};
-use enigo::{Button, Coordinate, Direction, Enigo, Mouse, Settings, set_dpi_awareness};
+use enigo::{
+    Axis, Button, Coordinate, Direction, Enigo, InputResult, Mouse, Settings, set_dpi_awareness,
+};
 use klondike_common::{
     action::{Action, apply_action, describe_action},
-    board::Board,
+    board::{Board, Card},
+};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, sleep},
+    time::{Duration, Instant},
 };
-use std::{thread::sleep, time::Duration};
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::VK_ESCAPE;
+
+const WINDOW_TIMEOUT: Duration = Duration::from_secs(5); // Wait for the game window at startup
+const FOCUS_TIMEOUT: Duration = Duration::from_secs(30); // Wait for the game window to regain focus
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const MIN_INTERVAL: u64 = 500; // Milliseconds
+const SYNC_CHECK_INTERVAL: usize = 5; // Moves between two comparisons of the game with the solution
 
-pub fn autoplay(mut board: Board, actions: Vec<Action>, interval: u64) -> Result<()> {
-    let (window_rect, hwnd) = get_window_rect(get_pid()?)?;
-    let window = Window::new(window_rect);
-    let interval = interval.max(500);
+pub fn autoplay(
+    mut board: Board,
+    actions: Vec<Action>,
+    interval: u64,
+    pacing: Pacing,
+    calibration: Option<&Calibration>,
+    config: &InspectConfig,
+) -> Result<()> {
+    let (pid, window_rect, hwnd) = wait_for_window(&config.process_name)?;
+    let mut window = new_window(window_rect, calibration);
+    if let Some(mismatch) = describe_mismatch(&board, &inspect(config)?) {
+        bail!("The game does not match the solved board: {mismatch}");
+    }
+    let interval = interval.max(MIN_INTERVAL);
+    let mut wait = interval;
 
     let mut enigo = Enigo::new(&Settings::default()).context("Failed to init enigo")?;
     set_dpi_awareness().map_err(|_| anyhow!("Failed to set DPI awareness"))?;
+    let stop_key = StopKey::watch();
+    println!("Press Esc to stop");
 
     focus_window(hwnd)?;
     sleep(Duration::from_millis(100));
 
     let actions_count = actions.len();
     for (index, action) in actions.iter().enumerate() {
-        sleep(Duration::from_millis(interval));
+        sleep(Duration::from_millis(wait));
+        if stop_key.is_pressed() {
+            println!("Stopped by Esc");
+            return Ok(());
+        }
         if !is_foreground_window(hwnd) {
-            bail!("Abort due to lost focus on the game window");
+            println!(
+                "Lost focus on the game window, waiting up to {}s for it to come back",
+                FOCUS_TIMEOUT.as_secs()
+            );
+            if !wait_for_focus(hwnd) {
+                bail!("Abort due to lost focus on the game window");
+            }
+            // The window may have been moved or resized meanwhile
+            window = new_window(get_window_rect(pid)?.0, calibration);
+            sleep(Duration::from_millis(interval));
+        }
+        if index > 0
+            && index % SYNC_CHECK_INTERVAL == 0
+            && let Some(mismatch) = describe_mismatch(&board, &inspect(config)?)
+        {
+            bail!(
+                "The game is out of sync with the solution before move {}: {mismatch}",
+                index + 1
+            );
         }
         println!(
             "{:03}/{actions_count:03} {}",
@@ -38,10 +93,139 @@ pub fn autoplay(mut board: Board, actions: Vec<Action>, interval: u64) -> Result
         );
         play_action(&board, action, &mut enigo, &window)?;
         apply_action(&mut board, action);
+        wait = ((interval as f32 * pacing.factor(action)) as u64).max(MIN_INTERVAL);
+    }
+    Ok(())
+}
+
+/// Print the mouse actions that [`autoplay`] would perform, without moving the mouse
+pub fn autoplay_dry_run(
+    mut board: Board,
+    actions: Vec<Action>,
+    calibration: Option<&Calibration>,
+    process_name: &str,
+) -> Result<()> {
+    let (_, window_rect, _) = wait_for_window(process_name)?;
+    println!(
+        "Window at ({}, {}), {}x{}",
+        window_rect.left,
+        window_rect.top,
+        window_rect.right - window_rect.left,
+        window_rect.bottom - window_rect.top
+    );
+    let window = new_window(window_rect, calibration);
+    let mut mouse = MouseLog::default();
+
+    let actions_count = actions.len();
+    for (index, action) in actions.iter().enumerate() {
+        println!(
+            "{:03}/{actions_count:03} {}",
+            index + 1,
+            describe_action(&board, action)
+        );
+        play_action(&board, action, &mut mouse, &window)?;
+        apply_action(&mut board, action);
     }
     Ok(())
 }
 
+/// The first difference of the game from the board expected by the solution, if any
+fn describe_mismatch(expected: &Board, live: &Board) -> Option<String> {
+    fn format_cards<'a>(cards: impl IntoIterator<Item = &'a Card>) -> String {
+        let cards: Vec<_> = cards.into_iter().map(|card| card.to_string()).collect();
+        format!("[{}]", cards.join(" "))
+    }
+    if live.draw_count() != expected.draw_count() {
+        return Some(format!(
+            "the game draws {} cards per turn, the solution {}",
+            live.draw_count(),
+            expected.draw_count()
+        ));
+    }
+    if live.foundations != expected.foundations {
+        return Some(format!(
+            "the foundations are {}, expected {}",
+            format_cards(live.foundations.iter().flatten()),
+            format_cards(expected.foundations.iter().flatten())
+        ));
+    }
+    for (i, (live, expected)) in live.tableaus.iter().zip(&expected.tableaus).enumerate() {
+        if live != expected {
+            return Some(format!(
+                "tableau {} is {} with {} face up, expected {} with {} face up",
+                i + 1,
+                format_cards(&live.cards),
+                live.face_up_count,
+                format_cards(&expected.cards),
+                expected.face_up_count
+            ));
+        }
+    }
+    if live.stock != expected.stock || live.waste != expected.waste {
+        return Some(format!(
+            "the stock and waste are {} and {}, expected {} and {}",
+            format_cards(&live.stock),
+            format_cards(&live.waste),
+            format_cards(&expected.stock),
+            format_cards(&expected.waste)
+        ));
+    }
+    None
+}
+
+/// Ask the user to point at the stock and the last foundation, to find the play area in the window
+pub fn calibrate(process_name: &str) -> Result<Calibration> {
+    let (_, window_rect, _) = wait_for_window(process_name)?;
+    let enigo = Enigo::new(&Settings::default()).context("Failed to init enigo")?;
+    set_dpi_awareness().map_err(|_| anyhow!("Failed to set DPI awareness"))?;
+
+    // Hovering instead of clicking, as a click on the stock would draw
+    let stock = read_mouse_location(&enigo, "the center of the stock")?;
+    let foundation = read_mouse_location(&enigo, "the center of the last foundation")?;
+    Calibration::from_points(&window_rect, stock, foundation)
+}
+
+fn read_mouse_location(enigo: &Enigo, target: &str) -> Result<Point> {
+    println!("Hover the mouse over {target}, then press Enter");
+    std::io::stdin().read_line(&mut String::new())?;
+    Ok(enigo.location()?)
+}
+
+fn new_window(rect: Rect, calibration: Option<&Calibration>) -> Window {
+    match calibration {
+        Some(calibration) => Window::calibrated(rect, calibration),
+        None => Window::new(rect),
+    }
+}
+
+/// Poll for the process and its main window until [`WINDOW_TIMEOUT`]
+fn wait_for_window(process_name: &str) -> Result<(u32, Rect, isize)> {
+    let deadline = Instant::now() + WINDOW_TIMEOUT;
+    loop {
+        let found = get_pid(process_name).and_then(|pid| {
+            let (rect, hwnd) = get_window_rect(pid)?;
+            Ok((pid, rect, hwnd))
+        });
+        match found {
+            Ok(found) => return Ok(found),
+            Err(err) if Instant::now() >= deadline => return Err(err),
+            Err(_) => sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// Poll until the window is in the foreground again, false after [`FOCUS_TIMEOUT`]
+fn wait_for_focus(hwnd: isize) -> bool {
+    let deadline = Instant::now() + FOCUS_TIMEOUT;
+    while !is_foreground_window(hwnd) {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        sleep(POLL_INTERVAL);
+    }
+    true
+}
+
 fn play_action(
     board: &Board,
     action: &Action,
@@ -119,6 +303,73 @@ fn play_action(
     Ok(())
 }
 
+/// Watches the Esc key from a background thread until dropped, for autoplay to stop between moves
+struct StopKey {
+    pressed: Arc<AtomicBool>,
+    done: Arc<AtomicBool>,
+}
+
+impl StopKey {
+    fn watch() -> Self {
+        let pressed = Arc::new(AtomicBool::new(false));
+        let done = Arc::new(AtomicBool::new(false));
+        {
+            let pressed = pressed.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    if is_key_down(VK_ESCAPE) {
+                        pressed.store(true, Ordering::Relaxed);
+                        break;
+                    }
+                    sleep(Duration::from_millis(50));
+                }
+            });
+        }
+        Self { pressed, done }
+    }
+
+    fn is_pressed(&self) -> bool {
+        self.pressed.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for StopKey {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The left button held down, released when dropped so that no exit path leaves it pressed
+struct PressedButton<'a, M: Mouse> {
+    mouse: &'a mut M,
+    released: bool,
+}
+
+impl<'a, M: Mouse> PressedButton<'a, M> {
+    fn press(mouse: &'a mut M) -> Result<Self> {
+        mouse.button(Button::Left, Direction::Press)?;
+        Ok(Self {
+            mouse,
+            released: false,
+        })
+    }
+
+    fn release(mut self) -> Result<()> {
+        self.released = true;
+        self.mouse.button(Button::Left, Direction::Release)?;
+        Ok(())
+    }
+}
+
+impl<M: Mouse> Drop for PressedButton<'_, M> {
+    fn drop(&mut self) {
+        if !self.released {
+            let _ = self.mouse.button(Button::Left, Direction::Release);
+        }
+    }
+}
+
 fn mouse_click(enigo: &mut impl Mouse, point: Point) -> Result<()> {
     enigo.move_mouse(point.0, point.1, Coordinate::Abs)?;
     sleep(Duration::from_millis(50));
@@ -131,7 +382,7 @@ fn mouse_move(enigo: &mut impl Mouse, from_point: (i32, i32), to_point: (i32, i3
     let (to_x, to_y) = to_point;
 
     enigo.move_mouse(from_x, from_y, Coordinate::Abs)?;
-    enigo.button(Button::Left, Direction::Press)?;
+    let pressed = PressedButton::press(enigo)?;
 
     sleep(Duration::from_millis(50));
 
@@ -141,10 +392,45 @@ fn mouse_move(enigo: &mut impl Mouse, from_point: (i32, i32), to_point: (i32, i3
     for i in 1..=steps {
         let x = from_x as f32 + dx * i as f32;
         let y = from_y as f32 + dy * i as f32;
-        enigo.move_mouse(x as i32, y as i32, Coordinate::Abs)?;
+        pressed
+            .mouse
+            .move_mouse(x as i32, y as i32, Coordinate::Abs)?;
         sleep(Duration::from_millis(15));
     }
 
-    enigo.button(Button::Left, Direction::Release)?;
-    Ok(())
+    pressed.release()
+}
+
+/// A [`Mouse`] printing the button presses, releases and clicks with their screen point
+#[derive(Debug, Default)]
+struct MouseLog {
+    location: Point,
+}
+
+impl Mouse for MouseLog {
+    fn button(&mut self, button: Button, direction: Direction) -> InputResult<()> {
+        let (x, y) = self.location;
+        println!("    {direction:?} {button:?} at ({x}, {y})");
+        Ok(())
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> InputResult<()> {
+        self.location = match coordinate {
+            Coordinate::Abs => (x, y),
+            Coordinate::Rel => (self.location.0 + x, self.location.1 + y),
+        };
+        Ok(())
+    }
+
+    fn scroll(&mut self, _length: i32, _axis: Axis) -> InputResult<()> {
+        Ok(())
+    }
+
+    fn main_display(&self) -> InputResult<(i32, i32)> {
+        Ok((0, 0))
+    }
+
+    fn location(&self) -> InputResult<(i32, i32)> {
+        Ok(self.location)
+    }
 }
@@ -0,0 +1,91 @@
+use anyhow::{Result, anyhow, bail};
+use klondike_common::action::Action;
+use std::str::FromStr;
+
+/// Multipliers of the autoplay interval, by the kind of the move waited after,
+/// to let the game finish its animation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pacing {
+    pub draw: f32,
+    pub redeal: f32,
+    pub foundation: f32, // Any move to a foundation
+    pub tableau: f32,    // A single card moved to a tableau
+    pub multi_card: f32, // A run of cards moved between tableaus
+}
+
+impl Default for Pacing {
+    fn default() -> Self {
+        Self {
+            draw: 0.75,
+            redeal: 1.5,
+            foundation: 0.75,
+            tableau: 1.0,
+            multi_card: 1.5,
+        }
+    }
+}
+
+impl Pacing {
+    pub fn factor(&self, action: &Action) -> f32 {
+        match action {
+            Action::Draw => self.draw,
+            Action::Redeal => self.redeal,
+            Action::WasteToFoundation(_) | Action::TableauToFoundation(..) => self.foundation,
+            Action::TableauToTableau(_, _, count) if *count > 1 => self.multi_card,
+            Action::WasteToTableau(_)
+            | Action::FoundationToTableau(..)
+            | Action::TableauToTableau(..) => self.tableau,
+        }
+    }
+}
+
+/// Parse `kind=factor` pairs separated by commas, e.g. `draw=0.5,multi_card=2`,
+/// the kinds not given keeping their default
+impl FromStr for Pacing {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut pacing = Self::default();
+        for pair in s.split(',') {
+            let (kind, factor) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid pacing '{pair}', expected KIND=FACTOR"))?;
+            let factor: f32 = factor
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid factor '{factor}' of '{kind}'"))?;
+            if !(factor.is_finite() && factor > 0.0) {
+                bail!("Factor of '{kind}' must be positive");
+            }
+            let field = match kind.trim() {
+                "draw" => &mut pacing.draw,
+                "redeal" => &mut pacing.redeal,
+                "foundation" => &mut pacing.foundation,
+                "tableau" => &mut pacing.tableau,
+                "multi_card" => &mut pacing.multi_card,
+                _ => bail!(
+                    "Unknown move kind '{kind}', expected draw, redeal, foundation, tableau or multi_card"
+                ),
+            };
+            *field = factor;
+        }
+        Ok(pacing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacing() {
+        let pacing: Pacing = "redeal=3, multi_card=2.5".parse().unwrap();
+        assert_eq!(pacing.factor(&Action::Redeal), 3.0);
+        assert_eq!(pacing.factor(&Action::TableauToTableau(0, 1, 3)), 2.5);
+        assert_eq!(pacing.factor(&Action::TableauToTableau(0, 1, 1)), 1.0);
+        assert_eq!(pacing.factor(&Action::TableauToFoundation(0, 1)), 0.75);
+        assert!("draw".parse::<Pacing>().is_err());
+        assert!("draw=0".parse::<Pacing>().is_err());
+        assert!("shuffle=1".parse::<Pacing>().is_err());
+    }
+}
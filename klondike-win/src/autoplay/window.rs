@@ -1,7 +1,11 @@
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
+use std::fmt;
+use std::str::FromStr;
 
+use klondike_common::board::TOTAL_FOUNDATIONS;
 use windows_sys::Win32::Foundation::{HWND, LPARAM, RECT, S_OK};
 use windows_sys::Win32::Graphics::Dwm::{DWMWA_EXTENDED_FRAME_BOUNDS, DwmGetWindowAttribute};
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VIRTUAL_KEY};
 use windows_sys::Win32::UI::WindowsAndMessaging::{
     EnumWindows, GetForegroundWindow, GetWindowThreadProcessId, IsIconic, IsWindowVisible,
     SW_RESTORE, SetForegroundWindow, ShowWindow,
@@ -28,9 +32,70 @@ pub struct Rect {
     pub bottom: i32,
 }
 
+/// Where the play area lies in a window that letterboxes it: its scale from the layout
+/// of the reference window, and the offset of its top left corner from the window's
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calibration {
+    pub scale: f32,
+    pub left: i32,
+    pub top: i32,
+}
+
+impl Calibration {
+    /// Anchor the play area with the screen points of the stock and the last foundation
+    pub fn from_points(rect: &Rect, stock: Point, foundation: Point) -> Result<Self> {
+        let model_distance = (TOTAL_FOUNDATIONS as i32 + 2) * TABLEAU_OFFSET_X;
+        let scale = (foundation.0 - stock.0) as f32 / model_distance as f32;
+        if scale <= 0.0 {
+            bail!("The last foundation must be to the right of the stock");
+        }
+        // Inverse of `Window::transform`, both points on the same row
+        let y = (stock.1 + foundation.1) / 2;
+        Ok(Self {
+            scale,
+            left: stock.0 - (STOCK_CENTER_X as f32 * scale) as i32 - rect.left,
+            top: y - (STOCK_CLICK_Y as f32 * scale) as i32 - rect.top,
+        })
+    }
+}
+
+/// `scale,left,top`, as printed by the calibration
+impl fmt::Display for Calibration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{}", self.scale, self.left, self.top)
+    }
+}
+
+impl FromStr for Calibration {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<_> = s.split(',').map(str::trim).collect();
+        let [scale, left, top] = parts[..] else {
+            bail!("Invalid calibration '{s}', expected SCALE,LEFT,TOP");
+        };
+        let scale: f32 = scale
+            .parse()
+            .with_context(|| format!("Invalid scale '{scale}'"))?;
+        if !(scale.is_finite() && scale > 0.0) {
+            bail!("Scale must be positive");
+        }
+        Ok(Self {
+            scale,
+            left: left
+                .parse()
+                .with_context(|| format!("Invalid left '{left}'"))?,
+            top: top
+                .parse()
+                .with_context(|| format!("Invalid top '{top}'"))?,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Window {
     rect: Rect,
+    offset: Point, // Of the play area from the top left corner of the window
     factor_x: f32,
     factor_y: f32,
 }
@@ -43,11 +108,22 @@ impl Window {
         let factor_y = height as f32 / WINDOW_HEIGHT as f32;
         Window {
             rect,
+            offset: (0, 0),
             factor_x,
             factor_y,
         }
     }
 
+    /// A window whose play area is placed by the calibration instead of filling the window
+    pub fn calibrated(rect: Rect, calibration: &Calibration) -> Self {
+        Window {
+            rect,
+            offset: (calibration.left, calibration.top),
+            factor_x: calibration.scale,
+            factor_y: calibration.scale,
+        }
+    }
+
     pub fn stock_point(&self) -> Point {
         self.transform(STOCK_CENTER_X, STOCK_CLICK_Y)
     }
@@ -116,8 +192,8 @@ impl Window {
 
     fn transform(&self, x: i32, y: i32) -> Point {
         (
-            (x as f32 * self.factor_x) as i32 + self.rect.left,
-            (y as f32 * self.factor_y) as i32 + self.rect.top,
+            (x as f32 * self.factor_x) as i32 + self.rect.left + self.offset.0,
+            (y as f32 * self.factor_y) as i32 + self.rect.top + self.offset.1,
         )
     }
 }
@@ -198,6 +274,11 @@ pub fn is_foreground_window(hwnd: isize) -> bool {
     fg == hwnd
 }
 
+/// Whether the key is held down, whichever window has the focus
+pub fn is_key_down(key: VIRTUAL_KEY) -> bool {
+    unsafe { GetAsyncKeyState(key as i32) < 0 }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -287,4 +368,45 @@ mod tests {
             "From Tableau#7, Count: 12, Uncovered: 6, Moved: 1",
         );
     }
+
+    #[test]
+    fn test_calibration() {
+        // The reference layout filling the window maps to itself
+        let rect = Rect {
+            left: 100,
+            top: 50,
+            right: 100 + WINDOW_WIDTH,
+            bottom: 50 + WINDOW_HEIGHT,
+        };
+        let window = Window::new(rect.clone());
+        let calibration =
+            Calibration::from_points(&rect, window.stock_point(), window.foundation_point(3))
+                .unwrap();
+        assert_eq!(
+            calibration,
+            Calibration {
+                scale: 1.0,
+                left: 0,
+                top: 0
+            }
+        );
+
+        // A wide window letterboxing a half-size play area in its middle
+        let rect = Rect {
+            left: 0,
+            top: 0,
+            right: 3000,
+            bottom: WINDOW_HEIGHT / 2,
+        };
+        let calibration = Calibration::from_points(&rect, (1095, 92), (1893, 92)).unwrap();
+        assert_eq!(calibration.to_string(), "0.5,1000,0");
+        let window = Window::calibrated(rect.clone(), &calibration.to_string().parse().unwrap());
+        assert_eq!(window.stock_point(), (1095, 92));
+        assert_eq!(window.foundation_point(0), (1494, 92));
+        assert_eq!(window.move_to_tableau_point(0, 1, 1), (1095, 274));
+
+        assert!(Calibration::from_points(&rect, (1893, 92), (1095, 92)).is_err());
+        assert!("0.5,10".parse::<Calibration>().is_err());
+        assert!("-1,0,0".parse::<Calibration>().is_err());
+    }
 }
@@ -0,0 +1,111 @@
+//! Where to find the game state in the Solitaire process, adjustable for other game versions.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+const PROCESS_NAME: &str = "Solitaire.exe";
+const PILE_LIST_OFFSETS: [usize; 3] = [0xBAFA8, 0x80, 0x98];
+const DRAW_OFFSETS: [usize; 3] = [0xBAFA8, 0x48, 0x14];
+const PROCESS_NAME_ENV: &str = "KLONDIKE_PROCESS_NAME";
+const PILE_LIST_OFFSETS_ENV: &str = "KLONDIKE_PILE_LIST_OFFSETS";
+const DRAW_OFFSETS_ENV: &str = "KLONDIKE_DRAW_OFFSETS";
+
+/// The name of the Solitaire process and the pointer chains, from its base address,
+/// to the pile list and the draw count
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct InspectConfig {
+    pub process_name: String,
+    pub pile_list_offsets: Vec<usize>,
+    pub draw_offsets: Vec<usize>,
+}
+
+impl Default for InspectConfig {
+    fn default() -> Self {
+        Self {
+            process_name: PROCESS_NAME.to_string(),
+            pile_list_offsets: PILE_LIST_OFFSETS.to_vec(),
+            draw_offsets: DRAW_OFFSETS.to_vec(),
+        }
+    }
+}
+
+impl InspectConfig {
+    /// Load the TOML file if any, then apply the `KLONDIKE_PROCESS_NAME`,
+    /// `KLONDIKE_PILE_LIST_OFFSETS` and `KLONDIKE_DRAW_OFFSETS` environment variables.
+    /// Settings given nowhere keep the values of the supported game version.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut config = match path {
+            Some(path) => {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read '{}'", path.display()))?;
+                Self::parse(&content)
+                    .with_context(|| format!("Failed to parse '{}'", path.display()))?
+            }
+            None => Self::default(),
+        };
+        if let Ok(name) = std::env::var(PROCESS_NAME_ENV) {
+            config.process_name = name;
+        }
+        if let Ok(value) = std::env::var(PILE_LIST_OFFSETS_ENV) {
+            config.pile_list_offsets = parse_offsets(&value)
+                .with_context(|| format!("Invalid {PILE_LIST_OFFSETS_ENV}"))?;
+        }
+        if let Ok(value) = std::env::var(DRAW_OFFSETS_ENV) {
+            config.draw_offsets =
+                parse_offsets(&value).with_context(|| format!("Invalid {DRAW_OFFSETS_ENV}"))?;
+        }
+        Ok(config)
+    }
+
+    /// Parse a TOML config such as
+    ///
+    /// ```toml
+    /// process_name = "Solitaire.exe"
+    /// pile_list_offsets = [0xBAFA8, 0x80, 0x98]
+    /// draw_offsets = [0xBAFA8, 0x48, 0x14]
+    /// ```
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+}
+
+/// Parse comma-separated offsets, each a decimal or `0x` hexadecimal number
+fn parse_offsets(value: &str) -> Result<Vec<usize>> {
+    value
+        .split(',')
+        .map(|offset| {
+            let offset = offset.trim();
+            match offset.strip_prefix("0x") {
+                Some(hex) => usize::from_str_radix(hex, 16),
+                None => offset.parse(),
+            }
+            .with_context(|| format!("Invalid offset '{offset}'"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = InspectConfig::parse(
+            "process_name = \"Klondike.exe\"\npile_list_offsets = [0xBB000, 0x80, 152]",
+        )
+        .unwrap();
+        assert_eq!(config.process_name, "Klondike.exe");
+        assert_eq!(config.pile_list_offsets, [0xBB000, 0x80, 0x98]);
+        assert_eq!(config.draw_offsets, DRAW_OFFSETS);
+        assert_eq!(InspectConfig::parse("").unwrap(), InspectConfig::default());
+        assert!(InspectConfig::parse("draw_offsets = \"0x48\"").is_err());
+
+        assert_eq!(
+            parse_offsets("0xBAFA8, 0x48,20").unwrap(),
+            [0xBAFA8, 0x48, 20]
+        );
+        assert!(parse_offsets("0xBAFA8,").is_err());
+    }
+}
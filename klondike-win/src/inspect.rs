@@ -1,5 +1,7 @@
 //! Reads the memory of the Solitaire process to extract the game state.
 
+use crate::config::InspectConfig;
+
 use klondike_common::board::{Board, Card, TOTAL_FOUNDATIONS, TOTAL_TABLEAUS, Tableau};
 
 use anyhow::{Context, Result, anyhow, bail};
@@ -18,31 +20,45 @@ use windows_sys::Win32::System::Threading::{
     OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ,
 };
 
-const PROCESS_NAME: &str = "Solitaire.exe";
-const PILE_LIST_OFFSETS: [usize; 3] = [0xBAFA8, 0x80, 0x98];
-const DRAW_OFFSETS: [usize; 3] = [0xBAFA8, 0x48, 0x14];
 const STOCK_PILE_INDEX: usize = TOTAL_FOUNDATIONS + TOTAL_TABLEAUS;
 const WASTE_PILE_INDEX: usize = STOCK_PILE_INDEX + 1;
+const WORD_SIZE: usize = std::mem::size_of::<usize>();
+const PROBE_RADIUS: usize = 4 * WORD_SIZE; // Bytes dumped on each side of a probed offset
 
 // Inspect the current state of the Solitaire game
-pub fn inspect() -> Result<Board> {
-    let inspector = Inspector::new()?;
+pub fn inspect(config: &InspectConfig) -> Result<Board> {
+    let inspector = Inspector::new(config)?;
     inspector.read()
 }
 
+// Walk the pointer chains of the config, dumping the words around each step
+// to help re-find the offsets after a game update
+pub fn probe(config: &InspectConfig) -> Result<String> {
+    let inspector = Inspector::new(config)?;
+    let mut report = format!("<base_addr> = {:#x}\n", inspector.base_addr);
+    for (name, offsets) in [
+        ("pile_list", &config.pile_list_offsets),
+        ("draw_count", &config.draw_offsets),
+    ] {
+        report.push_str(&format!("\n{name}: {}\n", format_offsets(offsets)));
+        inspector.probe_chain(offsets, &mut report);
+    }
+    Ok(report)
+}
+
 // Check if the Solitaire process is running
-pub fn is_running() -> bool {
-    get_pid().is_ok()
+pub fn is_running(process_name: &str) -> bool {
+    get_pid(process_name).is_ok()
 }
 
 // Get the PID of the Solitaire process
-pub fn get_pid() -> Result<u32> {
+pub fn get_pid(process_name: &str) -> Result<u32> {
     let mut system = System::new_all();
     system.refresh_all();
-    if let Some(process) = system.processes_by_name(OsStr::new(PROCESS_NAME)).next() {
+    if let Some(process) = system.processes_by_name(OsStr::new(process_name)).next() {
         Ok(process.pid().as_u32())
     } else {
-        bail!("Process '{PROCESS_NAME}' not found. Please ensure {PROCESS_NAME} is running.");
+        bail!("Process '{process_name}' not found. Please ensure {process_name} is running.");
     }
 }
 
@@ -58,25 +74,32 @@ impl Drop for Handle {
     }
 }
 
-struct Inspector {
+struct Inspector<'a> {
     handle: Handle,
     base_addr: usize,
+    config: &'a InspectConfig,
 }
 
-impl Inspector {
-    fn new() -> Result<Self> {
-        let pid = get_pid()?;
+impl<'a> Inspector<'a> {
+    fn new(config: &'a InspectConfig) -> Result<Self> {
+        let pid = get_pid(&config.process_name)?;
         let handle = Self::get_handle(pid)?;
         let base_addr = Self::get_base_addr(pid)?;
-        Ok(Self { handle, base_addr })
+        Ok(Self {
+            handle,
+            base_addr,
+            config,
+        })
     }
 
     fn read(&self) -> Result<Board> {
         let mut board = Board::new();
-        let pile_list = self.read_pile_list().context("Failed to read pile_list")?;
+        let pile_list = self.read_pile_list().context(
+            "Failed to read pile_list, check the pile list offsets for this game version",
+        )?;
         let draw_count = self
             .read_draw_count()
-            .context("Failed to read draw_count")?;
+            .context("Failed to read draw_count, check the draw offsets for this game version")?;
         board.set_draw_count(draw_count as usize);
         for i in 0..TOTAL_FOUNDATIONS {
             let (cards, _) = self.read_pile(&pile_list.piles, TOTAL_FOUNDATIONS - 1 - i)?;
@@ -137,20 +160,75 @@ impl Inspector {
     fn read_pointer_chain(&self, offsets: &[usize]) -> Result<usize> {
         let mut ptr = self.base_addr;
         let mut ptr_note = String::from("<base_addr>");
-        for offset in offsets.iter() {
+        let mut resolved = format!("<base_addr> = {ptr:#x}");
+        for (i, offset) in offsets.iter().enumerate() {
             ptr_note = format!("[{ptr_note}+{offset:#x}]");
-            ptr = self.read_memory::<usize>(ptr + offset, &ptr_note)?;
+            ptr = self
+                .read_memory::<usize>(ptr + offset, &ptr_note)
+                .with_context(|| {
+                    format!(
+                        "Pointer chain {} failed at offset #{i} ({offset:#x}), resolved so far: {resolved}",
+                        format_offsets(offsets)
+                    )
+                })?;
+            resolved.push_str(&format!(", {ptr_note} = {ptr:#x}"));
         }
         Ok(ptr)
     }
 
+    fn probe_chain(&self, offsets: &[usize], report: &mut String) {
+        let mut ptr = self.base_addr;
+        let mut ptr_note = String::from("<base_addr>");
+        for (i, offset) in offsets.iter().enumerate() {
+            ptr_note = format!("[{ptr_note}+{offset:#x}]");
+            report.push_str(&format!(
+                "  #{i} {ptr_note} at {:#x}\n",
+                ptr.wrapping_add(*offset)
+            ));
+            self.dump_words(ptr, *offset, report);
+            match self.read_memory::<usize>(ptr.wrapping_add(*offset), &ptr_note) {
+                Ok(value) => {
+                    report.push_str(&format!("     = {value:#x}\n"));
+                    ptr = value;
+                }
+                Err(err) => {
+                    report.push_str(&format!("     {err}\n"));
+                    return;
+                }
+            }
+        }
+    }
+
+    // Dump the words of the object at `ptr` around `offset`, marking the ones that
+    // point to readable memory as candidates for the next step of a chain
+    fn dump_words(&self, ptr: usize, offset: usize, report: &mut String) {
+        let start = offset - offset.min(PROBE_RADIUS) / WORD_SIZE * WORD_SIZE;
+        for word_offset in (start..=offset + PROBE_RADIUS).step_by(WORD_SIZE) {
+            let marker = if word_offset == offset { '>' } else { ' ' };
+            let addr = ptr.wrapping_add(word_offset);
+            let line = match self.read_memory::<[u8; WORD_SIZE]>(addr, "") {
+                Ok(bytes) => {
+                    let hex: Vec<_> = bytes.iter().map(|b| format!("{b:02x}")).collect();
+                    let value = usize::from_ne_bytes(bytes);
+                    let candidate = match self.read_memory::<usize>(value, "") {
+                        Ok(_) => " (pointer)",
+                        Err(_) => "",
+                    };
+                    format!("{} {value:#x}{candidate}", hex.join(" "))
+                }
+                Err(_) => "??".to_string(),
+            };
+            report.push_str(&format!("   {marker} +{word_offset:#06x} {line}\n"));
+        }
+    }
+
     fn read_pile_list(&self) -> Result<PileListObj> {
-        let ptr = self.read_pointer_chain(&PILE_LIST_OFFSETS)?;
+        let ptr = self.read_pointer_chain(&self.config.pile_list_offsets)?;
         self.read_memory::<PileListObj>(ptr, "<pile_list_ptr>")
     }
 
     fn read_draw_count(&self) -> Result<u8> {
-        let value = self.read_pointer_chain(&DRAW_OFFSETS)?;
+        let value = self.read_pointer_chain(&self.config.draw_offsets)?;
         Ok(value as u8)
     }
 
@@ -221,6 +299,14 @@ impl Inspector {
     }
 }
 
+fn format_offsets(offsets: &[usize]) -> String {
+    let offsets: Vec<_> = offsets
+        .iter()
+        .map(|offset| format!("{offset:#x}"))
+        .collect();
+    format!("[{}]", offsets.join(", "))
+}
+
 fn parse_card(card_str: &str) -> Option<Card> {
     let mut parts = card_str.split("Of");
     let rank_str = parts.next()?;
@@ -285,9 +371,10 @@ mod tests {
 
     #[test]
     fn test_inspect() {
-        match is_running() {
+        let config = InspectConfig::default();
+        match is_running(&config.process_name) {
             true => {
-                let board = inspect().unwrap();
+                let board = inspect(&config).unwrap();
                 assert!(
                     board.draw_count() == 1 || board.draw_count() == 3,
                     "Draw count should be 1 or 3"
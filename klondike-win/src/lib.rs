@@ -1,7 +1,9 @@
 #![cfg(windows)]
 
 mod autoplay;
+mod config;
 mod inspect;
 
 pub use autoplay::*;
+pub use config::*;
 pub use inspect::*;